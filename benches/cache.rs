@@ -0,0 +1,36 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use node_resolve::{EntryKind, LruCache, MemoryCache, ResolutionCache};
+use std::path::PathBuf;
+use std::thread;
+
+const THREADS: usize = 16;
+const PATHS: usize = 1024;
+
+/// Hammer a cache from many threads at once, like a parallel bundler does.
+fn stress(cache: &dyn ResolutionCache, paths: &[PathBuf]) {
+    thread::scope(|scope| {
+        for offset in 0..THREADS {
+            scope.spawn(move || {
+                for path in paths.iter().cycle().skip(offset * 64).take(PATHS * 4) {
+                    if cache.get_entry(path).is_none() {
+                        cache.set_entry(path, EntryKind::File);
+                    }
+                }
+            });
+        }
+    });
+}
+
+fn concurrent_caches(c: &mut Criterion) {
+    let paths: Vec<PathBuf> = (0..PATHS)
+        .map(|i| PathBuf::from(format!("/project/node_modules/pkg-{}/index.js", i)))
+        .collect();
+
+    let memory = MemoryCache::default();
+    c.bench_function("memory cache, 16 threads", |b| b.iter(|| stress(&memory, &paths)));
+    let lru = LruCache::new(PATHS * 4);
+    c.bench_function("lru cache, 16 threads", |b| b.iter(|| stress(&lru, &paths)));
+}
+
+criterion_group!(benches, concurrent_caches);
+criterion_main!(benches);
@@ -0,0 +1,33 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use node_resolve::Resolver;
+use std::path::PathBuf;
+
+fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+}
+
+/// Candidate generation: most of the time is spent building paths with extensions appended.
+fn candidates(c: &mut Criterion) {
+    let resolver = Resolver::default()
+        .extensions(&[".mjs", ".cjs", ".ts", ".tsx", ".js", ".json", ".node"])
+        .with_basedir(fixtures());
+
+    c.bench_function("extension", |b| {
+        b.iter(|| resolver.resolve(black_box("./extensions/js-file")))
+    });
+    c.bench_function("index", |b| {
+        b.iter(|| resolver.resolve(black_box("./node-modules/package-json/node_modules/dep/lib")))
+    });
+    c.bench_function("not found", |b| {
+        b.iter(|| resolver.resolve(black_box("./extensions/missing")))
+    });
+}
+
+fn packages(c: &mut Criterion) {
+    let resolver = Resolver::default().with_basedir(fixtures().join("node-modules/package-json"));
+
+    c.bench_function("package main", |b| b.iter(|| resolver.resolve(black_box("dep"))));
+}
+
+criterion_group!(benches, candidates, packages);
+criterion_main!(benches);
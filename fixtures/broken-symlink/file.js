@@ -0,0 +1 @@
+missing-target.js
\ No newline at end of file
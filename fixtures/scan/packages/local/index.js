@@ -0,0 +1,2 @@
+import React from 'react';
+import { helper } from '../../src/util';
@@ -0,0 +1,4 @@
+import React from 'react';
+import { helper } from './util';
+import local from 'local';
+import styles from './styles.css';
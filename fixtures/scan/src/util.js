@@ -0,0 +1,5 @@
+import { jsx } from 'react/jsx-runtime';
+import { Button } from '@scope/ui';
+import missing from 'missing-package';
+import fs from 'fs';
+import React from 'react';
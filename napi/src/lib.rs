@@ -0,0 +1,62 @@
+//! Node.js bindings for node-resolve, so JavaScript tools can resolve specifiers in-process.
+//!
+//! This crate lives next to the resolver so the options it accepts stay in sync with the
+//! [`Resolver`] methods. Build it with `npm run build` in this folder, which creates an
+//! `index.js` file that loads the native module:
+//!
+//! ```js
+//! const { resolveSync } = require('node-resolve-napi');
+//!
+//! resolveSync('react', { basedir: __dirname });
+//! resolveSync('./styles', { extensions: ['.css', '.scss'] });
+//! resolveSync('preact', { conditions: ['browser', 'import'] });
+//! ```
+
+use napi::{Error, Result, Status};
+use napi_derive::napi;
+use node_resolve::Resolver;
+use std::env;
+use std::path::PathBuf;
+
+/// Options for `resolveSync()`. Missing options use the defaults of [`Resolver::default`].
+#[napi(object)]
+pub struct ResolveOptions {
+    /// The directory to resolve from. The default is the working directory of the process.
+    pub basedir: Option<String>,
+    /// The file extensions to try, in order. See [`Resolver::extensions`].
+    pub extensions: Option<Vec<String>>,
+    /// The conditions to use in package.json "exports" fields. See [`Resolver::conditions`].
+    pub conditions: Option<Vec<String>>,
+}
+
+/// Resolve `specifier` like `require.resolve()`, and return the path of the file.
+///
+/// Throws an error with the message of the resolver if the specifier can not be resolved.
+#[napi]
+pub fn resolve_sync(specifier: String, options: Option<ResolveOptions>) -> Result<String> {
+    let options = options.unwrap_or(ResolveOptions {
+        basedir: None,
+        extensions: None,
+        conditions: None,
+    });
+
+    let mut resolver = Resolver::default();
+    if let Some(extensions) = options.extensions {
+        resolver = resolver.extensions(extensions);
+    }
+    if let Some(conditions) = options.conditions {
+        resolver = resolver.conditions(conditions);
+    }
+    let basedir = match options.basedir {
+        Some(basedir) => PathBuf::from(basedir),
+        None => env::current_dir().map_err(|err| Error::from_reason(err.to_string()))?,
+    };
+
+    let path = resolver
+        .with_basedir(basedir)
+        .resolve(&specifier)
+        .map_err(|err| Error::new(Status::GenericFailure, err.to_string()))?;
+    path.into_os_string()
+        .into_string()
+        .map_err(|_| Error::from_reason("the resolved path is not valid UTF-8"))
+}
@@ -0,0 +1,221 @@
+//! Finding every file that a specifier could refer to, to catch ambiguous imports.
+
+use crate::pure::find_file;
+use crate::{
+    first_occurrences, is_directory_specifier, is_path_specifier, parse_specifier, Context,
+    EntryKind, Error, ProbeOrder, Resolver,
+};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+impl Resolver {
+    /// Get every file that `target` could refer to with the current options, in the order the
+    /// resolver prefers them. The first one is the result of [`Resolver::resolve`].
+    ///
+    /// A specifier like `./config` can refer to `./config.js` and to `./config.json` if both
+    /// exist, and to the index file of a `./config` directory. Only the first of those is used,
+    /// so adding or renaming a file can silently change what is imported. Lint rules can use
+    /// this to ask for a more specific import.
+    ///
+    /// Bare specifiers are looked at in the package that they resolve to. Packages with an
+    /// "exports" field map each subpath to one file, so they are never ambiguous. Builtin
+    /// modules, `#` imports, directory specifiers ending in `/`, and fully specified
+    /// resolution also have a single interpretation. If `target` can not be resolved, the
+    /// error of [`Resolver::resolve`] is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().with_basedir(PathBuf::from("/project/src"));
+    /// let interpretations = resolver.interpretations("./config")?;
+    /// if interpretations.len() > 1 {
+    ///     eprintln!("./config is ambiguous, it could be any of {:?}", interpretations);
+    /// }
+    /// ```
+    pub fn interpretations(&self, target: &str) -> Result<Vec<PathBuf>, Error> {
+        if let Some(scoped) = self.scoped_resolver() {
+            return scoped.interpretations(target);
+        }
+        let resolved = self.resolve_with_context(target, &mut Context::default())?;
+
+        let target = &*self.expand(target)?;
+        let target = match target.strip_prefix('~') {
+            Some(bare) if self.tilde_prefix && !bare.starts_with('/') => bare,
+            _ => target,
+        };
+        if self.runtime.is_builtin(target)
+            || target.starts_with('#')
+            || is_directory_specifier(target)
+            || self.fully_specified
+        {
+            return Ok(vec![self.output_path(resolved)]);
+        }
+
+        let mut ctx = Context::default();
+        let interpretations = if is_path_specifier(target) {
+            let basedir = if target.starts_with('/') {
+                Cow::Owned(self.get_root())
+            } else {
+                self.enter_directory(self.get_basedir()?, &mut ctx)
+            };
+            self.path_interpretations(&basedir.join(target), self.probe_order, &mut ctx)
+        } else {
+            self.package_interpretations(target, &mut ctx)
+        };
+
+        // Make sure that the result comes first, even where it was found in another way, for
+        // example in a DefinitelyTyped package.
+        let interpretations = interpretations
+            .iter()
+            .filter_map(|path| self.normalize(path).ok())
+            .filter(|path| *path != resolved);
+        let interpretations =
+            first_occurrences(Some(resolved.clone()).into_iter().chain(interpretations));
+        Ok(interpretations
+            .into_iter()
+            .map(|path| self.output_path(path))
+            .collect())
+    }
+
+    /// Check if `target` could refer to more than one file. See [`Resolver::interpretations`].
+    pub fn is_ambiguous(&self, target: &str) -> Result<bool, Error> {
+        Ok(self.interpretations(target)?.len() > 1)
+    }
+
+    /// Find every file and directory that a path can refer to, in the order that
+    /// `resolve_path` tries them.
+    fn path_interpretations(
+        &self,
+        path: &Path,
+        probe_order: ProbeOrder,
+        ctx: &mut Context,
+    ) -> Vec<PathBuf> {
+        let mut found = Vec::new();
+        for kind in probe_order.kinds() {
+            if *kind == EntryKind::Directory {
+                if let Ok(index) = self.resolve_as_directory(path, ctx) {
+                    found.push(index);
+                }
+                continue;
+            }
+            if let Some(path) = self.try_typescript_file(path, ctx) {
+                found.push(path);
+            }
+            if self.is_file(path) {
+                found.push(path.to_path_buf());
+            }
+            let dir = path.parent().unwrap_or(path);
+            let extensions = self.extensions_for(dir, ctx);
+            find_file(path, &extensions, self.partials, |candidate| {
+                if self.is_file(candidate) {
+                    found.push(candidate.to_path_buf());
+                }
+                // Keep going to find every candidate that exists.
+                false
+            });
+        }
+        found
+    }
+
+    /// Find every file that a bare specifier can refer to in the first package that has it.
+    fn package_interpretations(&self, target: &str, ctx: &mut Context) -> Vec<PathBuf> {
+        let specifier = parse_specifier(target);
+        let package_name = specifier.package_name.unwrap_or(target);
+        let exports_apply = !self.exports_bypass.iter().any(|name| name == package_name);
+
+        let located = match self.overrides.iter().find(|(name, _)| name == package_name) {
+            Some((_, pkg_dir)) => {
+                let path = match specifier.subpath {
+                    Some(subpath) => pkg_dir.join(subpath),
+                    None => pkg_dir.clone(),
+                };
+                Some((pkg_dir.clone(), path))
+            }
+            None => {
+                let mut located = None;
+                let _ = self.walk_node_modules(ctx, |node_modules, ctx| {
+                    let pkg_dir = node_modules.join(package_name);
+                    let path = node_modules.join(target);
+                    let found = self.find_in_package(&pkg_dir, &path, target, ctx)?;
+                    if found.is_some() {
+                        located = Some((pkg_dir, path));
+                    }
+                    Ok(found)
+                });
+                located
+            }
+        };
+
+        match located {
+            Some((pkg_dir, path)) if !(exports_apply && self.has_exports(&pkg_dir, ctx)) => {
+                let probe_order = self.package_probe_order(&specifier);
+                if specifier.subpath.is_some() && !self.nested_package_json {
+                    let flat = self.clone().use_package_main(false);
+                    flat.path_interpretations(&path, probe_order, ctx)
+                } else {
+                    self.path_interpretations(&path, probe_order, ctx)
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Check if the package in `pkg_dir` has an "exports" field.
+    fn has_exports(&self, pkg_dir: &Path, ctx: &mut Context) -> bool {
+        let pkg_dir = self.enter_directory(pkg_dir, ctx);
+        let pkg_path = pkg_dir.join("package.json");
+        self.use_package_json
+            && self.is_file(&pkg_path)
+            && self
+                .load_package_json(&pkg_path, ctx)
+                .is_ok_and(|pkg| !matches!(pkg.get("exports"), None | Some(Value::Null)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Resolver;
+    use std::env;
+
+    #[test]
+    fn finds_ambiguous_specifiers() {
+        let root = env::current_dir().unwrap().join("fixtures/ambiguous");
+        let resolver = Resolver::default().with_basedir(root.clone());
+
+        assert_eq!(
+            vec![
+                root.join("config.js"),
+                root.join("config.json"),
+                root.join("config/index.js"),
+            ],
+            resolver.interpretations("./config").unwrap()
+        );
+        assert!(resolver.is_ambiguous("./config").unwrap());
+        assert_eq!(
+            vec![root.join("config.json")],
+            resolver.interpretations("./config.json").unwrap()
+        );
+        assert!(!resolver.is_ambiguous("./single").unwrap());
+        assert!(!resolver.is_ambiguous("./config/").unwrap());
+        assert!(!resolver.is_ambiguous("fs").unwrap());
+        assert!(resolver.interpretations("./missing").is_err());
+
+        let pkg = root.join("node_modules/pkg");
+        assert_eq!(
+            vec![pkg.join("lib.js"), pkg.join("lib.json")],
+            resolver.interpretations("pkg/lib").unwrap()
+        );
+        assert!(!resolver.is_ambiguous("pkg").unwrap());
+        assert!(!resolver.is_ambiguous("exported/lib").unwrap());
+
+        // Only the configured extensions count.
+        let resolver = resolver.extensions(vec![".json"]);
+        assert_eq!(
+            vec![root.join("config.json")],
+            resolver.interpretations("./config").unwrap()
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Executable scripts declared in the package.json "bin" field.
+
+use crate::{Error, InternalError, Resolver};
+use serde_json::Value;
+use std::io::{Error as IOError, ErrorKind as IOErrorKind};
+use std::path::PathBuf;
+
+impl Resolver {
+    /// Find the script for the command that has the same name as a package, like `eslint` for
+    /// the eslint package. For scoped packages, the command name is the part after the scope.
+    ///
+    /// The package is looked up in node_modules folders like other bare specifiers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().with_basedir(PathBuf::from("/path/to/project"));
+    /// let eslint = resolver.resolve_bin("eslint");
+    /// ```
+    pub fn resolve_bin(&self, package: &str) -> Result<PathBuf, Error> {
+        let bin_name = package.rsplit('/').next().unwrap_or(package);
+        self.resolve_bin_from(package, bin_name)
+    }
+
+    /// Find the script for the command `bin_name` in `package`, like `tsc` in the typescript
+    /// package.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().with_basedir(PathBuf::from("/path/to/project"));
+    /// let tsc = resolver.resolve_bin_from("typescript", "tsc");
+    /// ```
+    pub fn resolve_bin_from(&self, package: &str, bin_name: &str) -> Result<PathBuf, Error> {
+        let pkg_dir = self.find_directory(package)?;
+        let pkg_path = pkg_dir.join("package.json");
+        let not_found = || Error::BinNotFound {
+            package: package.to_string(),
+            bin: bin_name.to_string(),
+        };
+        if !self.is_file(&pkg_path) {
+            return Err(not_found());
+        }
+
+        let pkg = self.read_package_json(&pkg_path)?;
+        let own_name = pkg["name"]
+            .as_str()
+            .map(|name| name.rsplit('/').next().unwrap_or(name));
+        let script = match &pkg["bin"] {
+            // A single script is named after the package.
+            Value::String(script) if own_name.unwrap_or(package) == bin_name => script,
+            Value::Object(scripts) => match scripts.get(bin_name).and_then(Value::as_str) {
+                Some(script) => script,
+                None => return Err(not_found()),
+            },
+            _ => return Err(not_found()),
+        };
+
+        let path = pkg_dir.join(script);
+        if !self.is_file(&path) {
+            return Err(IOError::new(IOErrorKind::NotFound, "Not Found").into());
+        }
+        self.normalize(&path)
+            .map(|path| self.output_path(path))
+            .map_err(InternalError::into_public)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Resolver};
+    use std::env;
+    use std::path::PathBuf;
+
+    #[test]
+    fn resolves_bin_scripts() {
+        let root = env::current_dir().unwrap().join("fixtures/bin");
+        let resolver = Resolver::default().with_basedir(root.clone());
+
+        assert_eq!(
+            root.join("node_modules/single/cli.js"),
+            resolver.resolve_bin("single").unwrap()
+        );
+        assert_eq!(
+            root.join("node_modules/@scope/multi/bin/multi.js"),
+            resolver.resolve_bin("@scope/multi").unwrap()
+        );
+        assert_eq!(
+            root.join("node_modules/@scope/multi/bin/other.js"),
+            resolver.resolve_bin_from("@scope/multi", "multi-other").unwrap()
+        );
+        assert!(matches!(
+            resolver.resolve_bin_from("single", "other"),
+            Err(Error::BinNotFound { .. })
+        ));
+        assert!(matches!(
+            resolver.resolve_bin("missing"),
+            Err(Error::NotFound { .. })
+        ));
+
+        let relative = resolver.output_relative_to(root);
+        assert_eq!(
+            Ok(PathBuf::from("node_modules/single/cli.js")),
+            relative.resolve_bin("single")
+        );
+    }
+}
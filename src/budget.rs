@@ -0,0 +1,300 @@
+//! Limits on the work a single resolution may do.
+
+use crate::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Limits on the work done by a single `resolve()` call.
+///
+/// Embedders like editors and sandboxes can use this to bound the worst-case latency of a
+/// resolution. When a limit is exceeded, resolution fails with [`Error::BudgetExceeded`], or
+/// with [`Error::Timeout`] for the [timeout](Budget::timeout). By default, nothing is limited.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::{Budget, Resolver};
+/// use std::time::Duration;
+///
+/// let resolver = Resolver::default().budget(
+///     Budget::default()
+///         .max_ascent(8)
+///         .max_package_json_reads(32)
+///         .timeout(Duration::from_millis(200))
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Budget {
+    max_ascent: Option<usize>,
+    max_symlink_follows: Option<usize>,
+    max_package_json_reads: Option<usize>,
+    timeout: Option<Duration>,
+}
+
+impl Budget {
+    /// Limit how many parent directories of the basedir are searched for node_modules folders.
+    /// Consumes the Budget instance.
+    pub fn max_ascent(self, max_ascent: usize) -> Self {
+        Budget {
+            max_ascent: Some(max_ascent),
+            ..self
+        }
+    }
+
+    /// Limit how many symlinked directories are expanded while resolving. This only applies to
+    /// [`SymlinkStrategy::RealpathDirectories`](crate::SymlinkStrategy::RealpathDirectories).
+    /// Consumes the Budget instance.
+    pub fn max_symlink_follows(self, max_symlink_follows: usize) -> Self {
+        Budget {
+            max_symlink_follows: Some(max_symlink_follows),
+            ..self
+        }
+    }
+
+    /// Limit how many package.json files are read, including ones that come from the cache.
+    /// Consumes the Budget instance.
+    pub fn max_package_json_reads(self, max_package_json_reads: usize) -> Self {
+        Budget {
+            max_package_json_reads: Some(max_package_json_reads),
+            ..self
+        }
+    }
+
+    /// Limit how long a single resolution may take. Consumes the Budget instance.
+    ///
+    /// The clock starts when resolution starts. The time is checked before every path that is
+    /// looked up and every package.json file that is read, so a resolution stops soon after
+    /// the deadline. Other accesses, like expanding symlinks in the result, are not checked,
+    /// and a single slow access, like a stat on a network mount that hangs, is not
+    /// interrupted. Once the deadline has passed, the resolution fails with
+    /// [`Error::Timeout`], which lists the paths that were tried so far.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Budget {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+}
+
+/// A limit in a [`Budget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// See [`Budget::max_ascent`].
+    Ascent,
+    /// See [`Budget::max_symlink_follows`].
+    SymlinkFollows,
+    /// See [`Budget::max_package_json_reads`].
+    PackageJsonReads,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Ascent => write!(f, "maximum ascent"),
+            Limit::SymlinkFollows => write!(f, "maximum number of symlink follows"),
+            Limit::PackageJsonReads => write!(f, "maximum number of package.json reads"),
+        }
+    }
+}
+
+/// The part of the budget that a single resolution has used so far.
+#[derive(Debug, Default)]
+pub(crate) struct BudgetUsage {
+    symlink_follows: usize,
+    package_json_reads: usize,
+    /// The first limit that was exceeded. Once this is set, the resolution fails, even if
+    /// a fallback would find something.
+    exceeded: Option<Limit>,
+    /// When the resolution started, if there is a timeout.
+    started: Option<Instant>,
+    /// How long the resolution had taken when it ran out of time.
+    timed_out: Option<Duration>,
+}
+
+impl BudgetUsage {
+    /// Check that searching the `ascent`th parent directory of the basedir is allowed.
+    pub(crate) fn ascend(&mut self, budget: &Budget, ascent: usize) -> Result<(), Error> {
+        self.check(Limit::Ascent, ascent, budget.max_ascent)
+    }
+
+    /// Record that a symlinked directory was expanded.
+    pub(crate) fn follow_symlink(&mut self, budget: &Budget) -> Result<(), Error> {
+        self.symlink_follows += 1;
+        self.check(
+            Limit::SymlinkFollows,
+            self.symlink_follows,
+            budget.max_symlink_follows,
+        )
+    }
+
+    /// Record that a package.json file is about to be read.
+    pub(crate) fn read_package_json(&mut self, budget: &Budget) -> Result<(), Error> {
+        self.package_json_reads += 1;
+        self.check(
+            Limit::PackageJsonReads,
+            self.package_json_reads,
+            budget.max_package_json_reads,
+        )
+    }
+
+    /// Start the clock for the timeout, if it did not start yet.
+    pub(crate) fn start(&mut self, budget: &Budget) {
+        if budget.timeout.is_some() {
+            self.started.get_or_insert_with(Instant::now);
+        }
+    }
+
+    /// Check if the resolution may access the filesystem again. After the deadline, this
+    /// returns `false`, and the resolution fails once it is done.
+    pub(crate) fn has_time(&mut self, budget: &Budget) -> bool {
+        let timeout = match budget.timeout {
+            Some(timeout) => timeout,
+            None => return true,
+        };
+        if self.timed_out.is_none() {
+            let elapsed = self.started.get_or_insert_with(Instant::now).elapsed();
+            if elapsed >= timeout {
+                self.timed_out = Some(elapsed);
+            }
+        }
+        self.timed_out.is_none()
+    }
+
+    /// Get how long the resolution had taken when it ran out of time, if it did.
+    pub(crate) fn timed_out(&self) -> Option<Duration> {
+        self.timed_out
+    }
+
+    /// Get the first limit that was exceeded, if any.
+    pub(crate) fn exceeded(&self) -> Option<Limit> {
+        self.exceeded
+    }
+
+    fn check(&mut self, limit: Limit, used: usize, max: Option<usize>) -> Result<(), Error> {
+        if self.exceeded.is_none() && max.is_some_and(|max| used > max) {
+            self.exceeded = Some(limit);
+        }
+        match self.exceeded {
+            Some(limit) => Err(Error::BudgetExceeded(limit)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Budget, EntryKind, Error, FileSystem, Limit, Resolver, SnapshotFileSystem,
+        SymlinkStrategy,
+    };
+    use std::env;
+    use std::io::Error as IOError;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    /// A filesystem that takes a while to look up paths with "slow" in their name, like the
+    /// paths on a slow network mount.
+    struct SlowFileSystem(SnapshotFileSystem);
+
+    impl FileSystem for SlowFileSystem {
+        fn entry_kind(&self, path: &Path) -> EntryKind {
+            if path.to_string_lossy().contains("slow") {
+                thread::sleep(Duration::from_millis(150));
+            }
+            self.0.entry_kind(path)
+        }
+
+        fn read_to_string(&self, path: &Path) -> Result<String, IOError> {
+            self.0.read_to_string(path)
+        }
+
+        fn canonicalize(&self, path: &Path) -> Result<PathBuf, IOError> {
+            self.0.canonicalize(path)
+        }
+
+        fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, IOError> {
+            self.0.read_dir(path)
+        }
+    }
+
+    #[test]
+    fn enforces_budget() {
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+
+        let resolver =
+            Resolver::default().with_basedir(fixtures.join("node-modules/package-json"));
+        assert!(resolver.resolve("dep").is_ok());
+        assert!(matches!(
+            resolver
+                .clone()
+                .budget(Budget::default().max_package_json_reads(0))
+                .resolve("dep"),
+            Err(Error::BudgetExceeded(Limit::PackageJsonReads))
+        ));
+        // The package.json file of dep is read to look for "exports" and for "main".
+        assert!(resolver
+            .clone()
+            .budget(Budget::default().max_package_json_reads(2))
+            .resolve("dep")
+            .is_ok());
+
+        let nested =
+            Resolver::default().with_basedir(fixtures.join("node-modules/parent-dir/src"));
+        assert!(nested
+            .clone()
+            .budget(Budget::default().max_ascent(1))
+            .resolve("a")
+            .is_ok());
+        assert!(matches!(
+            nested.budget(Budget::default().max_ascent(0)).resolve("a"),
+            Err(Error::BudgetExceeded(Limit::Ascent))
+        ));
+
+        let symlinks = Resolver::default()
+            .symlinks(SymlinkStrategy::RealpathDirectories)
+            .with_basedir(fixtures.join("symlink-walk/app"));
+        assert!(symlinks.resolve("lib").is_ok());
+        assert!(matches!(
+            symlinks
+                .budget(Budget::default().max_symlink_follows(0))
+                .resolve("lib"),
+            Err(Error::BudgetExceeded(Limit::SymlinkFollows))
+        ));
+    }
+
+    #[test]
+    fn enforces_timeout() {
+        let files = ["/src/main.js", "/src/node_modules/dep/index.js"];
+        let snapshot: SnapshotFileSystem = files.iter().collect();
+        let all_tried = Resolver::default()
+            .filesystem(Arc::new(snapshot.clone()))
+            .with_basedir(PathBuf::from("/src"))
+            .resolve("./slow")
+            .unwrap_err()
+            .tried()
+            .len();
+
+        let resolver = Resolver::default()
+            .filesystem(Arc::new(SlowFileSystem(snapshot)))
+            .with_basedir(PathBuf::from("/src"))
+            .budget(Budget::default().timeout(Duration::from_millis(100)));
+        assert_eq!(Ok(PathBuf::from("/src/main.js")), resolver.resolve("./main"));
+        // The first lookup takes longer than the timeout, so nothing else is tried.
+        match resolver.resolve("./slow") {
+            Err(Error::Timeout { specifier, elapsed, tried }) => {
+                assert_eq!("./slow", specifier);
+                assert!(elapsed >= Duration::from_millis(100));
+                assert!(tried.len() == 1 && tried.len() < all_tried);
+            }
+            other => panic!("expected Timeout, got {:?}", other),
+        }
+        assert!(matches!(
+            resolver
+                .budget(Budget::default().timeout(Duration::ZERO))
+                .resolve("dep"),
+            Err(Error::Timeout { .. })
+        ));
+    }
+}
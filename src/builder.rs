@@ -0,0 +1,504 @@
+//! A non-consuming way to configure `Resolver` instances.
+
+use crate::{
+    normalize_extensions, AscentOrigin, Budget, CaseInsensitiveFileSystem, Environment,
+    FileSystem, LruCache, Observer, ProbeOrder, ResolutionCache, Resolver, Runtime,
+    SpecifierResolution, SymlinkStrategy, Target,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Builds a [`Resolver`] using `&mut self` setters, which makes conditional configuration
+/// easier than the consuming setters on `Resolver`.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::ResolverBuilder;
+///
+/// let mut builder = ResolverBuilder::new();
+/// builder.extensions(&[".js", ".mjs", ".json"]);
+/// if browser {
+///     builder.main_fields(&["browser", "main"]);
+/// }
+/// let resolver = builder.basedir(PathBuf::from(".")).build();
+/// ```
+#[derive(Clone, Default)]
+pub struct ResolverBuilder {
+    resolver: Resolver,
+}
+
+impl ResolverBuilder {
+    /// Create a builder with the default Node.js configuration.
+    pub fn new() -> Self {
+        ResolverBuilder::default()
+    }
+
+    /// Set the directory that specifiers are resolved from.
+    pub fn basedir(&mut self, basedir: PathBuf) -> &mut Self {
+        self.resolver = self.resolver.with_basedir(basedir);
+        self
+    }
+
+    /// Set several directories that specifiers are resolved from, in order. See
+    /// [`Resolver::with_basedirs`].
+    pub fn basedirs(&mut self, basedirs: Vec<PathBuf>) -> &mut Self {
+        self.resolver = self.resolver.with_basedirs(basedirs);
+        self
+    }
+
+    /// Use a different set of extensions. See [`Resolver::extensions`].
+    pub fn extensions<T>(&mut self, extensions: T) -> &mut Self
+    where
+        T: IntoIterator,
+        T::Item: ToString,
+    {
+        self.resolver.extensions = normalize_extensions(extensions).into();
+        self
+    }
+
+    /// Use a different set of main fields. See [`Resolver::main_fields`].
+    pub fn main_fields<T>(&mut self, main_fields: T) -> &mut Self
+    where
+        T: IntoIterator,
+        T::Item: ToString,
+    {
+        self.resolver.main_fields = main_fields
+            .into_iter()
+            .map(|field| field.to_string())
+            .collect();
+        self
+    }
+
+    /// Use a different set of module directory names. See [`Resolver::module_directories`].
+    pub fn module_directories<T>(&mut self, module_directories: T) -> &mut Self
+    where
+        T: IntoIterator,
+        T::Item: ToString,
+    {
+        self.resolver.module_directories = module_directories
+            .into_iter()
+            .map(|name| name.to_string())
+            .collect();
+        self
+    }
+
+    /// Also look up bare specifiers in `paths`. See [`Resolver::module_paths`].
+    pub fn module_paths(&mut self, paths: Vec<PathBuf>) -> &mut Self {
+        self.resolver.module_paths = paths.into();
+        self
+    }
+
+    /// Only search node_modules folders inside the workspace package that contains the
+    /// basedir. See [`Resolver::workspace_packages`].
+    pub fn workspace_packages(&mut self, roots: Vec<PathBuf>) -> &mut Self {
+        self.resolver.workspace_roots = roots.into();
+        self
+    }
+
+    /// Use a different set of module directory names inside `dir`. See
+    /// [`Resolver::module_directories_in`].
+    pub fn module_directories_in<T>(&mut self, dir: PathBuf, module_directories: T) -> &mut Self
+    where
+        T: IntoIterator,
+        T::Item: ToString,
+    {
+        let names = module_directories
+            .into_iter()
+            .map(|name| name.to_string())
+            .collect();
+        let scoped = Arc::make_mut(&mut self.resolver.scoped_module_directories);
+        scoped.retain(|(existing, _)| *existing != dir);
+        scoped.push((dir, names));
+        self
+    }
+
+    /// Choose where node_modules lookups start when the basedir is inside a symlinked folder.
+    /// See [`Resolver::ascent_origin`].
+    pub fn ascent_origin(&mut self, ascent_origin: AscentOrigin) -> &mut Self {
+        self.resolver.ascent_origin = ascent_origin;
+        self
+    }
+
+    /// Choose whether paths are tried as files or as directories first. See
+    /// [`Resolver::probe_order`].
+    pub fn probe_order(&mut self, probe_order: ProbeOrder) -> &mut Self {
+        self.resolver.probe_order = probe_order;
+        self
+    }
+
+    /// Configure whether package.json files are used to find the entry point of a directory.
+    /// See [`Resolver::use_package_main`].
+    pub fn use_package_main(&mut self, use_package_main: bool) -> &mut Self {
+        self.resolver.use_package_main = use_package_main;
+        self
+    }
+
+    /// Configure whether folders inside packages can have their own package.json file. See
+    /// [`Resolver::nested_package_json`].
+    pub fn nested_package_json(&mut self, nested_package_json: bool) -> &mut Self {
+        self.resolver.nested_package_json = nested_package_json;
+        self
+    }
+
+    /// Configure whether package.json files are read at all. See
+    /// [`Resolver::use_package_json`].
+    pub fn use_package_json(&mut self, use_package_json: bool) -> &mut Self {
+        self.resolver.use_package_json = use_package_json;
+        self
+    }
+
+    /// Configure whether package.json files with trailing commas are accepted. See
+    /// [`Resolver::tolerant_package_json`].
+    pub fn tolerant_package_json(&mut self, tolerant_package_json: bool) -> &mut Self {
+        self.resolver.tolerant_package_json = tolerant_package_json;
+        self
+    }
+
+    /// Ignore the "exports" field of some packages. See [`Resolver::bypass_exports`].
+    pub fn bypass_exports<T>(&mut self, packages: T) -> &mut Self
+    where
+        T: IntoIterator,
+        T::Item: ToString,
+    {
+        self.resolver.exports_bypass = packages
+            .into_iter()
+            .map(|package| package.to_string())
+            .collect();
+        self
+    }
+
+    /// Refuse to resolve bare specifiers for the packages that match `patterns`. See
+    /// [`Resolver::deny`].
+    pub fn deny<T>(&mut self, patterns: T) -> &mut Self
+    where
+        T: IntoIterator,
+        T::Item: ToString,
+    {
+        self.resolver.denied_packages = patterns
+            .into_iter()
+            .map(|pattern| pattern.to_string())
+            .collect();
+        self
+    }
+
+    /// Use a different set of conditions for package.json "exports" fields. See
+    /// [`Resolver::conditions`].
+    pub fn conditions<T>(&mut self, conditions: T) -> &mut Self
+    where
+        T: IntoIterator,
+        T::Item: ToString,
+    {
+        self.resolver.conditions = conditions
+            .into_iter()
+            .map(|condition| condition.to_string())
+            .collect();
+        self
+    }
+
+    /// Configure whether symlinks should be preserved. See [`Resolver::preserve_symlinks`].
+    pub fn preserve_symlinks(&mut self, preserve_symlinks: bool) -> &mut Self {
+        self.resolver.symlinks = if preserve_symlinks {
+            SymlinkStrategy::Preserve
+        } else {
+            SymlinkStrategy::RealpathResult
+        };
+        self
+    }
+
+    /// Configure how symlinks are handled. See [`Resolver::symlinks`].
+    pub fn symlinks(&mut self, symlinks: SymlinkStrategy) -> &mut Self {
+        self.resolver.symlinks = symlinks;
+        self
+    }
+
+    /// Configure whether the extension order depends on the package `"type"`. See
+    /// [`Resolver::package_type_extensions`].
+    pub fn package_type_extensions(&mut self, package_type_extensions: bool) -> &mut Self {
+        self.resolver.package_type_extensions = package_type_extensions;
+        self
+    }
+
+    /// Configure whether relative and absolute specifiers must point to a file exactly. See
+    /// [`Resolver::fully_specified`].
+    pub fn fully_specified(&mut self, fully_specified: bool) -> &mut Self {
+        self.resolver.fully_specified = fully_specified;
+        self
+    }
+
+    /// Configure how ES module specifiers are resolved. See [`Resolver::specifier_resolution`].
+    pub fn specifier_resolution(
+        &mut self,
+        specifier_resolution: SpecifierResolution,
+    ) -> &mut Self {
+        self.resolver.specifier_resolution = specifier_resolution;
+        self.resolver.fully_specified = specifier_resolution == SpecifierResolution::Explicit;
+        self
+    }
+
+    /// Configure whether resolved .json files are marked as JSON modules. See
+    /// [`Resolver::json_modules`].
+    pub fn json_modules(&mut self, json_modules: bool) -> &mut Self {
+        self.resolver.json_modules = json_modules;
+        self
+    }
+
+    /// Configure whether `~/` and `${VAR}` are expanded in absolute specifiers. See
+    /// [`Resolver::expand_env`].
+    pub fn expand_env(&mut self, expand_env: bool) -> &mut Self {
+        self.resolver.expand_env = expand_env;
+        self
+    }
+
+    /// Configure whether relative specifiers may reach into other packages in node_modules
+    /// folders. See [`Resolver::strict_package_boundaries`].
+    pub fn strict_package_boundaries(&mut self, strict_package_boundaries: bool) -> &mut Self {
+        self.resolver.strict_package_boundaries = strict_package_boundaries;
+        self
+    }
+
+    /// Configure whether folder mappings ending in `/` in the "exports" and "imports" fields are
+    /// supported. See [`Resolver::folder_exports`].
+    pub fn folder_exports(&mut self, folder_exports: bool) -> &mut Self {
+        self.resolver.folder_exports = folder_exports;
+        self
+    }
+
+    /// Configure whether the legacy package.json `directories.lib` field is used. See
+    /// [`Resolver::directories_lib`].
+    pub fn directories_lib(&mut self, directories_lib: bool) -> &mut Self {
+        self.resolver.directories_lib = directories_lib;
+        self
+    }
+
+    /// Configure whether `link:` and `portal:` dependencies are resolved from their
+    /// package.json declaration. See [`Resolver::link_dependencies`].
+    pub fn link_dependencies(&mut self, link_dependencies: bool) -> &mut Self {
+        self.resolver.link_dependencies = link_dependencies;
+        self
+    }
+
+    /// Configure whether `file:` dependencies are resolved from their package.json
+    /// declaration. See [`Resolver::file_dependencies`].
+    pub fn file_dependencies(&mut self, file_dependencies: bool) -> &mut Self {
+        self.resolver.file_dependencies = file_dependencies;
+        self
+    }
+
+    /// Configure whether JavaScript file extensions also find the TypeScript files that
+    /// compile to them. See [`Resolver::typescript_extensions`].
+    pub fn typescript_extensions(&mut self, typescript_extensions: bool) -> &mut Self {
+        self.resolver.typescript_extensions = typescript_extensions;
+        self
+    }
+
+    /// Configure whether packages without typings are resolved in their `@types` package. See
+    /// [`Resolver::types_packages`].
+    pub fn types_packages(&mut self, types_packages: bool) -> &mut Self {
+        self.resolver.types_packages = types_packages;
+        self
+    }
+
+    /// Configure whether a leading `~` marks a bare specifier. See [`Resolver::tilde_prefix`].
+    pub fn tilde_prefix(&mut self, tilde_prefix: bool) -> &mut Self {
+        self.resolver.tilde_prefix = tilde_prefix;
+        self
+    }
+
+    /// Configure whether partial file names are tried. See [`Resolver::partials`].
+    pub fn partials(&mut self, partials: bool) -> &mut Self {
+        self.resolver.partials = partials;
+        self
+    }
+
+    /// Cache filesystem lookups. See [`Resolver::cache`].
+    pub fn cache(&mut self, cache: Arc<dyn ResolutionCache>) -> &mut Self {
+        self.resolver.cache = Some(cache);
+        self
+    }
+
+    /// Cache filesystem lookups in a bounded cache. See [`Resolver::cache_capacity`].
+    pub fn cache_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.cache(Arc::new(LruCache::new(capacity)))
+    }
+
+    /// Use a different filesystem. See [`Resolver::filesystem`].
+    pub fn filesystem(&mut self, fs: Arc<dyn FileSystem>) -> &mut Self {
+        self.resolver.fs = fs;
+        if self.resolver.case_insensitive_fs.take().is_some() {
+            self.case_insensitive(true);
+        }
+        self
+    }
+
+    /// Configure whether files and folders are found regardless of the case of their names.
+    /// See [`Resolver::case_insensitive`].
+    pub fn case_insensitive(&mut self, case_insensitive: bool) -> &mut Self {
+        let fs = match self.resolver.case_insensitive_fs.take() {
+            Some(case_insensitive_fs) => Arc::clone(case_insensitive_fs.inner()),
+            None => Arc::clone(&self.resolver.fs),
+        };
+        if case_insensitive {
+            let case_insensitive_fs = Arc::new(CaseInsensitiveFileSystem::new(fs));
+            self.resolver.fs = case_insensitive_fs.clone();
+            self.resolver.case_insensitive_fs = Some(case_insensitive_fs);
+        } else {
+            self.resolver.fs = fs;
+        }
+        self
+    }
+
+    /// Use a different environment for environment variables and the current working
+    /// directory. See [`Resolver::environment`].
+    pub fn environment(&mut self, environment: Arc<dyn Environment>) -> &mut Self {
+        self.resolver.environment = environment;
+        self
+    }
+
+    /// Configure whether the folders in the `NODE_PATH` environment variable are searched for
+    /// packages. See [`Resolver::node_path`].
+    pub fn node_path(&mut self, node_path: bool) -> &mut Self {
+        self.resolver.node_path = node_path;
+        self
+    }
+
+    /// Return resolved paths relative to `root`. See [`Resolver::output_relative_to`].
+    pub fn output_relative_to(&mut self, root: PathBuf) -> &mut Self {
+        self.resolver.output_root = Some(root);
+        self
+    }
+
+    /// Report every filesystem access to an observer. See [`Resolver::observer`].
+    pub fn observer(&mut self, observer: Arc<dyn Observer>) -> &mut Self {
+        self.resolver.observer = Some(observer);
+        self
+    }
+
+    /// Set the JavaScript runtime that determines which specifiers are builtin modules. See
+    /// [`Resolver::target_runtime`].
+    pub fn target_runtime(&mut self, runtime: Runtime) -> &mut Self {
+        self.resolver.runtime = runtime;
+        self
+    }
+
+    /// Configure the resolver for the environment that resolved modules will run in. See
+    /// [`Resolver::target`].
+    pub fn target(&mut self, target: Target) -> &mut Self {
+        self.extensions(target.extensions())
+            .main_fields(target.main_fields())
+            .target_runtime(target.runtime());
+        self.resolver.conditions = target.conditions(&self.resolver.conditions).into();
+        self
+    }
+
+    /// Replace specifiers that match `pattern` with `replacement` before resolving them. See
+    /// [`Resolver::rewrite`].
+    #[cfg(feature = "regex")]
+    pub fn rewrite<S: ToString>(&mut self, pattern: regex::Regex, replacement: S) -> &mut Self {
+        Arc::make_mut(&mut self.resolver.rewrites).push((pattern, replacement.to_string()));
+        self
+    }
+
+    /// Configure whether the hottest packages and the slowest resolutions are recorded. See
+    /// [`Resolver::detailed_stats`].
+    pub fn detailed_stats(&mut self, detailed_stats: bool) -> &mut Self {
+        self.resolver.detailed_stats = detailed_stats;
+        self
+    }
+
+    /// Limit the work that a single resolution may do. See [`Resolver::budget`].
+    pub fn budget(&mut self, budget: Budget) -> &mut Self {
+        self.resolver.budget = budget;
+        self
+    }
+
+    /// Configure how many times a filesystem operation is retried after a transient error. See
+    /// [`Resolver::io_retries`].
+    pub fn io_retries(&mut self, io_retries: usize) -> &mut Self {
+        self.resolver.io_retries = io_retries;
+        self
+    }
+
+    /// Configure whether a missing permission to look at a path fails the resolution. See
+    /// [`Resolver::strict_permissions`].
+    pub fn strict_permissions(&mut self, strict_permissions: bool) -> &mut Self {
+        self.resolver.strict_permissions = strict_permissions;
+        self
+    }
+
+    /// Configure whether expanded symlinks keep the `\\?\` prefix on Windows. See
+    /// [`Resolver::verbatim_paths`].
+    pub fn verbatim_paths(&mut self, verbatim_paths: bool) -> &mut Self {
+        self.resolver.verbatim_paths = verbatim_paths;
+        self
+    }
+
+    /// Use a different resolver for basedirs inside `dir`. See [`Resolver::scope`].
+    pub fn scope(&mut self, dir: PathBuf, resolver: Resolver) -> &mut Self {
+        let scopes = Arc::make_mut(&mut self.resolver.scopes);
+        scopes.retain(|(existing, _)| *existing != dir);
+        scopes.push((dir, resolver));
+        self
+    }
+
+    /// Always resolve the package `name` from `dir`. See [`Resolver::override_package`].
+    pub fn override_package<S: ToString>(&mut self, name: S, dir: PathBuf) -> &mut Self {
+        let name = name.to_string();
+        let overrides = Arc::make_mut(&mut self.resolver.overrides);
+        overrides.retain(|(existing, _)| *existing != name);
+        overrides.push((name, dir));
+        self
+    }
+
+    /// Create a `Resolver` with the current configuration. The builder can be reused
+    /// afterwards.
+    pub fn build(&self) -> Resolver {
+        self.resolver.clone()
+    }
+}
+
+impl From<Resolver> for ResolverBuilder {
+    /// Start from the configuration of an existing resolver, such as one of the presets.
+    fn from(resolver: Resolver) -> Self {
+        ResolverBuilder { resolver }
+    }
+}
+
+impl Resolver {
+    /// Create a builder with the default Node.js configuration.
+    pub fn builder() -> ResolverBuilder {
+        ResolverBuilder::new()
+    }
+
+    /// Apply a builder setter to this resolver. This backs the consuming setters.
+    pub(crate) fn configure<F>(self, configure: F) -> Self
+    where
+        F: FnOnce(&mut ResolverBuilder),
+    {
+        let mut builder = ResolverBuilder::from(self);
+        configure(&mut builder);
+        builder.resolver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_conditionally() {
+        let fixtures = std::env::current_dir().unwrap().join("fixtures");
+
+        let mut builder = Resolver::builder();
+        builder.basedir(fixtures.clone());
+        let plain = builder.build();
+        builder.extensions(&["mjs"]);
+        let mjs = builder.build();
+
+        assert!(plain.resolve("./extensions/module").is_err());
+        assert_eq!(
+            fixtures.join("extensions/module.mjs"),
+            mjs.resolve("./extensions/module").unwrap()
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! Caching of filesystem lookups.
+//!
+//! Resolving many specifiers checks the same files and reads the same package.json files over
+//! and over. A [`ResolutionCache`] remembers those results. Only facts about the filesystem are
+//! cached, not resolution results, so a cache can be shared by resolvers with different options.
+
+use crate::Resolver;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::iter;
+use std::thread::{self, JoinHandle};
+
+/// What kind of entry exists at a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    /// The path is a file, or a symlink to a file.
+    File,
+    /// The path is a directory, or a symlink to a directory.
+    Directory,
+    /// Nothing usable exists at the path.
+    Missing,
+}
+
+/// A cache for filesystem lookups done by a resolver.
+///
+/// Implement this trait to store lookups somewhere else than in memory, or to bound the memory
+/// they use. Implementations are shared between threads, so they use interior mutability.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::{MemoryCache, Resolver};
+/// use std::sync::Arc;
+///
+/// let resolver = Resolver::default().cache(Arc::new(MemoryCache::default()));
+/// ```
+pub trait ResolutionCache: Send + Sync {
+    /// Get the kind of entry at `path`, if it is known.
+    fn get_entry(&self, path: &Path) -> Option<EntryKind>;
+    /// Remember the kind of entry at `path`.
+    fn set_entry(&self, path: &Path, kind: EntryKind);
+    /// Get the parsed contents of the package.json file at `path`, if it is known. Files that
+    /// could not be read or parsed are never stored.
+    fn get_package_json(&self, path: &Path) -> Option<Arc<Value>>;
+    /// Remember the parsed contents of the package.json file at `path`.
+    fn set_package_json(&self, path: &Path, pkg: Arc<Value>);
+    /// Forget everything.
+    fn clear(&self);
+    /// Forget what is known about `path`, and about the paths inside it if it is a directory.
+    /// The default implementation forgets everything.
+    fn forget(&self, path: &Path) {
+        let _ = path;
+        self.clear();
+    }
+    /// Get the number of entries that were thrown out to make room for new ones. Unbounded
+    /// caches never evict anything.
+    fn evictions(&self) -> u64 {
+        0
+    }
+}
+
+/// The number of shards in concurrent caches.
+const SHARDS: usize = 16;
+
+/// The smallest number of entries per shard in an [`LruCache`]. Smaller caches use fewer
+/// shards, so their eviction order stays close to the global LRU order.
+const MIN_LRU_SHARD_CAPACITY: usize = 256;
+
+/// An unbounded in-memory [`ResolutionCache`].
+///
+/// The cache is split into shards by path, so threads that resolve at the same time rarely
+/// wait for each other.
+#[derive(Debug)]
+pub struct MemoryCache {
+    entries: Sharded<RwLock<HashMap<PathBuf, EntryKind>>>,
+    package_jsons: Sharded<RwLock<HashMap<PathBuf, Arc<Value>>>>,
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        MemoryCache {
+            entries: Sharded::new(SHARDS, RwLock::default),
+            package_jsons: Sharded::new(SHARDS, RwLock::default),
+        }
+    }
+}
+
+impl ResolutionCache for MemoryCache {
+    fn get_entry(&self, path: &Path) -> Option<EntryKind> {
+        self.entries.get(path).read().unwrap().get(path).copied()
+    }
+
+    fn set_entry(&self, path: &Path, kind: EntryKind) {
+        self.entries
+            .get(path)
+            .write()
+            .unwrap()
+            .insert(path.to_path_buf(), kind);
+    }
+
+    fn get_package_json(&self, path: &Path) -> Option<Arc<Value>> {
+        self.package_jsons.get(path).read().unwrap().get(path).cloned()
+    }
+
+    fn set_package_json(&self, path: &Path, pkg: Arc<Value>) {
+        self.package_jsons
+            .get(path)
+            .write()
+            .unwrap()
+            .insert(path.to_path_buf(), pkg);
+    }
+
+    fn clear(&self) {
+        for shard in self.entries.iter() {
+            shard.write().unwrap().clear();
+        }
+        for shard in self.package_jsons.iter() {
+            shard.write().unwrap().clear();
+        }
+    }
+
+    fn forget(&self, path: &Path) {
+        for shard in self.entries.iter() {
+            shard.write().unwrap().retain(|key, _| !key.starts_with(path));
+        }
+        for shard in self.package_jsons.iter() {
+            shard.write().unwrap().retain(|key, _| !key.starts_with(path));
+        }
+    }
+}
+
+/// A [`ResolutionCache`] that keeps a bounded number of entries in memory.
+///
+/// At most `capacity` file lookups and `capacity` package.json files are kept. When the cache
+/// is full, the least recently used entry is evicted. This keeps memory use in check for long
+/// running processes like language servers.
+///
+/// Large caches are split into shards by path, each with their own capacity and LRU order, so
+/// threads that resolve at the same time rarely wait for each other.
+#[derive(Debug)]
+pub struct LruCache {
+    entries: Sharded<Mutex<Lru<EntryKind>>>,
+    package_jsons: Sharded<Mutex<Lru<Arc<Value>>>>,
+    evictions: AtomicU64,
+}
+
+impl LruCache {
+    /// Create a cache that holds up to `capacity` entries of each kind.
+    pub fn new(capacity: usize) -> Self {
+        let shards = (capacity / MIN_LRU_SHARD_CAPACITY).clamp(1, SHARDS);
+        let shard_capacity = capacity / shards;
+        LruCache {
+            entries: Sharded::new(shards, || Mutex::new(Lru::new(shard_capacity))),
+            package_jsons: Sharded::new(shards, || Mutex::new(Lru::new(shard_capacity))),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    fn count_evictions(&self, evicted: usize) {
+        self.evictions.fetch_add(evicted as u64, Ordering::Relaxed);
+    }
+}
+
+impl ResolutionCache for LruCache {
+    fn get_entry(&self, path: &Path) -> Option<EntryKind> {
+        self.entries.get(path).lock().unwrap().get(path).copied()
+    }
+
+    fn set_entry(&self, path: &Path, kind: EntryKind) {
+        let evicted = self.entries.get(path).lock().unwrap().insert(path, kind);
+        self.count_evictions(evicted);
+    }
+
+    fn get_package_json(&self, path: &Path) -> Option<Arc<Value>> {
+        self.package_jsons.get(path).lock().unwrap().get(path).cloned()
+    }
+
+    fn set_package_json(&self, path: &Path, pkg: Arc<Value>) {
+        let evicted = self.package_jsons.get(path).lock().unwrap().insert(path, pkg);
+        self.count_evictions(evicted);
+    }
+
+    fn clear(&self) {
+        for shard in self.entries.iter() {
+            shard.lock().unwrap().clear();
+        }
+        for shard in self.package_jsons.iter() {
+            shard.lock().unwrap().clear();
+        }
+    }
+
+    fn forget(&self, path: &Path) {
+        for shard in self.entries.iter() {
+            shard.lock().unwrap().remove_below(path);
+        }
+        for shard in self.package_jsons.iter() {
+            shard.lock().unwrap().remove_below(path);
+        }
+    }
+
+    fn evictions(&self) -> u64 {
+        self.evictions.load(Ordering::Relaxed)
+    }
+}
+
+impl Resolver {
+    /// Fill the cache with the packages installed below `roots` on a background thread.
+    ///
+    /// This reads every package.json file in `root/node_modules` for each root, and in the
+    /// `node_modules` folders nested inside those packages, so that the first resolutions do not
+    /// have to wait for the filesystem. It is useful in editors, where the project is known
+    /// before the first specifier needs resolving. Without a [cache](Resolver::cache), this does
+    /// nothing useful.
+    ///
+    /// The returned handle can be joined to wait until preloading is done. Unreadable folders
+    /// are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().cache_capacity(50_000);
+    /// resolver.preload(vec![PathBuf::from("/path/to/project")]);
+    /// ```
+    pub fn preload<I>(&self, roots: I) -> JoinHandle<()>
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        let resolver = self.clone();
+        let roots: Vec<PathBuf> = roots.into_iter().collect();
+        thread::spawn(move || {
+            for root in roots {
+                // Reading the packages is what fills the cache, so there is nothing to do here.
+                let _ = resolver.visit_installed_packages(&root, |_, _| Ok(()));
+            }
+        })
+    }
+}
+
+/// Values that are split over several locks by the hash of a path.
+#[derive(Debug)]
+struct Sharded<T> {
+    shards: Box<[T]>,
+}
+
+impl<T> Sharded<T> {
+    fn new(count: usize, make_shard: impl FnMut() -> T) -> Self {
+        Sharded {
+            shards: iter::repeat_with(make_shard).take(count.max(1)).collect(),
+        }
+    }
+
+    /// Get the shard that `path` belongs to.
+    fn get(&self, path: &Path) -> &T {
+        if self.shards.len() == 1 {
+            return &self.shards[0];
+        }
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &T> {
+        self.shards.iter()
+    }
+}
+
+/// A map that forgets its least recently used entries when it grows beyond its capacity.
+#[derive(Debug)]
+struct Lru<V> {
+    capacity: usize,
+    /// Incremented on every access, so a higher tick means a more recent use.
+    tick: u64,
+    map: HashMap<PathBuf, (V, u64)>,
+    /// Keys by the tick they were last used on, oldest first.
+    order: BTreeMap<u64, PathBuf>,
+}
+
+impl<V> Lru<V> {
+    fn new(capacity: usize) -> Self {
+        Lru {
+            capacity,
+            tick: 0,
+            map: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, path: &Path) -> Option<&V> {
+        let (value, last_used) = self.map.get_mut(path)?;
+        self.tick += 1;
+        let key = self.order.remove(last_used).expect("lru order out of sync");
+        self.order.insert(self.tick, key);
+        *last_used = self.tick;
+        Some(value)
+    }
+
+    /// Insert a value, and return how many entries were evicted to make room for it.
+    fn insert(&mut self, path: &Path, value: V) -> usize {
+        self.tick += 1;
+        if let Some((_, last_used)) = self.map.insert(path.to_path_buf(), (value, self.tick)) {
+            self.order.remove(&last_used);
+        }
+        self.order.insert(self.tick, path.to_path_buf());
+
+        let mut evicted = 0;
+        while self.map.len() > self.capacity {
+            let (_, oldest) = self.order.pop_first().expect("lru order out of sync");
+            self.map.remove(&oldest);
+            evicted += 1;
+        }
+        evicted
+    }
+
+    /// Remove `path` and the paths inside it.
+    fn remove_below(&mut self, path: &Path) {
+        let order = &mut self.order;
+        self.map.retain(|key, (_, last_used)| {
+            let keep = !key.starts_with(path);
+            if !keep {
+                order.remove(last_used);
+            }
+            keep
+        });
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resolver;
+    use std::env;
+
+    #[test]
+    fn caches_lookups() {
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let cache = Arc::new(MemoryCache::default());
+        let resolver = Resolver::default()
+            .cache(cache.clone())
+            .with_basedir(fixtures.clone());
+
+        let first = resolver.resolve("./package-json/main-file").unwrap();
+        let stats = resolver.stats();
+        // The directory is checked as a file first, and then found in the cache as a directory.
+        assert_eq!(1, stats.cache_hits);
+        assert_eq!(7, stats.cache_misses);
+        assert_eq!(1, stats.package_json_reads);
+
+        resolver.reset_stats();
+        let second = resolver.resolve("./package-json/main-file").unwrap();
+        let stats = resolver.stats();
+        assert_eq!(first, second);
+        assert_eq!(0, stats.stat_calls);
+        assert_eq!(0, stats.package_json_reads);
+        assert_eq!(8, stats.cache_hits);
+
+        assert_eq!(
+            Some(EntryKind::Directory),
+            cache.get_entry(&fixtures.join("package-json/main-file"))
+        );
+        cache.clear();
+        assert_eq!(None, cache.get_entry(&fixtures.join("package-json/main-file")));
+    }
+
+    #[test]
+    fn preloads_packages() {
+        let root = env::current_dir()
+            .unwrap()
+            .join("fixtures/node-modules/package-json");
+        let resolver = Resolver::default()
+            .cache(Arc::new(MemoryCache::default()))
+            .with_basedir(root.clone());
+        resolver.preload(vec![root]).join().unwrap();
+        assert_eq!(1, resolver.stats().package_json_reads);
+
+        resolver.reset_stats();
+        resolver.resolve("dep").unwrap();
+        let stats = resolver.stats();
+        assert_eq!(0, stats.package_json_reads);
+        assert!(stats.cache_hits >= 3);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = LruCache::new(2);
+        cache.set_entry(Path::new("/a"), EntryKind::File);
+        cache.set_entry(Path::new("/b"), EntryKind::Directory);
+        assert_eq!(Some(EntryKind::File), cache.get_entry(Path::new("/a")));
+        cache.set_entry(Path::new("/c"), EntryKind::Missing);
+
+        assert_eq!(Some(EntryKind::File), cache.get_entry(Path::new("/a")));
+        assert_eq!(None, cache.get_entry(Path::new("/b")));
+        assert_eq!(Some(EntryKind::Missing), cache.get_entry(Path::new("/c")));
+        assert_eq!(1, cache.evictions());
+
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let resolver = Resolver::default()
+            .cache_capacity(2)
+            .with_basedir(fixtures);
+        resolver.resolve("./package-json/main-file").unwrap();
+        // The main-file directory is evicted before it's checked as a directory, so seven
+        // lookups are stored in total.
+        assert_eq!(5, resolver.stats().cache_evictions);
+    }
+
+    #[test]
+    fn shards_large_caches() {
+        let cache = LruCache::new(4096);
+        assert_eq!(SHARDS, cache.entries.shards.len());
+        for i in 0..8192 {
+            cache.set_entry(&PathBuf::from(format!("/{}", i)), EntryKind::File);
+        }
+        let kept = (0..8192)
+            .filter(|i| cache.get_entry(&PathBuf::from(format!("/{}", i))).is_some())
+            .count();
+        assert!(kept <= 4096);
+        assert_eq!(8192 - kept as u64, cache.evictions());
+        assert_eq!(Some(EntryKind::File), cache.get_entry(Path::new("/8191")));
+    }
+}
@@ -0,0 +1,123 @@
+//! Combine several resolvers into one.
+
+use crate::{first_occurrences, Error, Resolver};
+use std::path::PathBuf;
+
+/// Tries a sequence of resolvers, and returns the first successful result.
+///
+/// This is useful to layer differently configured resolvers, for example one that applies
+/// path aliases in front of a plain Node.js resolver. If no resolver finds the specifier, the
+/// returned [`Error::NotFound`] lists the candidates that every resolver tried, in order,
+/// and candidates that several resolvers tried only once.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::{FallbackResolver, Resolver};
+///
+/// let resolver = FallbackResolver::new(vec![
+///     Resolver::default().extensions(vec![".ts"]),
+///     Resolver::default(),
+/// ])
+/// .with_basedir(PathBuf::from("/path/to/project"));
+/// resolver.resolve("./src/index");
+/// ```
+#[derive(Clone, Default)]
+pub struct FallbackResolver {
+    resolvers: Vec<Resolver>,
+}
+
+impl FallbackResolver {
+    /// Create a fallback resolver that tries `resolvers` in order.
+    pub fn new(resolvers: Vec<Resolver>) -> Self {
+        FallbackResolver { resolvers }
+    }
+
+    /// Add a resolver to try after all the existing ones.
+    ///
+    /// Consumes the FallbackResolver instance.
+    pub fn or(mut self, resolver: Resolver) -> Self {
+        self.resolvers.push(resolver);
+        self
+    }
+
+    /// Create a new fallback resolver where every resolver uses a different basedir.
+    pub fn with_basedir(&self, basedir: PathBuf) -> Self {
+        FallbackResolver {
+            resolvers: self
+                .resolvers
+                .iter()
+                .map(|resolver| resolver.with_basedir(basedir.clone()))
+                .collect(),
+        }
+    }
+
+    /// Get the resolvers that are tried, in order.
+    pub fn resolvers(&self) -> &[Resolver] {
+        &self.resolvers
+    }
+
+    /// Resolve a `require('target')` argument with each resolver in turn.
+    ///
+    /// Resolution stops at the first resolver that finds the target. If a resolver fails for
+    /// another reason than not finding the target, for example because of an invalid
+    /// package.json file, that error is returned instead.
+    pub fn resolve(&self, target: &str) -> Result<PathBuf, Error> {
+        let mut not_found: Option<(PathBuf, Vec<PathBuf>)> = None;
+        for resolver in &self.resolvers {
+            match resolver.resolve(target) {
+                Ok(path) => return Ok(path),
+                Err(Error::NotFound { basedir, tried, .. }) => match not_found {
+                    Some((_, ref mut all_tried)) => all_tried.extend(tried),
+                    None => not_found = Some((basedir, tried)),
+                },
+                Err(err) => return Err(err),
+            }
+        }
+
+        let (basedir, tried) = not_found.unwrap_or_default();
+        Err(Error::NotFound {
+            specifier: target.to_string(),
+            basedir,
+            tried: first_occurrences(tried),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn falls_back_to_next_resolver() {
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let resolver = FallbackResolver::new(vec![Resolver::default().extensions(vec![".json"])])
+            .or(Resolver::default())
+            .with_basedir(fixtures.clone());
+
+        assert_eq!(
+            fixtures.join("extensions/js-file.js"),
+            resolver.resolve("./extensions/js-file").unwrap()
+        );
+
+        match resolver.resolve("./extensions/missing") {
+            Err(Error::NotFound {
+                specifier, tried, ..
+            }) => {
+                assert_eq!("./extensions/missing", specifier);
+                let missing = fixtures.join("extensions/missing");
+                assert_eq!(
+                    vec![
+                        missing.clone(),
+                        missing.with_extension("json"),
+                        missing.with_extension("js"),
+                        missing.with_extension("node"),
+                    ],
+                    tried
+                );
+            }
+            other => panic!("expected NotFound, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Resolvers whose options are fixed, for resolving many specifiers from different basedirs.
+
+use crate::{Error, Resolver, ResolverBuilder, ResolverStats, DEFAULT_CACHE_CAPACITY};
+use std::path::{Path, PathBuf};
+
+/// A resolver whose options can no longer change. Create one with [`Resolver::compile`] or
+/// [`ResolverBuilder::compile`] once, and use it for every resolution.
+///
+/// The basedir is passed with each resolution instead of being part of the configuration, and
+/// compiled resolvers can be shared between threads. Each resolution still starts from a copy
+/// of the options, which only clones reference-counted pointers.
+///
+/// A compiled resolver always has a cache, so resolutions can reuse the filesystem lookups,
+/// the parsed package.json files, and the compiled "exports" fields of earlier ones. If the
+/// resolver did not have a cache, a bounded one is added, like the one of the shared default
+/// resolver. Set a [cache](Resolver::cache) or a [capacity](Resolver::cache_capacity) before
+/// compiling to use a different one.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::Resolver;
+/// use std::path::Path;
+///
+/// let resolver = Resolver::node_esm().compile();
+/// for file in files {
+///     resolver.resolve("react", file.parent().unwrap());
+/// }
+/// ```
+#[derive(Clone)]
+pub struct CompiledResolver {
+    resolver: Resolver,
+}
+
+impl CompiledResolver {
+    /// Resolve `target` from `basedir`.
+    pub fn resolve(&self, target: &str, basedir: &Path) -> Result<PathBuf, Error> {
+        self.resolver
+            .with_basedir(basedir.to_path_buf())
+            .resolve(target)
+    }
+
+    /// Forget all cached filesystem lookups, for example after files were added or removed.
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.resolver.cache {
+            cache.clear();
+        }
+        if let Some(fs) = &self.resolver.case_insensitive_fs {
+            fs.clear();
+        }
+        self.resolver.compiled_exports.clear();
+    }
+
+    /// Get statistics about the resolutions done so far. See [`Resolver::stats`].
+    pub fn stats(&self) -> ResolverStats {
+        self.resolver.stats()
+    }
+
+    /// Get the underlying resolver, for example to use other resolution methods.
+    pub fn resolver(&self) -> &Resolver {
+        &self.resolver
+    }
+}
+
+impl Resolver {
+    /// Freeze the options of this resolver, so it can be used for many resolutions from
+    /// different basedirs. A bounded cache is added if the resolver does not have a cache. See
+    /// [`CompiledResolver`].
+    pub fn compile(self) -> CompiledResolver {
+        let resolver = if self.cache.is_some() {
+            self
+        } else {
+            self.cache_capacity(DEFAULT_CACHE_CAPACITY)
+        };
+        CompiledResolver { resolver }
+    }
+}
+
+impl ResolverBuilder {
+    /// Create a [`CompiledResolver`] with the current configuration. The builder can be reused
+    /// afterwards.
+    pub fn compile(&self) -> CompiledResolver {
+        self.build().compile()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Resolver, ResolverBuilder, SnapshotFileSystem, DEFAULT_CACHE_CAPACITY};
+    use std::env;
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn compiles_resolvers() {
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let resolver = Resolver::default().compile();
+        assert_eq!(
+            Ok(fixtures.join("extensions/js-file.js")),
+            resolver.resolve("./js-file", &fixtures.join("extensions"))
+        );
+        let basedir = fixtures.join("node-modules/package-json");
+        let dep = basedir.join("node_modules/dep/lib/index.js");
+        for _ in 0..2 {
+            assert_eq!(Ok(dep.clone()), resolver.resolve("dep", &basedir));
+        }
+        assert!(resolver.stats().cache_hits > 0);
+
+        let resolver = Arc::new(ResolverBuilder::new().extensions(&[".js"]).compile());
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let resolver = Arc::clone(&resolver);
+                let basedir = basedir.clone();
+                thread::spawn(move || resolver.resolve("dep", &basedir))
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(Ok(dep.clone()), thread.join().unwrap());
+        }
+        resolver.clear_cache();
+
+        // The cache that is added is bounded.
+        let resolver = Resolver::default()
+            .filesystem(Arc::new(SnapshotFileSystem::default()))
+            .compile();
+        for i in 0..DEFAULT_CACHE_CAPACITY {
+            assert!(resolver.resolve(&format!("./{}", i), Path::new("/src")).is_err());
+        }
+        assert!(resolver.stats().cache_evictions > 0);
+    }
+}
@@ -0,0 +1,226 @@
+//! Cross-checking resolution results against a local Node.js installation.
+
+use crate::{Error, Resolver};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Resolves a specifier with `require.resolve()` from a file in the current directory.
+static REQUIRE_SCRIPT: &str = "
+const { createRequire } = require('module');
+const path = require('path');
+const require2 = createRequire(path.join(process.cwd(), 'noop.js'));
+process.stdout.write(require2.resolve(process.argv[1]));
+";
+
+/// Resolves a specifier with `import.meta.resolve()` from a module in the current directory.
+/// `import.meta.resolve()` does not check that the file exists, but importing it would.
+static IMPORT_SCRIPT: &str = "
+import { statSync } from 'fs';
+import { fileURLToPath } from 'url';
+const url = import.meta.resolve(process.argv[1]);
+if (!url.startsWith('file:')) {
+  process.stdout.write(url);
+} else if (statSync(fileURLToPath(url), { throwIfNoEntry: false })?.isFile()) {
+  process.stdout.write(fileURLToPath(url));
+} else {
+  process.exit(1);
+}
+";
+
+/// How Node.js resolves a specifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeMode {
+    /// Like `require.resolve()` in a CommonJS module.
+    Require,
+    /// Like `import.meta.resolve()` in an ES module.
+    Import,
+}
+
+/// A specifier that Node.js and a [`Resolver`] resolved differently.
+#[derive(Debug)]
+pub struct Mismatch {
+    /// The specifier.
+    pub specifier: String,
+    /// The directory the specifier was resolved from.
+    pub basedir: PathBuf,
+    /// What Node.js resolved the specifier to, or `None` if Node.js could not resolve it.
+    pub node: Option<PathBuf>,
+    /// What the resolver returned.
+    pub resolved: Result<PathBuf, Error>,
+}
+
+/// Runs a local `node` binary to check that a [`Resolver`] agrees with Node.js.
+///
+/// This is a development tool for catching differences between this crate and Node.js as
+/// the resolution algorithm grows. It starts one `node` process per specifier, so it is slow.
+///
+/// This requires the `conformance` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::{NodeMode, NodeOracle, Resolver};
+///
+/// if let Some(node) = NodeOracle::find() {
+///     let mismatches = node
+///         .compare_all(
+///             &Resolver::default(),
+///             vec![("./index", Path::new("/project/src"))],
+///             NodeMode::Require,
+///         )
+///         .unwrap();
+///     assert!(mismatches.is_empty(), "{:#?}", mismatches);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct NodeOracle {
+    node: PathBuf,
+}
+
+impl NodeOracle {
+    /// Use the Node.js binary at `node`.
+    pub fn new(node: PathBuf) -> Self {
+        NodeOracle { node }
+    }
+
+    /// Use the `node` binary on the `PATH`. Returns `None` if it cannot be run.
+    pub fn find() -> Option<Self> {
+        let oracle = NodeOracle::new(PathBuf::from("node"));
+        let output = Command::new(&oracle.node).arg("--version").output().ok()?;
+        if output.status.success() {
+            Some(oracle)
+        } else {
+            None
+        }
+    }
+
+    /// Resolve `specifier` from `basedir` with Node.js. Returns `Ok(None)` if Node.js could
+    /// not resolve it, and an error if `node` could not be run.
+    pub fn resolve(
+        &self,
+        specifier: &str,
+        basedir: &Path,
+        mode: NodeMode,
+    ) -> io::Result<Option<PathBuf>> {
+        let mut command = Command::new(&self.node);
+        match mode {
+            NodeMode::Require => command.arg("-e").arg(REQUIRE_SCRIPT),
+            NodeMode::Import => command.arg("--input-type=module").arg("-e").arg(IMPORT_SCRIPT),
+        };
+        let output = command.arg(specifier).current_dir(basedir).output()?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        let resolved = String::from_utf8_lossy(&output.stdout);
+        // Builtin modules resolve to their name, like they do in this crate.
+        let resolved = resolved.strip_prefix("node:").unwrap_or(&resolved);
+        Ok(Some(PathBuf::from(resolved)))
+    }
+
+    /// Resolve `specifier` from `basedir` with Node.js and with `resolver`, and describe the
+    /// difference, if any.
+    pub fn compare(
+        &self,
+        resolver: &Resolver,
+        specifier: &str,
+        basedir: &Path,
+        mode: NodeMode,
+    ) -> io::Result<Option<Mismatch>> {
+        let node = self.resolve(specifier, basedir, mode)?;
+        let resolved = resolver.with_basedir(basedir.to_path_buf()).resolve(specifier);
+        if resolved.as_ref().ok() == node.as_ref() {
+            return Ok(None);
+        }
+        Ok(Some(Mismatch {
+            specifier: specifier.to_string(),
+            basedir: basedir.to_path_buf(),
+            node,
+            resolved,
+        }))
+    }
+
+    /// Compare every `(specifier, basedir)` pair, and collect the differences.
+    pub fn compare_all<'a, I>(
+        &self,
+        resolver: &Resolver,
+        cases: I,
+        mode: NodeMode,
+    ) -> io::Result<Vec<Mismatch>>
+    where
+        I: IntoIterator<Item = (&'a str, &'a Path)>,
+    {
+        let mut mismatches = Vec::new();
+        for (specifier, basedir) in cases {
+            mismatches.extend(self.compare(resolver, specifier, basedir, mode)?);
+        }
+        Ok(mismatches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    /// Run with `cargo test --features conformance -- --ignored` on a machine with Node.js.
+    #[test]
+    #[ignore]
+    fn agrees_with_node() {
+        let node = NodeOracle::find().expect("node is not installed");
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let package_json = fixtures.join("package-json");
+        let node_modules = fixtures.join("node-modules/package-json");
+        let exports = fixtures.join("exports");
+        let imports = fixtures.join("imports/src");
+
+        let common = [
+            ("fs", fixtures.as_path()),
+            ("./extensions/js-file.js", fixtures.as_path()),
+            ("./extensions/missing.js", fixtures.as_path()),
+            ("dep", node_modules.as_path()),
+            ("exports", exports.as_path()),
+            ("exports/feature", exports.as_path()),
+            ("exports/utils/string", exports.as_path()),
+            ("exports/lib/feature.js", exports.as_path()),
+            ("exports/blocked", exports.as_path()),
+            ("patterns/utils/string", exports.as_path()),
+            ("patterns/config.json", exports.as_path()),
+            ("#internal/helper", imports.as_path()),
+            ("#env", imports.as_path()),
+            ("#dep", imports.as_path()),
+        ];
+        let require = [
+            ("./extensions/js-file", fixtures.as_path()),
+            ("./extensions/json-file", fixtures.as_path()),
+            ("./main-file", package_json.as_path()),
+            ("./main-dir", package_json.as_path()),
+            ("./main-none", package_json.as_path()),
+            ("./module", package_json.as_path()),
+            ("exports/conditional", exports.as_path()),
+        ];
+        let import = [
+            ("./extensions/js-file", fixtures.as_path()),
+            ("./extensions/module.mjs", fixtures.as_path()),
+            ("exports/conditional", exports.as_path()),
+        ];
+
+        let mut mismatches = node
+            .compare_all(
+                &Resolver::default(),
+                common.iter().chain(&require).copied(),
+                NodeMode::Require,
+            )
+            .unwrap();
+        mismatches.extend(
+            node.compare_all(
+                &Resolver::node_esm(),
+                common.iter().chain(&import).copied(),
+                NodeMode::Import,
+            )
+            .unwrap(),
+        );
+        assert!(mismatches.is_empty(), "{:#?}", mismatches);
+    }
+}
@@ -0,0 +1,143 @@
+//! Deduplication of identical resolution requests.
+
+use crate::{Error, Resolver};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+/// The result of a deduplicated resolution, shared by everyone who requested it.
+pub type SharedResult = Arc<Result<PathBuf, Error>>;
+
+/// A `(specifier, basedir)` pair.
+type Request = (String, PathBuf);
+
+/// Resolves every `(specifier, basedir)` pair at most once, and shares the result.
+///
+/// Bundlers often resolve the same specifier from many files in the same directory. When a
+/// pair is requested from multiple threads at the same time, one thread resolves it and the
+/// others wait for its result. Results are kept until [`DedupResolver::clear`] is called.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::{DedupResolver, Resolver};
+/// use std::path::Path;
+///
+/// let resolver = DedupResolver::new(Resolver::default());
+/// let a = resolver.resolve("lodash", Path::new("/project/src"));
+/// let b = resolver.resolve("lodash", Path::new("/project/src"));
+/// assert!(std::sync::Arc::ptr_eq(&a, &b));
+/// ```
+#[derive(Default)]
+pub struct DedupResolver {
+    resolver: Resolver,
+    results: Mutex<HashMap<Request, Arc<OnceLock<SharedResult>>>>,
+}
+
+impl DedupResolver {
+    /// Create a deduplicating resolver that uses `resolver` with different basedirs.
+    pub fn new(resolver: Resolver) -> Self {
+        DedupResolver {
+            resolver,
+            results: Mutex::default(),
+        }
+    }
+
+    /// Resolve `specifier` from `basedir`, or wait for and reuse the result of an identical
+    /// request.
+    pub fn resolve(&self, specifier: &str, basedir: &Path) -> SharedResult {
+        let cell = {
+            let mut results = self.results.lock().unwrap_or_else(PoisonError::into_inner);
+            let key = (specifier.to_string(), basedir.to_path_buf());
+            Arc::clone(results.entry(key).or_default())
+        };
+        let result = cell.get_or_init(|| {
+            Arc::new(
+                self.resolver
+                    .with_basedir(basedir.to_path_buf())
+                    .resolve(specifier),
+            )
+        });
+        Arc::clone(result)
+    }
+
+    /// Forget all results, so they are resolved again the next time they are requested.
+    pub fn clear(&self) {
+        self.results
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Get the underlying resolver.
+    pub fn resolver(&self) -> &Resolver {
+        &self.resolver
+    }
+}
+
+impl Resolver {
+    /// Resolve a batch of `(specifier, basedir)` pairs. Identical pairs are only resolved once,
+    /// and share their result.
+    ///
+    /// The results are in the same order as the requests. Use a [`DedupResolver`] to share
+    /// results between batches or threads.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    /// use std::path::Path;
+    ///
+    /// let src = Path::new("/project/src");
+    /// let results = Resolver::default().resolve_many(vec![("react", src), ("react", src)]);
+    /// ```
+    pub fn resolve_many<'a, I>(&self, requests: I) -> Vec<SharedResult>
+    where
+        I: IntoIterator<Item = (&'a str, &'a Path)>,
+    {
+        let dedup = DedupResolver::new(self.clone());
+        requests
+            .into_iter()
+            .map(|(specifier, basedir)| dedup.resolve(specifier, basedir))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::thread;
+
+    #[test]
+    fn deduplicates_requests() {
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let resolver = Resolver::default();
+        let results = resolver.resolve_many(vec![
+            ("./js-file", fixtures.join("extensions").as_path()),
+            ("./missing", fixtures.join("extensions").as_path()),
+            ("./js-file", fixtures.join("extensions").as_path()),
+            ("./extensions/js-file", fixtures.as_path()),
+        ]);
+        assert_eq!(4, results.len());
+        assert_eq!(Ok(fixtures.join("extensions/js-file.js")), *results[0]);
+        assert!(results[1].is_err());
+        assert!(Arc::ptr_eq(&results[0], &results[2]));
+        assert!(!Arc::ptr_eq(&results[0], &results[3]));
+        assert_eq!(3, resolver.stats().resolutions);
+
+        let dedup = Arc::new(DedupResolver::new(Resolver::default()));
+        let basedir = fixtures.join("node-modules/package-json");
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let dedup = Arc::clone(&dedup);
+                let basedir = basedir.clone();
+                thread::spawn(move || dedup.resolve("dep", &basedir))
+            })
+            .collect();
+        for thread in threads {
+            assert!(thread.join().unwrap().is_ok());
+        }
+        assert_eq!(1, dedup.resolver().stats().resolutions);
+    }
+}
@@ -0,0 +1,117 @@
+//! Rich diagnostics for resolution errors, using `miette`.
+
+use crate::{is_path_specifier, Error};
+use miette::Diagnostic;
+use std::fmt::{self, Write};
+
+/// Resolution errors can be rendered by `miette` reporters, with a stable error code, the
+/// paths that were tried, and a hint on how to fix the problem.
+///
+/// This requires the `miette` feature.
+impl Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let code = match self {
+            Error::JSONError(_) => "node_resolve::json",
+            Error::IOError(_) => "node_resolve::io",
+            Error::PermissionDenied { .. } => "node_resolve::permission_denied",
+            Error::TransientIOError { .. } => "node_resolve::transient_io",
+            Error::UnconfiguredBasedir => "node_resolve::unconfigured_basedir",
+            Error::UndefinedVariable(_) => "node_resolve::undefined_variable",
+            Error::InvalidSpecifier { .. } => "node_resolve::invalid_specifier",
+            Error::NotFound { .. } => "node_resolve::not_found",
+            Error::PackagePathNotExported { .. } => "node_resolve::package_path_not_exported",
+            Error::InvalidPackageTarget { .. } => "node_resolve::invalid_package_target",
+            Error::PackageImportNotDefined { .. } => "node_resolve::package_import_not_defined",
+            Error::BinNotFound { .. } => "node_resolve::bin_not_found",
+            Error::BudgetExceeded(_) => "node_resolve::budget_exceeded",
+            Error::Timeout { .. } => "node_resolve::timeout",
+            Error::InvalidPackageJson { .. } => "node_resolve::invalid_package_json",
+            Error::InvalidPackageConfig { .. } => "node_resolve::invalid_package_config",
+            Error::BrokenSymlink { .. } => "node_resolve::broken_symlink",
+            Error::PackageBoundary { .. } => "node_resolve::package_boundary",
+            Error::DeniedPackage { .. } => "node_resolve::denied_package",
+            Error::ManifestCycle { .. } => "node_resolve::manifest_cycle",
+        };
+        Some(Box::new(code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let help = match self {
+            Error::UnconfiguredBasedir => String::from("call `with_basedir()` before resolving"),
+            Error::NotFound {
+                specifier, tried, ..
+            } => not_found_help(specifier, tried),
+            Error::PackagePathNotExported { .. } => String::from(
+                "only the subpaths listed in the \"exports\" field of the package can be imported",
+            ),
+            Error::PackageImportNotDefined { .. } => String::from(
+                "add the specifier to the \"imports\" field of the package.json file",
+            ),
+            Error::BrokenSymlink { .. } => String::from(
+                "the install may be corrupted, try reinstalling your dependencies",
+            ),
+            Error::PackageBoundary { .. } => String::from(
+                "import the package by its name, so its \"exports\" field is respected",
+            ),
+            Error::DeniedPackage { .. } => String::from(
+                "this package may not be used here, remove the import or use an allowed package",
+            ),
+            Error::PermissionDenied { .. } => String::from(
+                "check the permissions of the path and of the directories above it",
+            ),
+            Error::TransientIOError { .. } => String::from(
+                "the filesystem may be overloaded, configure `io_retries()` to try again",
+            ),
+            Error::ManifestCycle { .. } => String::from(
+                "point the \"main\" field of one of the package.json files to a file instead",
+            ),
+            Error::BinNotFound { .. } => {
+                String::from("check the \"bin\" field in the package.json file of the package")
+            }
+            _ => return None,
+        };
+        Some(Box::new(help))
+    }
+}
+
+/// Describe the paths that were tried for a specifier, and suggest a fix.
+fn not_found_help(specifier: &str, tried: &[std::path::PathBuf]) -> String {
+    let mut help = String::new();
+    if !tried.is_empty() {
+        help.push_str("tried these paths:");
+        for path in tried {
+            let _ = write!(help, "\n  - {}", path.display());
+        }
+        help.push('\n');
+    }
+    if is_path_specifier(specifier) {
+        help.push_str("check the path and the file extension");
+    } else {
+        help.push_str("did you forget to npm install?");
+    }
+    help
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Resolver;
+    use miette::Diagnostic;
+    use std::env;
+
+    #[test]
+    fn describes_errors() {
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let resolver = Resolver::default().with_basedir(fixtures.clone());
+
+        let err = resolver.resolve("missing-package").unwrap_err();
+        assert_eq!("node_resolve::not_found", err.code().unwrap().to_string());
+        let help = err.help().unwrap().to_string();
+        assert!(help.ends_with("did you forget to npm install?"));
+
+        let err = resolver.resolve("./missing-file").unwrap_err();
+        let help = err.help().unwrap().to_string();
+        assert!(help.starts_with("tried these paths:"));
+        assert!(help.contains(&fixtures.join("missing-file.js").display().to_string()));
+        assert!(help.ends_with("check the path and the file extension"));
+    }
+}
@@ -0,0 +1,128 @@
+//! Find packages that are installed more than once in a `node_modules` tree.
+
+use crate::{Error, InternalError, Resolver};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A single installed copy of a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageInstance {
+    /// The directory containing the package.
+    pub path: PathBuf,
+    /// The `"version"` field from the package.json file, if there is one.
+    pub version: Option<String>,
+}
+
+/// A package that is installed in more than one location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicatePackage {
+    /// The name of the package.
+    pub name: String,
+    /// All installed copies of the package, in the order they were found.
+    pub instances: Vec<PackageInstance>,
+}
+
+impl DuplicatePackage {
+    /// Get the distinct versions of the package that are installed, sorted alphabetically.
+    pub fn versions(&self) -> Vec<&str> {
+        let mut versions: Vec<&str> = self
+            .instances
+            .iter()
+            .filter_map(|instance| instance.version.as_deref())
+            .collect();
+        versions.sort_unstable();
+        versions.dedup();
+        versions
+    }
+}
+
+impl Resolver {
+    /// Find all packages that are installed in more than one location below `root`.
+    ///
+    /// This scans `root/node_modules` and every nested `node_modules` folder the resolver could
+    /// reach from a package inside it. A copy that is reachable through several symlinks is only
+    /// reported once. The result is sorted by package name.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// for package in Resolver::default().find_duplicate_packages(Path::new("."))? {
+    ///     println!("{} is installed {} times", package.name, package.instances.len());
+    /// }
+    /// ```
+    pub fn find_duplicate_packages(&self, root: &Path) -> Result<Vec<DuplicatePackage>, Error> {
+        let mut packages = BTreeMap::<String, Vec<PackageInstance>>::new();
+        self.visit_installed_packages(root, |dir, pkg| {
+            let name = pkg
+                .as_ref()
+                .and_then(|pkg| pkg["name"].as_str())
+                .map(String::from)
+                .unwrap_or_else(|| package_dir_name(dir));
+            let version = pkg
+                .as_ref()
+                .and_then(|pkg| pkg["version"].as_str())
+                .map(String::from);
+
+            let path = self.normalize(dir).map_err(InternalError::into_public)?;
+            packages
+                .entry(name)
+                .or_default()
+                .push(PackageInstance { path, version });
+            Ok(())
+        })?;
+
+        Ok(packages
+            .into_iter()
+            .filter(|(_, instances)| instances.len() > 1)
+            .map(|(name, instances)| DuplicatePackage { name, instances })
+            .collect())
+    }
+}
+
+/// Get the package name implied by a directory inside `node_modules`, like `@scope/name`.
+fn package_dir_name(dir: &Path) -> String {
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    match dir.parent().and_then(Path::file_name).map(|scope| scope.to_string_lossy()) {
+        Some(scope) if scope.starts_with('@') => format!("{}/{}", scope, name),
+        _ => name.into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn fixture(part: &str) -> PathBuf {
+        env::current_dir().unwrap().join("fixtures").join(part)
+    }
+
+    #[test]
+    fn finds_duplicate_packages() {
+        let duplicates = Resolver::default()
+            .find_duplicate_packages(&fixture("duplicates"))
+            .unwrap();
+
+        assert_eq!(
+            vec!["@scope/b", "a"],
+            duplicates.iter().map(|p| p.name.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                PackageInstance {
+                    path: fixture("duplicates/node_modules/a"),
+                    version: Some(String::from("1.0.0")),
+                },
+                PackageInstance {
+                    path: fixture("duplicates/node_modules/c/node_modules/a"),
+                    version: Some(String::from("2.0.0")),
+                },
+            ],
+            duplicates[1].instances
+        );
+        assert_eq!(vec!["1.0.0", "2.0.0"], duplicates[1].versions());
+        assert_eq!(vec!["1.1.0"], duplicates[0].versions());
+    }
+}
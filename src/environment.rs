@@ -0,0 +1,156 @@
+//! Access to the environment of the process, like environment variables and the current
+//! working directory.
+//!
+//! All of it goes through the [`Environment`] trait, so hermetic builds can resolve without
+//! reading ambient state, and targets without an environment, like WASM, can provide one.
+
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// The environment access the resolver needs.
+pub trait Environment: Send + Sync {
+    /// Get the value of an environment variable, or `None` if it is not set.
+    fn var(&self, name: &str) -> Option<String>;
+    /// Get the current working directory, or `None` if there is none.
+    fn current_dir(&self) -> Option<PathBuf>;
+}
+
+/// The environment of the current process. This is the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessEnvironment;
+
+impl Environment for ProcessEnvironment {
+    fn var(&self, name: &str) -> Option<String> {
+        env::var(name).ok()
+    }
+
+    fn current_dir(&self) -> Option<PathBuf> {
+        env::current_dir().ok()
+    }
+}
+
+/// A fixed set of environment variables and working directory, independent of the process.
+///
+/// An empty environment has no variables and no working directory, which disables
+/// everything that depends on them: `~/` and `${VAR}` can not be expanded, relative basedirs
+/// are not made absolute, and `NODE_PATH` is empty.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::{FixedEnvironment, Resolver};
+/// use std::sync::Arc;
+///
+/// let mut environment = FixedEnvironment::new();
+/// environment.set_var("HOME", "/home/ci").set_current_dir("/project");
+/// let resolver = Resolver::default().environment(Arc::new(environment));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FixedEnvironment {
+    vars: HashMap<String, String>,
+    current_dir: Option<PathBuf>,
+}
+
+impl FixedEnvironment {
+    /// Create an empty environment.
+    pub fn new() -> Self {
+        FixedEnvironment::default()
+    }
+
+    /// Set an environment variable.
+    pub fn set_var<N, V>(&mut self, name: N, value: V) -> &mut Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the current working directory.
+    pub fn set_current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.current_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+}
+
+impl Environment for FixedEnvironment {
+    fn var(&self, name: &str) -> Option<String> {
+        self.vars.get(name).cloned()
+    }
+
+    fn current_dir(&self) -> Option<PathBuf> {
+        self.current_dir.clone()
+    }
+}
+
+/// Get the folders in the `NODE_PATH` environment variable. Relative folders are relative to
+/// the current working directory, and are left out if there is none.
+pub(crate) fn node_path(environment: &dyn Environment) -> Vec<PathBuf> {
+    let value = match environment.var("NODE_PATH") {
+        Some(value) => value,
+        None => return vec![],
+    };
+    let cwd = environment.current_dir();
+    env::split_paths(&value)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .filter_map(|dir| {
+            if dir.is_absolute() {
+                Some(dir)
+            } else {
+                cwd.as_ref().map(|cwd| crate::normalize(&cwd.join(dir)))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, Resolver};
+    use std::sync::Arc;
+
+    fn fixture(part: &str) -> PathBuf {
+        env::current_dir().unwrap().join("fixtures").join(part)
+    }
+
+    #[test]
+    fn uses_configured_environment() {
+        let mut environment = FixedEnvironment::new();
+        environment
+            .set_var("HOME", fixture("").to_string_lossy())
+            .set_var("USERPROFILE", fixture("").to_string_lossy())
+            .set_var("FIXTURES", fixture("extensions").to_string_lossy())
+            .set_var("NODE_PATH", "jest/lib")
+            .set_current_dir(fixture(""));
+        let resolver = Resolver::default()
+            .expand_env(true)
+            .node_path(true)
+            .environment(Arc::new(environment))
+            .with_basedir(fixture("jest/src"));
+
+        assert_eq!(
+            Ok(fixture("extensions/js-file.js")),
+            resolver.resolve("~/extensions/js-file")
+        );
+        assert_eq!(Ok(fixture("extensions/js-file.js")), resolver.resolve("${FIXTURES}/js-file"));
+        assert_eq!(Ok(fixture("jest/lib/shared/index.js")), resolver.resolve("shared"));
+        // Relative basedirs are relative to the configured working directory.
+        let paths = resolver.node_modules_paths(Path::new("jest/src"));
+        assert_eq!(Some(&fixture("jest/src/node_modules")), paths.first());
+        assert_eq!(Some(&fixture("jest/lib")), paths.last());
+
+        // An empty environment provides nothing.
+        let hermetic = resolver.environment(Arc::new(FixedEnvironment::new()));
+        assert!(matches!(
+            hermetic.resolve("~/extensions/js-file"),
+            Err(Error::UndefinedVariable(_))
+        ));
+        assert!(hermetic.resolve("shared").is_err());
+        assert_eq!(
+            Some(&PathBuf::from("jest/src/node_modules")),
+            hermetic.node_modules_paths(Path::new("jest/src")).first()
+        );
+    }
+}
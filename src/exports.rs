@@ -0,0 +1,964 @@
+//! Package entry points defined by the package.json "exports" field, and package-internal
+//! specifiers defined by the "imports" field.
+//!
+//! See the [Node.js documentation](https://nodejs.org/api/packages.html#package-entry-points)
+//! for how "exports" and "imports" work.
+
+use crate::{not_found, Context, Error, InternalError, Resolver, Warning};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{Error as IOError, ErrorKind as IOErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+
+/// The result of resolving a target in the "exports" field.
+enum Target {
+    /// The target maps to this path.
+    Path(PathBuf),
+    /// The target is `null`, so the subpath is explicitly not exported.
+    Blocked,
+    /// The target is a bare specifier for another package. Only "imports" can do this.
+    Package(String),
+    /// None of the conditions in the target matched.
+    NoMatch,
+}
+
+/// How a subpath matched a key in the "exports" or "imports" field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Match<'s> {
+    /// The key is the subpath.
+    Exact,
+    /// The key is a pattern, and the `*` in it stands for this part of the subpath.
+    Pattern(&'s str),
+    /// The key is a deprecated folder mapping ending in `/`, and this is the rest of the
+    /// subpath.
+    Folder(&'s str),
+}
+
+/// An "exports" field that was prepared for a set of conditions, so subpaths can be matched
+/// without walking the whole field every time.
+#[derive(Debug)]
+pub(crate) struct CompiledExports {
+    /// The targets of all keys, with the conditions already applied. A target is `None` if
+    /// none of its conditions match.
+    exact: HashMap<String, Option<Value>>,
+    /// The subpath patterns and folder mappings, most specific first.
+    patterns: Vec<(String, Option<Value>)>,
+}
+
+impl CompiledExports {
+    /// Prepare an "exports" field for `conditions`. Returns `None` if the field is invalid,
+    /// so the error is reported when it is used.
+    fn compile(exports: &Value, conditions: &[String]) -> Option<Self> {
+        let mut exact = HashMap::new();
+        let mut patterns = Vec::new();
+        match exports_subpaths(exports).ok()? {
+            Some(subpaths) => {
+                for (key, target) in subpaths {
+                    // Keys whose conditions do not match are kept, because they still win
+                    // over less specific keys, and then the subpath is not exported.
+                    let target = select_conditions(target, conditions);
+                    if split_pattern(key).is_some() || (key.ends_with('/') && !key.contains('*')) {
+                        patterns.push((key.clone(), target.clone()));
+                    }
+                    exact.insert(key.clone(), target);
+                }
+            }
+            None => {
+                exact.insert(String::from("."), select_conditions(exports, conditions));
+            }
+        }
+        // The sort is stable, so equally specific keys stay in the order of the package.json
+        // file, like in `match_subpath`.
+        patterns.sort_by(|(a, _), (b, _)| pattern_key_compare(a, b));
+        Some(CompiledExports { exact, patterns })
+    }
+
+    /// Find the key and target for `subpath`, like `match_subpath` does. The target is `None`
+    /// if none of the conditions of the key match.
+    fn match_subpath<'a, 's>(
+        &'a self,
+        subpath: &'s str,
+        folders: bool,
+    ) -> Option<(&'a str, Option<&'a Value>, Match<'s>)> {
+        if !subpath.contains('*') {
+            if let Some((key, target)) = self.exact.get_key_value(subpath) {
+                return Some((key, target.as_ref(), Match::Exact));
+            }
+        }
+        self.patterns.iter().find_map(|(key, target)| {
+            let matched = match_key(key, subpath, folders)?;
+            Some((key.as_str(), target.as_ref(), matched))
+        })
+    }
+}
+
+/// A cache of compiled "exports" fields, by package.json path and conditions. Entries are only
+/// used while the package.json cache returns the same parsed file. They do not keep the file
+/// alive, and are pruned once the package.json cache dropped it, so this cache is bounded by
+/// the package.json cache.
+#[derive(Debug, Default)]
+pub(crate) struct ExportsCache {
+    entries: Mutex<CompiledEntries>,
+}
+
+/// The path of a package.json file, and the conditions its "exports" field was compiled for.
+type CompiledKey = (PathBuf, Arc<[String]>);
+
+/// A compiled "exports" field, and the parsed package.json file it was compiled from.
+type CompiledEntry = (Weak<Value>, Arc<CompiledExports>);
+
+#[derive(Debug, Default)]
+struct CompiledEntries {
+    map: HashMap<CompiledKey, CompiledEntry>,
+    /// The number of entries at which the entries of dropped package.json files are pruned.
+    prune_at: usize,
+}
+
+/// The smallest number of entries at which entries are pruned.
+const MIN_PRUNE_AT: usize = 64;
+
+impl ExportsCache {
+    /// Get the compiled "exports" field of `pkg`, compiling it if it is not cached yet.
+    fn get(
+        &self,
+        pkg_path: &Path,
+        pkg: &Arc<Value>,
+        conditions: &Arc<[String]>,
+    ) -> Option<Arc<CompiledExports>> {
+        let key = (pkg_path.to_path_buf(), Arc::clone(conditions));
+        let mut entries = self.lock();
+        if let Some((cached_pkg, compiled)) = entries.map.get(&key) {
+            // A file that is still alive can not share its address with `pkg` by accident.
+            if cached_pkg.upgrade().is_some_and(|cached_pkg| Arc::ptr_eq(&cached_pkg, pkg)) {
+                return Some(Arc::clone(compiled));
+            }
+        }
+        let compiled = Arc::new(CompiledExports::compile(&pkg["exports"], conditions)?);
+        entries.map.insert(key, (Arc::downgrade(pkg), Arc::clone(&compiled)));
+        if entries.map.len() >= entries.prune_at {
+            entries.prune();
+        }
+        Some(compiled)
+    }
+
+    /// Forget the compiled "exports" fields of the package.json files at or below `path`.
+    pub(crate) fn forget(&self, path: &Path) {
+        self.lock().map.retain(|(pkg_path, _), _| !pkg_path.starts_with(path));
+    }
+
+    /// Forget all compiled "exports" fields.
+    pub(crate) fn clear(&self) {
+        self.lock().map.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CompiledEntries> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Get the number of compiled "exports" fields.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.lock().map.len()
+    }
+}
+
+impl CompiledEntries {
+    /// Drop the entries of package.json files that are no longer cached. The next prune
+    /// happens when the number of entries doubled, so inserting stays cheap on average.
+    fn prune(&mut self) {
+        self.map.retain(|_, (pkg, _)| pkg.strong_count() > 0);
+        self.prune_at = (self.map.len() * 2).max(MIN_PRUNE_AT);
+    }
+}
+
+impl Resolver {
+    /// Resolve `subpath` inside the package in `pkg_dir` using its "exports" field. `subpath`
+    /// is `.` for the package itself, or starts with `./`.
+    ///
+    /// Returns `None` if the package does not have an "exports" field, so the files in the
+    /// package should be looked up directly.
+    pub(crate) fn resolve_package_exports(
+        &self,
+        pkg_dir: &Path,
+        subpath: &str,
+        ctx: &mut Context,
+    ) -> Option<Result<PathBuf, InternalError>> {
+        let pkg_path = pkg_dir.join("package.json");
+        if !self.use_package_json || !self.probe_file(&pkg_path, ctx) {
+            return None;
+        }
+        let pkg = match self.load_package_json(&pkg_path, ctx) {
+            Ok(pkg) => pkg,
+            Err(err) => {
+                let err = InternalError::from(err);
+                return if err.is_fatal() { Some(Err(err)) } else { None };
+            }
+        };
+        let exports = match pkg.get("exports") {
+            None | Some(Value::Null) => return None,
+            Some(exports) => exports,
+        };
+        // Compiling only pays off if the parsed package.json file is reused.
+        let compiled = match self.cache {
+            Some(_) => self.compiled_exports.get(&pkg_path, &pkg, &self.conditions),
+            None => None,
+        };
+
+        Some(self.resolve_exports_subpath(
+            pkg_dir,
+            &pkg_path,
+            exports,
+            compiled.as_deref(),
+            subpath,
+            ctx,
+        ))
+    }
+
+    fn resolve_exports_subpath(
+        &self,
+        pkg_dir: &Path,
+        pkg_path: &Path,
+        exports: &Value,
+        compiled: Option<&CompiledExports>,
+        subpath: &str,
+        ctx: &mut Context,
+    ) -> Result<PathBuf, InternalError> {
+        let (path, folder_key) = self.match_exports(pkg_dir, exports, compiled, subpath)?;
+        if let Some(key) = folder_key {
+            ctx.warn(Warning::FolderMapping {
+                path: pkg_path.to_path_buf(),
+                key,
+            });
+        }
+        self.resolve_target_file(path, ctx)
+    }
+
+    /// Find the path that `subpath` maps to in the "exports" field of the package in
+    /// `pkg_dir`, using the conditions of this resolver. `subpath` is `.` for the package
+    /// itself, or starts with `./`.
+    ///
+    /// This does not touch the filesystem, and does not check that the path exists. It is the
+    /// same decision that [`Resolver::resolve`] makes for bare specifiers, so it can be tested
+    /// or fuzzed with synthetic "exports" fields.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    /// use serde_json::json;
+    ///
+    /// let exports = json!({ "./*": { "node": "./lib/*.js" } });
+    /// assert_eq!(
+    ///     Ok(PathBuf::from("/pkg/lib/feature.js")),
+    ///     Resolver::default().exports_target(Path::new("/pkg"), &exports, "./feature")
+    /// );
+    /// ```
+    pub fn exports_target(
+        &self,
+        pkg_dir: &Path,
+        exports: &Value,
+        subpath: &str,
+    ) -> Result<PathBuf, Error> {
+        self.match_exports(pkg_dir, exports, None, subpath)
+            .map(|(path, _)| path)
+    }
+
+    /// Find the path that `subpath` maps to in an "exports" field, using its compiled form if
+    /// there is one. If the subpath matched a folder mapping, its key is returned too.
+    fn match_exports(
+        &self,
+        pkg_dir: &Path,
+        exports: &Value,
+        compiled: Option<&CompiledExports>,
+        subpath: &str,
+    ) -> Result<(PathBuf, Option<String>), Error> {
+        let not_exported = || Error::PackagePathNotExported {
+            package_dir: pkg_dir.to_path_buf(),
+            subpath: subpath.to_string(),
+        };
+
+        let matched = match compiled {
+            Some(compiled) => compiled.match_subpath(subpath, self.folder_exports),
+            None => {
+                let subpaths =
+                    exports_subpaths(exports).map_err(|reason| Error::InvalidPackageConfig {
+                        path: pkg_dir.join("package.json"),
+                        reason,
+                    })?;
+                match subpaths {
+                    Some(subpaths) => match_subpath(subpaths, subpath, self.folder_exports)
+                        .map(|(key, target, matched)| (key, Some(target), matched)),
+                    // A string, an array, or an object with conditions is the entry point of
+                    // the package.
+                    None if subpath == "." => Some((".", Some(exports), Match::Exact)),
+                    None => None,
+                }
+            }
+        };
+        let (key, target, matched) = matched.ok_or_else(not_exported)?;
+        let target = target.ok_or_else(not_exported)?;
+        let folder_key = match matched {
+            Match::Folder(_) => Some(key.to_string()),
+            _ => None,
+        };
+
+        match self
+            .resolve_package_target(pkg_dir, target, matched, false)
+            .map_err(InternalError::into_public)?
+        {
+            Target::Path(path) => Ok((path, folder_key)),
+            Target::Blocked | Target::NoMatch | Target::Package(_) => Err(not_exported()),
+        }
+    }
+
+    /// Resolve a `#` specifier using the "imports" field of the package that contains
+    /// `basedir`. Targets that refer to other packages are resolved from the package directory
+    /// like any other bare specifier.
+    pub(crate) fn resolve_package_imports(
+        &self,
+        target: &str,
+        basedir: &Path,
+        ctx: &mut Context,
+    ) -> Result<PathBuf, Error> {
+        if target == "#" || target.starts_with("#/") {
+            return Err(Error::InvalidSpecifier {
+                specifier: target.to_string(),
+                reason: "subpath imports must have a name after the `#`",
+            });
+        }
+
+        let pkg_path = self.find_package_scope(basedir, ctx);
+        let pkg_dir = pkg_path.as_deref().and_then(Path::parent);
+        let not_defined = || Error::PackageImportNotDefined {
+            specifier: target.to_string(),
+            package_dir: pkg_dir.map(Path::to_path_buf),
+        };
+        let (pkg_path, pkg_dir) = match (pkg_path.as_deref(), pkg_dir) {
+            (Some(pkg_path), Some(pkg_dir)) => (pkg_path, pkg_dir),
+            _ => return Err(not_defined()),
+        };
+
+        let pkg = self.load_package_json(pkg_path, ctx)?;
+        let imports = pkg["imports"].as_object().ok_or_else(not_defined)?;
+        let (key, import, matched) =
+            match_subpath(imports, target, self.folder_exports).ok_or_else(not_defined)?;
+        if let Match::Folder(_) = matched {
+            ctx.warn(Warning::FolderMapping {
+                path: pkg_path.to_path_buf(),
+                key: key.to_string(),
+            });
+        }
+
+        let result = match self.resolve_package_target(pkg_dir, import, matched, true) {
+            Ok(Target::Path(path)) => self.resolve_target_file(path, ctx),
+            Ok(Target::Package(specifier)) => {
+                return self
+                    .with_basedir(pkg_dir.to_path_buf())
+                    .resolve_inner(&specifier, ctx);
+            }
+            Ok(Target::Blocked) | Ok(Target::NoMatch) => return Err(not_defined()),
+            Err(err) => Err(err),
+        };
+
+        result
+            .and_then(|path| self.normalize(&path))
+            .map_err(|err| not_found(err, target, basedir, ctx))
+    }
+
+    /// Check that the file an "exports" or "imports" target points to exists.
+    fn resolve_target_file(
+        &self,
+        path: PathBuf,
+        ctx: &mut Context,
+    ) -> Result<PathBuf, InternalError> {
+        if let Some(path) = self.try_typescript_file(&path, ctx) {
+            Ok(path)
+        } else if self.try_file(&path, ctx) {
+            Ok(path)
+        } else {
+            Err(Error::IOError(IOError::new(IOErrorKind::NotFound, "Not Found")).into())
+        }
+    }
+
+    /// Resolve a target in the "exports" or "imports" field to a path inside `pkg_dir`.
+    /// `matched` describes how the subpath matched the key of the target. Targets in the
+    /// "imports" field may also refer to other packages.
+    fn resolve_package_target(
+        &self,
+        pkg_dir: &Path,
+        target: &Value,
+        matched: Match,
+        imports: bool,
+    ) -> Result<Target, InternalError> {
+        let invalid_target = || Error::InvalidPackageTarget {
+            package_dir: pkg_dir.to_path_buf(),
+            target: target.to_string(),
+        };
+
+        match target {
+            Value::String(target) if imports && is_package_target(target) => {
+                let specifier = expand_target(target, matched).ok_or_else(invalid_target)?;
+                Ok(Target::Package(specifier))
+            }
+            Value::String(target) => {
+                let relative = target
+                    .strip_prefix("./")
+                    .filter(|relative| !has_invalid_segments(relative))
+                    .ok_or_else(invalid_target)?;
+                if let Match::Pattern(rest) | Match::Folder(rest) = matched {
+                    if has_invalid_segments(rest) {
+                        return Err(Error::InvalidSpecifier {
+                            specifier: rest.to_string(),
+                            reason: "pattern match contains `.`, `..`, or `node_modules` segments",
+                        }
+                        .into());
+                    }
+                }
+                let relative = expand_target(relative, matched).ok_or_else(invalid_target)?;
+                Ok(Target::Path(pkg_dir.join(relative)))
+            }
+            Value::Array(fallbacks) => {
+                let mut last_error = None;
+                for fallback in fallbacks {
+                    match self.resolve_package_target(pkg_dir, fallback, matched, imports) {
+                        Ok(Target::NoMatch) => continue,
+                        Ok(resolved) => return Ok(resolved),
+                        Err(err @ InternalError::Public(Error::InvalidPackageTarget { .. })) => {
+                            last_error = Some(err);
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+                last_error.map_or(Ok(Target::NoMatch), Err)
+            }
+            Value::Object(conditions) => {
+                if conditions.keys().any(|key| key.parse::<u32>().is_ok()) {
+                    return Err(Error::InvalidPackageConfig {
+                        path: pkg_dir.join("package.json"),
+                        reason: "\"exports\" conditions cannot be array indices",
+                    }
+                    .into());
+                }
+                // Conditions are checked in the order they are listed in the package.json file,
+                // not in the order of the resolver's conditions. A matching condition that
+                // leads to nested conditions without a match falls through to the next one.
+                for (condition, target) in conditions {
+                    if condition != "default" && !self.conditions.contains(condition) {
+                        continue;
+                    }
+                    match self.resolve_package_target(pkg_dir, target, matched, imports)? {
+                        Target::NoMatch => continue,
+                        resolved => return Ok(resolved),
+                    }
+                }
+                Ok(Target::NoMatch)
+            }
+            Value::Null => Ok(Target::Blocked),
+            _ => Err(invalid_target().into()),
+        }
+    }
+}
+
+/// Get the subpaths defined in an "exports" field. Returns `None` if the field only describes
+/// the package entry point, which is the case if it is not an object with `.`-prefixed keys.
+fn exports_subpaths(exports: &Value) -> Result<Option<&Map<String, Value>>, &'static str> {
+    let map = match exports.as_object() {
+        Some(map) => map,
+        None => return Ok(None),
+    };
+
+    let subpath_keys = map.keys().filter(|key| key.starts_with('.')).count();
+    if subpath_keys == 0 {
+        Ok(None)
+    } else if subpath_keys == map.len() {
+        Ok(Some(map))
+    } else {
+        Err("\"exports\" cannot contain both subpaths and conditions")
+    }
+}
+
+/// Find the key and target for `subpath`. Exact matches win over patterns. When several
+/// patterns match, the one with the longest prefix before the `*` wins, and then the longest
+/// key, regardless of the order in the package.json file. If `folders` is true, keys ending in
+/// `/` match every subpath inside that folder, like they did in older Node.js versions.
+fn match_subpath<'a, 's>(
+    subpaths: &'a Map<String, Value>,
+    subpath: &'s str,
+    folders: bool,
+) -> Option<(&'a str, &'a Value, Match<'s>)> {
+    if !subpath.contains('*') {
+        if let Some((key, target)) = subpaths.get_key_value(subpath) {
+            return Some((key, target, Match::Exact));
+        }
+    }
+
+    subpaths
+        .iter()
+        .filter_map(|(key, target)| {
+            let matched = match_key(key, subpath, folders)?;
+            Some((key.as_str(), target, matched))
+        })
+        // `min_by` keeps the first of equally specific keys.
+        .min_by(|(a, ..), (b, ..)| pattern_key_compare(a, b))
+}
+
+/// Check if `subpath` matches a subpath pattern, or a folder mapping if `folders` is true.
+fn match_key<'s>(key: &str, subpath: &'s str, folders: bool) -> Option<Match<'s>> {
+    match split_pattern(key) {
+        // The `*` must stand for at least one character.
+        Some((prefix, trailer))
+            if subpath.len() >= key.len()
+                && subpath.starts_with(prefix)
+                && subpath.ends_with(trailer) =>
+        {
+            Some(Match::Pattern(
+                &subpath[prefix.len()..subpath.len() - trailer.len()],
+            ))
+        }
+        None if folders && key.ends_with('/') && !key.contains('*') => {
+            Some(Match::Folder(subpath.strip_prefix(key)?))
+        }
+        _ => None,
+    }
+}
+
+/// Apply `conditions` to an "exports" target ahead of time, keeping only the first branch of
+/// each condition object that leads to a target, like `resolve_package_target` picks it.
+/// Returns `None` if no condition matches, also not in any of the fallbacks of an array.
+/// Condition objects with array index keys are kept, so the error is reported when they are
+/// used.
+fn select_conditions(target: &Value, conditions: &[String]) -> Option<Value> {
+    match target {
+        Value::Array(fallbacks) => {
+            let fallbacks: Vec<Value> = fallbacks
+                .iter()
+                .filter_map(|fallback| select_conditions(fallback, conditions))
+                .collect();
+            if fallbacks.is_empty() {
+                None
+            } else {
+                Some(Value::Array(fallbacks))
+            }
+        }
+        Value::Object(branches) if !branches.keys().any(|key| key.parse::<u32>().is_ok()) => {
+            branches
+                .iter()
+                .filter(|(condition, _)| {
+                    *condition == "default" || conditions.contains(condition)
+                })
+                .find_map(|(_, target)| select_conditions(target, conditions))
+        }
+        _ => Some(target.clone()),
+    }
+}
+
+/// Order subpath patterns and folder mappings from most to least specific, like
+/// `PATTERN_KEY_COMPARE` in the Node.js resolution algorithm.
+fn pattern_key_compare(a: &str, b: &str) -> Ordering {
+    let base_length = |key: &str| key.find('*').map_or(key.len(), |star| star + 1);
+    base_length(b)
+        .cmp(&base_length(a))
+        .then_with(|| b.contains('*').cmp(&a.contains('*')))
+        .then_with(|| b.len().cmp(&a.len()))
+}
+
+/// Apply a match to a target. Returns `None` if a folder mapping points to something that is
+/// not a folder.
+fn expand_target(target: &str, matched: Match) -> Option<String> {
+    match matched {
+        Match::Exact => Some(target.to_string()),
+        Match::Pattern(pattern_match) => Some(target.replace('*', pattern_match)),
+        Match::Folder(rest) if target.ends_with('/') => Some(format!("{}{}", target, rest)),
+        Match::Folder(_) => None,
+    }
+}
+
+/// Split a subpath pattern at its `*`. Returns `None` if `key` does not contain exactly one `*`.
+fn split_pattern(key: &str) -> Option<(&str, &str)> {
+    let star = key.find('*')?;
+    let (prefix, trailer) = (&key[..star], &key[star + 1..]);
+    if trailer.contains('*') {
+        None
+    } else {
+        Some((prefix, trailer))
+    }
+}
+
+/// Check if an "imports" target refers to another package instead of a file.
+fn is_package_target(target: &str) -> bool {
+    !target.starts_with("./")
+        && !target.starts_with("../")
+        && !target.starts_with('/')
+        && !target.starts_with('#')
+        && !target.contains(':')
+}
+
+/// Check if a relative path contains segments that could escape the package folder or reach
+/// into its dependencies.
+fn has_invalid_segments(path: &str) -> bool {
+    path.split(['/', '\\']).any(|segment| {
+        segment == "." || segment == ".." || segment.eq_ignore_ascii_case("node_modules")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{match_subpath, CompiledExports, ExportsCache, Match::*, MIN_PRUNE_AT};
+    use crate::{Error, MemoryCache, Resolver, Warning};
+    use serde_json::{json, Map, Value};
+    use std::env;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    fn fixture(part: &str) -> PathBuf {
+        env::current_dir().unwrap().join("fixtures/exports").join(part)
+    }
+
+    #[test]
+    fn resolves_exports() {
+        let resolver = Resolver::default().with_basedir(fixture(""));
+        let pkg = fixture("node_modules/exports");
+
+        assert_eq!(pkg.join("main.js"), resolver.resolve("exports").unwrap());
+        assert_eq!(
+            pkg.join("lib/feature.js"),
+            resolver.resolve("exports/feature").unwrap()
+        );
+        assert_eq!(
+            pkg.join("lib/utils/string.js"),
+            resolver.resolve("exports/utils/string").unwrap()
+        );
+        assert_eq!(
+            pkg.join("require.js"),
+            resolver.resolve("exports/conditional").unwrap()
+        );
+        assert_eq!(
+            pkg.join("import.js"),
+            Resolver::node_esm()
+                .with_basedir(fixture(""))
+                .resolve("exports/conditional")
+                .unwrap()
+        );
+        assert_eq!(
+            pkg.join("main.js"),
+            resolver.resolve("exports/fallback").unwrap()
+        );
+
+        // Files that exist but are not exported can not be resolved.
+        assert!(matches!(
+            resolver.resolve("exports/lib/feature.js"),
+            Err(Error::PackagePathNotExported { ref subpath, .. }) if subpath == "./lib/feature.js"
+        ));
+        assert!(matches!(
+            resolver.resolve("exports/missing"),
+            Err(Error::NotFound { .. })
+        ));
+        assert!(matches!(
+            resolver.resolve("exports/invalid"),
+            Err(Error::InvalidPackageTarget { .. })
+        ));
+    }
+
+    #[test]
+    fn compiles_exports() {
+        let fields = [
+            json!("./main.js"),
+            json!({ "node": { "import": "./a.mjs" }, "default": "./b.js" }),
+            json!({ ".": [{ "import": "./a.mjs" }, "./b.js"], "./c": [{ "import": "./c.mjs" }] }),
+            json!({ "./*": "./lib/*.js", "./lib/*": null, "./dir/": "./dir/", "./x": "./x.js" }),
+            json!({ "./*.js": { "require": "./cjs/*.js", "default": "./esm/*.js" } }),
+            json!({ ".": { "0": "./zero.js" } }),
+            json!({ ".": "./main.js", "node": "./node.js" }),
+            json!({ "./a": 1, "./b": ["bad", "./b.js"] }),
+            json!({
+                "./*": "./lib/*.js",
+                "./x": { "browser": "./b.js" },
+                "./features/*": { "browser": "./b/*.js" }
+            }),
+        ];
+        let subpaths = [
+            ".",
+            "./c",
+            "./x",
+            "./lib/a",
+            "./dir/file.js",
+            "./a",
+            "./b",
+            "./f.js",
+            "./features/y",
+        ];
+        let resolver = Resolver::default().folder_exports(true);
+        for exports in &fields {
+            let compiled = CompiledExports::compile(exports, &resolver.conditions);
+            for subpath in subpaths {
+                let pkg_dir = Path::new("/pkg");
+                assert_eq!(
+                    resolver.match_exports(pkg_dir, exports, None, subpath),
+                    match &compiled {
+                        Some(compiled) => {
+                            resolver.match_exports(pkg_dir, exports, Some(compiled), subpath)
+                        }
+                        None => resolver.match_exports(pkg_dir, exports, None, subpath),
+                    },
+                    "{} in {}",
+                    subpath,
+                    exports
+                );
+            }
+        }
+
+        let resolver = Resolver::default()
+            .cache(Arc::new(MemoryCache::default()))
+            .with_basedir(fixture(""));
+        let pkg = fixture("node_modules/exports");
+        for _ in 0..2 {
+            assert_eq!(Ok(pkg.join("main.js")), resolver.resolve("exports"));
+            assert_eq!(Ok(pkg.join("lib/feature.js")), resolver.resolve("exports/feature"));
+            assert_eq!(Ok(pkg.join("require.js")), resolver.resolve("exports/conditional"));
+        }
+        assert_eq!(1, resolver.compiled_exports.len());
+        resolver.clone().compile().clear_cache();
+        assert_eq!(0, resolver.compiled_exports.len());
+
+        // Entries do not keep package.json files alive, and are pruned once they are dropped.
+        let exports_cache = ExportsCache::default();
+        for i in 0..MIN_PRUNE_AT {
+            let pkg = Arc::new(json!({ "exports": "./main.js" }));
+            let pkg_path = Path::new("/pkg").join(i.to_string()).join("package.json");
+            assert!(exports_cache.get(&pkg_path, &pkg, &resolver.conditions).is_some());
+        }
+        assert_eq!(1, exports_cache.len());
+        let esm = Resolver::node_esm()
+            .cache(Arc::new(MemoryCache::default()))
+            .with_basedir(fixture(""));
+        assert_eq!(Ok(pkg.join("import.js")), esm.resolve("exports/conditional"));
+    }
+
+    #[test]
+    fn resolves_nested_conditions() {
+        let pkg = fixture("node_modules/nested");
+        let cjs = Resolver::default().with_basedir(fixture(""));
+        let esm = Resolver::node_esm().with_basedir(fixture(""));
+        let web = Resolver::webpack_web().with_basedir(fixture(""));
+
+        assert_eq!(pkg.join("cjs/index.cjs"), cjs.resolve("nested").unwrap());
+        assert_eq!(pkg.join("esm/index.mjs"), esm.resolve("nested").unwrap());
+        assert_eq!(pkg.join("browser.js"), web.resolve("nested").unwrap());
+        assert_eq!(
+            pkg.join("browser.js"),
+            Resolver::default()
+                .conditions(&["node"])
+                .with_basedir(fixture(""))
+                .resolve("nested")
+                .unwrap()
+        );
+
+        // "default" is listed first, so it wins over "node".
+        assert_eq!(
+            pkg.join("browser.js"),
+            cjs.resolve("nested/ordered").unwrap()
+        );
+        assert!(matches!(
+            cjs.resolve("nested/numeric"),
+            Err(Error::InvalidPackageConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn resolves_imports() {
+        let root = env::current_dir().unwrap().join("fixtures/imports");
+        let resolver = Resolver::default().with_basedir(root.join("src"));
+
+        assert_eq!(
+            root.join("src/internal/helper.js"),
+            resolver.resolve("#internal/helper").unwrap()
+        );
+        assert_eq!(root.join("src/node.js"), resolver.resolve("#env").unwrap());
+        assert_eq!(
+            root.join("src/default.js"),
+            Resolver::webpack_web()
+                .with_basedir(root.clone())
+                .resolve("#env")
+                .unwrap()
+        );
+
+        // Targets in other packages go through their "exports" field.
+        assert_eq!(
+            root.join("node_modules/dep/lib/feature.js"),
+            resolver.resolve("#dep").unwrap()
+        );
+        assert_eq!(
+            root.join("node_modules/dep/lib/other.js"),
+            resolver.resolve("#dep/other").unwrap()
+        );
+        assert!(matches!(
+            resolver.resolve("#dep/lib/other.js"),
+            Err(Error::PackagePathNotExported { .. })
+        ));
+        assert_eq!(
+            PathBuf::from("events"),
+            resolver.resolve("#events").unwrap()
+        );
+
+        assert!(matches!(
+            resolver.resolve("#missing"),
+            Err(Error::NotFound { .. })
+        ));
+        assert!(matches!(
+            resolver.resolve("#blocked"),
+            Err(Error::PackageImportNotDefined { .. })
+        ));
+        assert!(matches!(
+            resolver.resolve("#undefined"),
+            Err(Error::PackageImportNotDefined { package_dir: Some(ref dir), .. }) if *dir == root
+        ));
+        assert!(matches!(
+            resolver.resolve("#outside"),
+            Err(Error::InvalidPackageTarget { .. })
+        ));
+        assert!(matches!(
+            resolver.resolve("#/root"),
+            Err(Error::InvalidSpecifier { .. })
+        ));
+    }
+
+    #[test]
+    fn orders_patterns() {
+        let resolver = Resolver::default().with_basedir(fixture(""));
+        let pkg = fixture("node_modules/patterns");
+
+        // The longest prefix wins, even when a shorter one is listed first.
+        assert_eq!(pkg.join("lib/main.js"), resolver.resolve("patterns/main").unwrap());
+        assert_eq!(
+            pkg.join("utils/string.js"),
+            resolver.resolve("patterns/utils/string").unwrap()
+        );
+        // With equal prefixes, the longest key wins.
+        assert_eq!(
+            pkg.join("data/config.json"),
+            resolver.resolve("patterns/config.json").unwrap()
+        );
+    }
+
+    /// Subpath matching cases from the Node.js resolution algorithm and its test suite.
+    #[test]
+    fn matches_subpaths_like_node() {
+        let exports = json!({
+            "./*": "root",
+            "./features/*": "features",
+            "./features/*.js": "features-js",
+            "./features/private/*": "private",
+            "./features/special.js": "special",
+            "./a/*/c": "trailer",
+            "./a/b/*": "prefix",
+            "./multi/*/*": "multi",
+            "./star*": "literal-star",
+        });
+        let exports = exports.as_object().unwrap();
+        let matched = |subpath| {
+            match_subpath(exports, subpath, false)
+                .map(|(_, target, matched)| (target.as_str().unwrap(), matched))
+        };
+
+        // Literal keys beat patterns.
+        assert_eq!(Some(("special", Exact)), matched("./features/special.js"));
+        // Longest prefix first, then the longest key.
+        assert_eq!(Some(("features-js", Pattern("x"))), matched("./features/x.js"));
+        assert_eq!(Some(("features", Pattern("x.cjs"))), matched("./features/x.cjs"));
+        assert_eq!(Some(("private", Pattern("x.js"))), matched("./features/private/x.js"));
+        assert_eq!(Some(("prefix", Pattern("c"))), matched("./a/b/c"));
+        // Trailers must match, and the `*` must stand for something.
+        assert_eq!(Some(("trailer", Pattern("x"))), matched("./a/x/c"));
+        assert_eq!(Some(("root", Pattern("a/x/d"))), matched("./a/x/d"));
+        assert_eq!(Some(("features", Pattern(".js"))), matched("./features/.js"));
+        assert_eq!(Some(("root", Pattern("features/"))), matched("./features/"));
+        // Keys with more than one `*` are ignored.
+        assert_eq!(Some(("root", Pattern("multi/x/y"))), matched("./multi/x/y"));
+        // Specifiers that contain a `*` only match patterns.
+        assert_eq!(Some(("literal-star", Pattern("*"))), matched("./star*"));
+        assert_eq!(None, matched("."));
+        assert_eq!(None, matched("./"));
+
+        // The order of the keys does not matter.
+        let reversed: Map<String, Value> = exports
+            .iter()
+            .rev()
+            .map(|(key, target)| (key.clone(), target.clone()))
+            .collect();
+        for subpath in ["./features/x.js", "./features/private/x.js", "./a/b/c", "./a/x/c"] {
+            assert_eq!(
+                match_subpath(exports, subpath, false),
+                match_subpath(&reversed, subpath, false)
+            );
+        }
+    }
+
+    #[test]
+    fn matches_folder_mappings() {
+        let exports = json!({
+            "./*": "root",
+            "./lib/": "lib",
+            "./lib/*": "lib-pattern",
+            "./lib/internal/": "internal",
+        });
+        let exports = exports.as_object().unwrap();
+        let matched = |subpath| {
+            match_subpath(exports, subpath, true)
+                .map(|(_, target, matched)| (target.as_str().unwrap(), matched))
+        };
+
+        // Patterns win over folders with the same prefix, but longer folders win over both.
+        assert_eq!(Some(("lib-pattern", Pattern("x.js"))), matched("./lib/x.js"));
+        assert_eq!(Some(("internal", Folder("x.js"))), matched("./lib/internal/x.js"));
+        assert_eq!(Some(("root", Pattern("other.js"))), matched("./other.js"));
+        assert_eq!(
+            Some(("./lib/*", Pattern("internal/x.js"))),
+            match_subpath(exports, "./lib/internal/x.js", false).map(|(key, _, m)| (key, m))
+        );
+    }
+
+    #[test]
+    fn resolves_folder_exports() {
+        let pkg = fixture("node_modules/folders");
+        let resolver = Resolver::default()
+            .folder_exports(true)
+            .with_basedir(fixture(""));
+
+        let resolution = resolver.resolve_full("folders/lib/util.js").unwrap();
+        assert_eq!(pkg.join("lib/util.js"), resolution.path);
+        assert_eq!(
+            vec![Warning::FolderMapping {
+                path: pkg.join("package.json"),
+                key: String::from("./lib/"),
+            }],
+            resolution.warnings
+        );
+        assert!(matches!(
+            resolver.resolve("folders/invalid/util.js"),
+            Err(Error::InvalidPackageTarget { .. })
+        ));
+        assert!(matches!(
+            Resolver::default()
+                .with_basedir(fixture(""))
+                .resolve("folders/lib/util.js"),
+            Err(Error::PackagePathNotExported { .. })
+        ));
+    }
+
+    #[test]
+    fn blocks_null_targets() {
+        let resolver = Resolver::default().with_basedir(fixture(""));
+
+        assert!(matches!(
+            resolver.resolve("exports/utils/internal/secret"),
+            Err(Error::PackagePathNotExported { ref subpath, .. })
+                if subpath == "./utils/internal/secret"
+        ));
+        assert!(matches!(
+            resolver.resolve("exports/blocked"),
+            Err(Error::PackagePathNotExported { .. })
+        ));
+    }
+}
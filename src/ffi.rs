@@ -0,0 +1,286 @@
+//! A C interface, so tools written in other languages can embed the resolver.
+//!
+//! Build a shared library with `cargo rustc --release --features ffi --crate-type cdylib`,
+//! and declare the functions like this:
+//!
+//! ```c
+//! char *node_resolve_resolve(const char *specifier, const char *basedir,
+//!                            const char *options_json);
+//! const char *node_resolve_last_error(void);
+//! void node_resolve_free(char *path);
+//! ```
+
+use crate::{Resolver, Target};
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, UnwindSafe};
+use std::path::PathBuf;
+use std::ptr;
+
+thread_local! {
+    /// The message of the last error on this thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Resolve `specifier` from the directory `basedir`, with the options in the JSON object
+/// `options_json`. `options_json` may be null to use the default options.
+///
+/// Returns the resolved path as a string that must be freed with [`node_resolve_free`], or
+/// null if the specifier could not be resolved or the resolver panicked. Then
+/// [`node_resolve_last_error`] describes the problem. Every call creates a new resolver, so
+/// nothing is cached between calls.
+///
+/// The options object accepts these keys, which correspond to the [`Resolver`] methods of
+/// the same name:
+///
+/// - `"target"`: `"browser"`, `"node"`, `"worker"`, or `"deno"`;
+/// - `"extensions"`, `"mainFields"`, `"conditions"`, and `"moduleDirectories"`: arrays of
+///   strings;
+/// - `"preserveSymlinks"` and `"fullySpecified"`: booleans.
+///
+/// This requires the `ffi` feature.
+///
+/// # Safety
+///
+/// `specifier` and `basedir` must point to NUL-terminated strings, and `options_json` must
+/// be null or point to a NUL-terminated string. The strings must be valid UTF-8.
+#[no_mangle]
+pub unsafe extern "C" fn node_resolve_resolve(
+    specifier: *const c_char,
+    basedir: *const c_char,
+    options_json: *const c_char,
+) -> *mut c_char {
+    let result = catch_panic(|| resolve(specifier, basedir, options_json)).and_then(|path| {
+        let path = path.to_str().ok_or("the resolved path is not valid UTF-8")?;
+        CString::new(path).map_err(|_| String::from("the resolved path contains a NUL byte"))
+    });
+    match result {
+        Ok(path) => {
+            set_last_error(None);
+            path.into_raw()
+        }
+        Err(message) => {
+            set_last_error(Some(message));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Get the message of the last error of [`node_resolve_resolve`] on the current thread, or
+/// null if the last call succeeded. The string is owned by the library, and is valid until
+/// the next call to [`node_resolve_resolve`] on the same thread.
+///
+/// This requires the `ffi` feature.
+#[no_mangle]
+pub extern "C" fn node_resolve_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| match &*last_error.borrow() {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+/// Free a path that was returned by [`node_resolve_resolve`]. Null pointers are ignored.
+///
+/// This requires the `ffi` feature.
+///
+/// # Safety
+///
+/// `path` must be null or a pointer that [`node_resolve_resolve`] returned, and it must not
+/// be used or freed again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn node_resolve_free(path: *mut c_char) {
+    if !path.is_null() {
+        drop(CString::from_raw(path));
+    }
+}
+
+/// Call `f`, and turn a panic into an error, because unwinding into the caller of an
+/// `extern "C"` function aborts the process.
+fn catch_panic<T, F>(f: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, String> + UnwindSafe,
+{
+    panic::catch_unwind(f).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic");
+        Err(format!("the resolver panicked: {}", message))
+    })
+}
+
+/// Remember the message of the last error on this thread.
+fn set_last_error(message: Option<String>) {
+    let message = message.map(|message| {
+        CString::new(message.replace('\0', " ")).expect("NUL bytes were removed")
+    });
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+}
+
+/// Read a string argument.
+///
+/// # Safety
+///
+/// `string` must be null or point to a NUL-terminated string.
+unsafe fn read_str<'a>(string: *const c_char, name: &str) -> Result<&'a str, String> {
+    if string.is_null() {
+        return Err(format!("{} is null", name));
+    }
+    CStr::from_ptr(string)
+        .to_str()
+        .map_err(|_| format!("{} is not valid UTF-8", name))
+}
+
+/// Resolve the arguments of [`node_resolve_resolve`].
+///
+/// # Safety
+///
+/// See [`node_resolve_resolve`].
+unsafe fn resolve(
+    specifier: *const c_char,
+    basedir: *const c_char,
+    options_json: *const c_char,
+) -> Result<PathBuf, String> {
+    let specifier = read_str(specifier, "specifier")?;
+    let basedir = read_str(basedir, "basedir")?;
+    let options = if options_json.is_null() {
+        Map::new()
+    } else {
+        match serde_json::from_str(read_str(options_json, "options_json")?) {
+            Ok(Value::Object(options)) => options,
+            Ok(_) => return Err(String::from("options_json must be a JSON object")),
+            Err(err) => return Err(format!("invalid options_json: {}", err)),
+        }
+    };
+
+    configure(&options)?
+        .with_basedir(PathBuf::from(basedir))
+        .resolve(specifier)
+        .map_err(|err| err.to_string())
+}
+
+/// Create a resolver with the options from an options object.
+fn configure(options: &Map<String, Value>) -> Result<Resolver, String> {
+    let strings = |key: &str, value: &Value| -> Result<Vec<String>, String> {
+        value
+            .as_array()
+            .and_then(|values| {
+                values
+                    .iter()
+                    .map(|value| value.as_str().map(String::from))
+                    .collect()
+            })
+            .ok_or_else(|| format!("option \"{}\" must be an array of strings", key))
+    };
+    let boolean = |key: &str, value: &Value| {
+        value
+            .as_bool()
+            .ok_or_else(|| format!("option \"{}\" must be a boolean", key))
+    };
+
+    // The target sets several options at once, so it is applied first.
+    let mut resolver = match options.get("target").map(|target| target.as_str()) {
+        None => Resolver::default(),
+        Some(Some("browser")) => Resolver::default().target(Target::Browser),
+        Some(Some("node")) => Resolver::default().target(Target::Node),
+        Some(Some("worker")) => Resolver::default().target(Target::Worker),
+        Some(Some("deno")) => Resolver::default().target(Target::Deno),
+        Some(_) => {
+            return Err(String::from(
+                "option \"target\" must be \"browser\", \"node\", \"worker\", or \"deno\"",
+            ))
+        }
+    };
+    for (key, value) in options {
+        resolver = match key.as_str() {
+            "target" => resolver,
+            "extensions" => resolver.extensions(strings(key, value)?),
+            "mainFields" => resolver.main_fields(strings(key, value)?),
+            "conditions" => resolver.conditions(strings(key, value)?),
+            "moduleDirectories" => resolver.module_directories(strings(key, value)?),
+            "preserveSymlinks" => resolver.preserve_symlinks(boolean(key, value)?),
+            "fullySpecified" => resolver.fully_specified(boolean(key, value)?),
+            _ => return Err(format!("unknown option \"{}\"", key)),
+        };
+    }
+    Ok(resolver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    /// Call `node_resolve_resolve`, and convert the result or the error to a string.
+    fn call(specifier: &str, basedir: &str, options: Option<&str>) -> Result<String, String> {
+        let specifier = CString::new(specifier).unwrap();
+        let basedir = CString::new(basedir).unwrap();
+        let options = options.map(|options| CString::new(options).unwrap());
+        unsafe {
+            let path = node_resolve_resolve(
+                specifier.as_ptr(),
+                basedir.as_ptr(),
+                options.as_ref().map_or(ptr::null(), |options| options.as_ptr()),
+            );
+            if path.is_null() {
+                let error = CStr::from_ptr(node_resolve_last_error());
+                return Err(error.to_str().unwrap().to_string());
+            }
+            assert!(node_resolve_last_error().is_null());
+            let result = CStr::from_ptr(path).to_str().unwrap().to_string();
+            node_resolve_free(path);
+            Ok(result)
+        }
+    }
+
+    #[test]
+    fn resolves_through_c_abi() {
+        let root = env::current_dir().unwrap().join("fixtures/target");
+        let basedir = root.to_str().unwrap();
+        let resolved = |path: &str| Ok(root.join(path).to_str().unwrap().to_string());
+
+        assert_eq!(resolved("node_modules/env/node.js"), call("env", basedir, None));
+        assert_eq!(
+            resolved("node_modules/env/browser.js"),
+            call("env", basedir, Some(r#"{ "target": "browser" }"#))
+        );
+        assert_eq!(
+            resolved("node_modules/legacy/main.js"),
+            call("legacy", basedir, Some(r#"{ "target": "browser", "mainFields": ["main"] }"#))
+        );
+
+        assert!(call("missing", basedir, None)
+            .unwrap_err()
+            .starts_with("cannot find module \"missing\""));
+        assert_eq!(
+            Err(String::from("unknown option \"mainField\"")),
+            call("env", basedir, Some(r#"{ "mainField": ["main"] }"#))
+        );
+        assert_eq!(
+            Err(String::from("option \"extensions\" must be an array of strings")),
+            call("env", basedir, Some(r#"{ "extensions": ".js" }"#))
+        );
+        assert!(call("env", basedir, Some("[]")).is_err());
+        unsafe {
+            let specifier = CString::new("env").unwrap();
+            assert!(node_resolve_resolve(specifier.as_ptr(), ptr::null(), ptr::null()).is_null());
+            node_resolve_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn reports_panics() {
+        assert_eq!(Ok(1), catch_panic(|| Ok(1)));
+        assert_eq!(
+            Err(String::from("the resolver panicked: broken")),
+            catch_panic::<(), _>(|| panic!("broken"))
+        );
+        assert_eq!(
+            Err(String::from("the resolver panicked: broken 2")),
+            catch_panic::<(), _>(|| panic!("broken {}", 2))
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Resolution results as URLs, for ES module loaders.
+
+use crate::{is_core_module, is_path_specifier, Context, Error, Resolver};
+use std::io::{Error as IOError, ErrorKind as IOErrorKind};
+use std::path::{Path, PathBuf};
+use url::Url;
+
+impl Resolver {
+    /// Resolve an `import` specifier to a URL, like `import.meta.resolve()` does in Node.js.
+    ///
+    /// Files become `file://` URLs, with special characters in the path percent-encoded. Node.js
+    /// builtin modules become `node:` URLs. Builtins of other runtimes are returned as is if
+    /// they already look like a URL, such as `bun:sqlite`. Relative results are made absolute
+    /// using the current working directory of the [environment](Resolver::environment).
+    ///
+    /// This requires the `url` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().with_basedir(PathBuf::from("/path/to/project"));
+    /// assert_eq!("node:events", resolver.resolve_url("events").unwrap().as_str());
+    /// ```
+    pub fn resolve_url(&self, target: &str) -> Result<Url, Error> {
+        let path = self.resolve_with_context(target, &mut Context::default())?;
+        if path.is_absolute() {
+            return to_file_url(&path);
+        }
+
+        let specifier = path.to_string_lossy();
+        if self.runtime.is_builtin(&specifier) {
+            let name = specifier.strip_prefix("node:").unwrap_or(&specifier);
+            if is_core_module(name) {
+                return Url::parse(&format!("node:{}", name)).map_err(|_| invalid_url(&specifier));
+            }
+            return Url::parse(&specifier).map_err(|_| Error::InvalidSpecifier {
+                specifier: specifier.to_string(),
+                reason: "builtin module cannot be represented as a URL",
+            });
+        }
+
+        match self.environment.current_dir() {
+            Some(cwd) => to_file_url(&cwd.join(&path)),
+            None => Err(invalid_url(&specifier)),
+        }
+    }
+
+    /// Resolve an `import` specifier from the module at `parent_url`, like
+    /// `import.meta.resolve()` does in Node.js.
+    ///
+    /// Relative and absolute specifiers are resolved as URLs against `parent_url`, so they may
+    /// contain percent-encoded characters, and they must point to a file exactly, unless
+    /// [`SpecifierResolution::Node`](crate::SpecifierResolution::Node) is used. `node:` and
+    /// `data:` URLs are returned as is. Bare specifiers are looked up in node_modules folders
+    /// relative to `parent_url`, which must be a `file://` URL. Use a resolver created with
+    /// [`Resolver::node_esm`] to match the conditions that Node.js uses for `import`.
+    ///
+    /// This requires the `url` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    /// use url::Url;
+    ///
+    /// let parent = Url::parse("file:///path/to/project/index.js").unwrap();
+    /// let resolver = Resolver::node_esm();
+    /// assert_eq!(
+    ///     "node:events",
+    ///     resolver.resolve_import_meta("events", &parent).unwrap().as_str()
+    /// );
+    /// ```
+    pub fn resolve_import_meta(&self, specifier: &str, parent_url: &Url) -> Result<Url, Error> {
+        let invalid = |reason| Error::InvalidSpecifier {
+            specifier: specifier.to_string(),
+            reason,
+        };
+
+        let url = if is_path_specifier(specifier) {
+            parent_url.join(specifier).map_err(|_| invalid("invalid relative URL"))?
+        } else {
+            match Url::parse(specifier) {
+                Ok(url) => url,
+                Err(_) => {
+                    // A bare specifier.
+                    let basedir = file_url_dir(parent_url).ok_or_else(|| {
+                        invalid("bare specifiers can only be resolved from file URLs")
+                    })?;
+                    return self.esm_resolver().with_basedir(basedir).resolve_url(specifier);
+                }
+            }
+        };
+
+        match url.scheme() {
+            "file" => {
+                let path = url.to_file_path().map_err(|_| invalid("invalid file URL"))?;
+                self.esm_resolver().resolve_url(&path.to_string_lossy())
+            }
+            "node" if is_core_module(url.path()) => Ok(url),
+            "node" => Err(invalid("unknown builtin module")),
+            "data" => Ok(url),
+            _ => Err(invalid("unsupported URL scheme")),
+        }
+    }
+
+    /// Get a copy of this resolver that follows the ES module rules for specifiers.
+    fn esm_resolver(&self) -> Resolver {
+        let resolution = self.specifier_resolution;
+        self.clone().specifier_resolution(resolution)
+    }
+}
+
+/// Get the directory that contains the file at a `file://` URL.
+fn file_url_dir(url: &Url) -> Option<PathBuf> {
+    let path = url.to_file_path().ok()?;
+    path.parent().map(Path::to_path_buf)
+}
+
+/// Convert an absolute path to a `file://` URL.
+fn to_file_url(path: &Path) -> Result<Url, Error> {
+    Url::from_file_path(path).map_err(|_| invalid_url(&path.to_string_lossy()))
+}
+
+/// Create the error for a path that cannot be represented as a URL.
+fn invalid_url(path: &str) -> Error {
+    Error::IOError(IOError::new(
+        IOErrorKind::InvalidInput,
+        format!("cannot convert {} to a URL", path),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Resolver, Runtime, SpecifierResolution};
+    use std::env;
+    use std::path::Path;
+    use url::Url;
+
+    #[test]
+    fn resolves_to_urls() {
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let resolver = Resolver::default().with_basedir(fixtures.clone());
+
+        let url = resolver.resolve_url("./extensions/js-file").unwrap();
+        assert_eq!("file", url.scheme());
+        assert_eq!(
+            fixtures.join("extensions/js-file.js"),
+            url.to_file_path().unwrap()
+        );
+        assert_eq!(
+            "node:events",
+            resolver.resolve_url("events").unwrap().as_str()
+        );
+        assert_eq!(
+            "node:fs",
+            resolver.resolve_url("node:fs").unwrap().as_str()
+        );
+        assert_eq!(
+            "bun:sqlite",
+            resolver
+                .clone()
+                .target_runtime(Runtime::Bun)
+                .resolve_url("bun:sqlite")
+                .unwrap()
+                .as_str()
+        );
+    }
+
+    #[test]
+    fn resolves_import_meta() {
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let resolver = Resolver::node_esm();
+        let parent = Url::from_file_path(fixtures.join("extensions/index.js")).unwrap();
+
+        let url = resolver.resolve_import_meta("./js-file.js", &parent).unwrap();
+        assert_eq!(fixtures.join("extensions/js-file.js"), url.to_file_path().unwrap());
+        assert!(resolver.resolve_import_meta("./js-file", &parent).is_err());
+        assert_eq!(
+            "node:events",
+            resolver.resolve_import_meta("events", &parent).unwrap().as_str()
+        );
+        assert_eq!(
+            "node:fs",
+            resolver.resolve_import_meta("node:fs", &parent).unwrap().as_str()
+        );
+        assert!(resolver.resolve_import_meta("node:nope", &parent).is_err());
+        assert_eq!(
+            "data:text/javascript,export%20default%201",
+            resolver
+                .resolve_import_meta("data:text/javascript,export%20default%201", &parent)
+                .unwrap()
+                .as_str()
+        );
+
+        let parent = Url::from_file_path(fixtures.join("node-modules/package-json/index.js"));
+        let url = resolver.resolve_import_meta("dep", &parent.unwrap()).unwrap();
+        assert_eq!(
+            fixtures.join("node-modules/package-json/node_modules/dep/lib/index.js"),
+            url.to_file_path().unwrap()
+        );
+
+        let data = Url::parse("data:text/javascript,").unwrap();
+        assert!(resolver.resolve_import_meta("dep", &data).is_err());
+
+        let parent = Url::from_file_path(fixtures.join("index.js")).unwrap();
+        let url = resolver
+            .specifier_resolution(SpecifierResolution::Node)
+            .resolve_import_meta("./extensions/js-file", &parent)
+            .unwrap();
+        assert_eq!(fixtures.join("extensions/js-file.js"), url.to_file_path().unwrap());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn percent_encodes_paths() {
+        let url = super::to_file_url(Path::new("/a dir/100%/#hash?.js")).unwrap();
+        assert_eq!("file:///a%20dir/100%25/%23hash%3F.js", url.as_str());
+    }
+}
@@ -0,0 +1,543 @@
+//! Filesystem access used by the resolver.
+//!
+//! All I/O goes through the [`FileSystem`] trait, so resolution can run against something other
+//! than the real disk, like a [`SnapshotFileSystem`] built from a list of files.
+
+use crate::verbatim::long_path;
+use crate::{normalize, EntryKind};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::{Error as IOError, ErrorKind as IOErrorKind};
+use std::iter::FromIterator;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// The filesystem operations the resolver needs.
+pub trait FileSystem: Send + Sync {
+    /// Find out what exists at `path`, following symlinks.
+    fn entry_kind(&self, path: &Path) -> EntryKind;
+    /// Find out what exists at `path`, following symlinks, or fail if that can not be checked,
+    /// for example because of a missing permission. The default implementation never fails.
+    fn try_entry_kind(&self, path: &Path) -> Result<EntryKind, IOError> {
+        Ok(self.entry_kind(path))
+    }
+    /// Read the contents of a file.
+    fn read_to_string(&self, path: &Path) -> Result<String, IOError>;
+    /// Get the absolute path to an existing entry with all symlinks expanded.
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, IOError>;
+    /// List the entries in a directory.
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, IOError>;
+    /// Read the target of a symlink, without following it. Filesystems without symlinks can use
+    /// the default implementation, which always fails.
+    fn read_link(&self, path: &Path) -> Result<PathBuf, IOError> {
+        Err(IOError::new(
+            IOErrorKind::InvalidInput,
+            format!("{} is not a symlink", path.display()),
+        ))
+    }
+}
+
+/// The real filesystem. This is the default.
+///
+/// On Windows, paths that are too long for the Windows APIs get the `\\?\` prefix, so deeply
+/// nested node_modules folders can be accessed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn entry_kind(&self, path: &Path) -> EntryKind {
+        match long_path(path).metadata() {
+            Ok(metadata) if metadata.is_file() => EntryKind::File,
+            Ok(metadata) if metadata.is_dir() => EntryKind::Directory,
+            _ => EntryKind::Missing,
+        }
+    }
+
+    fn try_entry_kind(&self, path: &Path) -> Result<EntryKind, IOError> {
+        match long_path(path).metadata() {
+            Ok(metadata) if metadata.is_file() => Ok(EntryKind::File),
+            Ok(metadata) if metadata.is_dir() => Ok(EntryKind::Directory),
+            Err(err) if err.kind() == IOErrorKind::PermissionDenied => Err(err),
+            _ => Ok(EntryKind::Missing),
+        }
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String, IOError> {
+        fs::read_to_string(long_path(path))
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, IOError> {
+        long_path(path).canonicalize()
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, IOError> {
+        fs::read_dir(long_path(path))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf, IOError> {
+        fs::read_link(long_path(path))
+    }
+}
+
+/// A fixed list of files to resolve against, without touching the disk.
+///
+/// This is useful to resolve against a module map from a remote build cache or a bundler
+/// metafile, or to reproduce resolution decisions offline. Directories are implied by the
+/// paths of the files. Only files that the resolver needs to read, like package.json files,
+/// need to have contents. Paths should be absolute, and there are no symlinks.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::{Resolver, SnapshotFileSystem};
+/// use std::sync::Arc;
+///
+/// let mut snapshot = SnapshotFileSystem::new();
+/// snapshot.add_file("/project/node_modules/dep/package.json", Some(r#"{"main": "lib"}"#));
+/// snapshot.add_file("/project/node_modules/dep/lib/index.js", None);
+///
+/// assert_eq!(Ok(PathBuf::from("/project/node_modules/dep/lib/index.js")),
+///     Resolver::default()
+///         .filesystem(Arc::new(snapshot))
+///         .with_basedir(PathBuf::from("/project/src"))
+///         .resolve("dep")
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotFileSystem {
+    files: BTreeMap<PathBuf, Option<String>>,
+    directories: BTreeSet<PathBuf>,
+}
+
+impl SnapshotFileSystem {
+    /// Create an empty snapshot.
+    pub fn new() -> Self {
+        SnapshotFileSystem::default()
+    }
+
+    /// Add a file, and optionally its contents, to the snapshot.
+    pub fn add_file<P, S>(&mut self, path: P, contents: Option<S>) -> &mut Self
+    where
+        P: AsRef<Path>,
+        S: Into<String>,
+    {
+        let path = normalize(path.as_ref());
+        for dir in path.ancestors().skip(1) {
+            self.directories.insert(dir.to_path_buf());
+        }
+        self.files.insert(path, contents.map(Into::into));
+        self
+    }
+}
+
+impl<P> FromIterator<P> for SnapshotFileSystem
+where
+    P: AsRef<Path>,
+{
+    /// Create a snapshot from a list of files without contents.
+    fn from_iter<I>(paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+    {
+        let mut snapshot = SnapshotFileSystem::new();
+        for path in paths {
+            snapshot.add_file(path, None::<String>);
+        }
+        snapshot
+    }
+}
+
+impl FileSystem for SnapshotFileSystem {
+    fn entry_kind(&self, path: &Path) -> EntryKind {
+        let path = normalize(path);
+        if self.files.contains_key(&path) {
+            EntryKind::File
+        } else if self.directories.contains(&path) {
+            EntryKind::Directory
+        } else {
+            EntryKind::Missing
+        }
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String, IOError> {
+        match self.files.get(&normalize(path)) {
+            Some(Some(contents)) => Ok(contents.clone()),
+            Some(None) => Err(IOError::new(
+                IOErrorKind::InvalidData,
+                "file contents are not part of the snapshot",
+            )),
+            None => Err(IOError::new(IOErrorKind::NotFound, "Not Found")),
+        }
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, IOError> {
+        match self.entry_kind(path) {
+            EntryKind::Missing => Err(IOError::new(IOErrorKind::NotFound, "Not Found")),
+            _ => Ok(normalize(path)),
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, IOError> {
+        let path = normalize(path);
+        if !self.directories.contains(&path) {
+            return Err(IOError::new(IOErrorKind::NotFound, "Not Found"));
+        }
+        let is_child = |entry: &&PathBuf| entry.parent() == Some(&path);
+        let mut entries: Vec<PathBuf> = self
+            .files
+            .keys()
+            .filter(is_child)
+            .chain(self.directories.iter().filter(is_child))
+            .cloned()
+            .collect();
+        entries.sort();
+        Ok(entries)
+    }
+}
+
+/// A filesystem that finds entries regardless of the case of their names, on top of another
+/// filesystem.
+///
+/// When a path does not exist as it is written, the directory listings along the path are
+/// searched for names that only differ in case. This emulates the default filesystems of
+/// macOS and Windows on a case-sensitive filesystem, so Linux CI can reproduce how a project
+/// resolves there. Directory listings are cached until they are
+/// [forgotten](CaseInsensitiveFileSystem::forget). The listings of a filesystem that was
+/// wrapped with [`Resolver::case_insensitive`](crate::Resolver::case_insensitive) are
+/// forgotten along with the resolver's cache. Paths keep the case they were written with,
+/// until symlinks are expanded, which returns the case of the names on disk.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::{CaseInsensitiveFileSystem, RealFileSystem, Resolver};
+/// use std::sync::Arc;
+///
+/// let fs = CaseInsensitiveFileSystem::new(Arc::new(RealFileSystem));
+/// let resolver = Resolver::default().filesystem(Arc::new(fs));
+/// ```
+pub struct CaseInsensitiveFileSystem {
+    inner: Arc<dyn FileSystem>,
+    listings: Mutex<HashMap<PathBuf, Arc<[PathBuf]>>>,
+}
+
+impl CaseInsensitiveFileSystem {
+    /// Find entries in `inner` regardless of case.
+    pub fn new(inner: Arc<dyn FileSystem>) -> Self {
+        CaseInsensitiveFileSystem {
+            inner,
+            listings: Mutex::default(),
+        }
+    }
+
+    /// Get the filesystem underneath.
+    pub fn inner(&self) -> &Arc<dyn FileSystem> {
+        &self.inner
+    }
+
+    /// Forget the cached listings of the directory that contains `path`, of `path` itself,
+    /// and of the directories inside it, after something was created, removed, or renamed
+    /// there.
+    pub fn forget(&self, path: &Path) {
+        let path = normalize(path);
+        let parent = path.parent();
+        self.lock_listings()
+            .retain(|dir, _| !dir.starts_with(&path) && Some(dir.as_path()) != parent);
+    }
+
+    /// Forget all cached listings.
+    pub fn clear(&self) {
+        self.lock_listings().clear();
+    }
+
+    fn lock_listings(&self) -> MutexGuard<'_, HashMap<PathBuf, Arc<[PathBuf]>>> {
+        self.listings.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Get the path to the entry at `path`, with the names written in the case they have on
+    /// disk where they differ. Returns `None` if there is no such entry in any case.
+    fn find(&self, path: &Path) -> Option<PathBuf> {
+        let path = normalize(path);
+        if self.inner.entry_kind(&path) != EntryKind::Missing {
+            return Some(path);
+        }
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        let dir = self.find(path.parent()?)?;
+        self.listing(&dir)
+            .iter()
+            .find(|entry| {
+                entry
+                    .file_name()
+                    .and_then(|entry_name| entry_name.to_str())
+                    .is_some_and(|entry_name| entry_name.to_lowercase() == name)
+            })
+            .cloned()
+    }
+
+    /// Get the entries in a directory, from the cache if it was listed before.
+    fn listing(&self, dir: &Path) -> Arc<[PathBuf]> {
+        let mut listings = self.lock_listings();
+        let listing = listings
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| self.inner.read_dir(dir).unwrap_or_default().into());
+        Arc::clone(listing)
+    }
+
+    /// Run a filesystem operation on the entry at `path` in whatever case it exists, or on
+    /// `path` itself if it does not exist, so the error is the same.
+    fn with_found<T>(&self, path: &Path, operation: impl FnOnce(&Path) -> T) -> T {
+        match self.find(path) {
+            Some(found) => operation(&found),
+            None => operation(path),
+        }
+    }
+}
+
+impl FileSystem for CaseInsensitiveFileSystem {
+    fn entry_kind(&self, path: &Path) -> EntryKind {
+        self.with_found(path, |path| self.inner.entry_kind(path))
+    }
+
+    fn try_entry_kind(&self, path: &Path) -> Result<EntryKind, IOError> {
+        self.with_found(path, |path| self.inner.try_entry_kind(path))
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String, IOError> {
+        self.with_found(path, |path| self.inner.read_to_string(path))
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, IOError> {
+        self.with_found(path, |path| self.inner.canonicalize(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, IOError> {
+        self.with_found(path, |path| self.inner.read_dir(path))
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf, IOError> {
+        self.with_found(path, |path| self.inner.read_link(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, Resolver, Warning};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A filesystem where reading files fails a number of times before it works, where files
+    /// in `/private` can not be read at all, and where nothing in `/project/locked` can be
+    /// checked.
+    struct FlakyFileSystem {
+        snapshot: SnapshotFileSystem,
+        failures: AtomicUsize,
+    }
+
+    impl FileSystem for FlakyFileSystem {
+        fn entry_kind(&self, path: &Path) -> EntryKind {
+            self.snapshot.entry_kind(path)
+        }
+
+        fn try_entry_kind(&self, path: &Path) -> Result<EntryKind, IOError> {
+            if path.starts_with("/project/locked") {
+                return Err(IOError::new(IOErrorKind::PermissionDenied, "Permission denied"));
+            }
+            Ok(self.snapshot.entry_kind(path))
+        }
+
+        fn read_to_string(&self, path: &Path) -> Result<String, IOError> {
+            if path.starts_with("/private") {
+                return Err(IOError::new(IOErrorKind::PermissionDenied, "Permission denied"));
+            }
+            let failed = self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+            match failed {
+                Ok(_) => Err(IOError::new(IOErrorKind::Interrupted, "Interrupted")),
+                Err(_) => self.snapshot.read_to_string(path),
+            }
+        }
+
+        fn canonicalize(&self, path: &Path) -> Result<PathBuf, IOError> {
+            self.snapshot.canonicalize(path)
+        }
+
+        fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, IOError> {
+            self.snapshot.read_dir(path)
+        }
+    }
+
+    #[test]
+    fn resolves_from_snapshot() {
+        let mut snapshot: SnapshotFileSystem = vec![
+            "/project/src/a.js",
+            "/project/src/dir/index.json",
+            "/project/node_modules/dep/lib/index.js",
+        ]
+        .into_iter()
+        .collect();
+        snapshot.add_file(
+            "/project/node_modules/dep/package.json",
+            Some(r#"{ "main": "lib" }"#),
+        );
+        let resolver = Resolver::default()
+            .filesystem(Arc::new(snapshot))
+            .with_basedir(PathBuf::from("/project/src"));
+
+        assert_eq!(
+            PathBuf::from("/project/node_modules/dep/lib/index.js"),
+            resolver.resolve("dep").unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("/project/src/a.js"),
+            resolver.resolve("./a").unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("/project/src/dir/index.json"),
+            resolver.resolve("./dir").unwrap()
+        );
+        assert!(resolver.resolve("./b").is_err());
+        assert!(resolver.resolve("other").is_err());
+    }
+
+    #[test]
+    fn classifies_and_retries_io_errors() {
+        let mut snapshot = SnapshotFileSystem::new();
+        snapshot.add_file("/project/node_modules/dep/package.json", Some(r#"{ "main": "a" }"#));
+        snapshot.add_file("/project/node_modules/dep/a.js", None::<String>);
+        snapshot.add_file("/private/main.js", Some(""));
+        let flaky = |failures| {
+            Resolver::default().filesystem(Arc::new(FlakyFileSystem {
+                snapshot: snapshot.clone(),
+                failures: AtomicUsize::new(failures),
+            }))
+        };
+
+        let resolver = flaky(2).with_basedir(PathBuf::from("/project"));
+        match resolver.resolve("dep") {
+            Err(Error::TransientIOError { path, .. }) => {
+                assert_eq!(PathBuf::from("/project/node_modules/dep/package.json"), path)
+            }
+            other => panic!("expected TransientIOError, got {:?}", other),
+        }
+        let resolver = flaky(2).io_retries(2).with_basedir(PathBuf::from("/project"));
+        assert_eq!(Ok(PathBuf::from("/project/node_modules/dep/a.js")), resolver.resolve("dep"));
+
+        // Permission errors are not retried.
+        let err = flaky(0)
+            .io_retries(2)
+            .scan_imports(vec![PathBuf::from("/private/main.js")], |_, _| vec![])
+            .unwrap_err();
+        assert!(matches!(err, Error::PermissionDenied { .. }));
+        assert_eq!(IOErrorKind::PermissionDenied, err.io_kind());
+    }
+
+    #[test]
+    fn reports_permission_errors() {
+        let mut snapshot = SnapshotFileSystem::new();
+        snapshot.add_file("/project/node_modules/dep/index.js", None::<String>);
+        snapshot.add_file("/project/locked/node_modules/dep/index.js", None::<String>);
+        snapshot.add_file("/project/locked/package.json", Some("{}"));
+        snapshot.add_file("/private/package.json", Some(r#"{ "main": "main.js" }"#));
+        snapshot.add_file("/private/index.js", None::<String>);
+        snapshot.add_file("/private/util.js", None::<String>);
+        let resolver = Resolver::default().filesystem(Arc::new(FlakyFileSystem {
+            snapshot,
+            failures: AtomicUsize::new(0),
+        }));
+        let locked = resolver.with_basedir(PathBuf::from("/project/locked/src"));
+
+        // Paths that can not be checked count as missing, like in Node.js.
+        let resolution = locked.resolve_full("dep").unwrap();
+        assert_eq!(PathBuf::from("/project/node_modules/dep/index.js"), resolution.path);
+        assert!(matches!(
+            resolution.warnings.as_slice(),
+            [Warning::PermissionDenied { path, .. }, ..]
+                if path == Path::new("/project/locked/src/node_modules")
+        ));
+
+        let strict = locked.clone().strict_permissions(true);
+        match strict.resolve("dep") {
+            Err(Error::PermissionDenied { path, .. }) => {
+                assert_eq!(PathBuf::from("/project/locked/src/node_modules"), path)
+            }
+            other => panic!("expected PermissionDenied, got {:?}", other),
+        }
+        assert!(matches!(
+            strict.resolve_directory("dep"),
+            Err(Error::PermissionDenied { .. })
+        ));
+
+        // Unreadable package.json files are reported too.
+        let private = resolver.with_basedir(PathBuf::from("/"));
+        let resolution = private.resolve_full("./private").unwrap();
+        assert_eq!(PathBuf::from("/private/index.js"), resolution.path);
+        assert!(resolution
+            .warnings
+            .iter()
+            .any(|warning| matches!(warning, Warning::PermissionDenied { .. })));
+        assert!(matches!(
+            private.clone().strict_permissions(true).resolve("./private"),
+            Err(Error::PermissionDenied { .. })
+        ));
+
+        // So are the ones that are only read for the details of a result.
+        let resolution = private.resolve_full("./private/util.js").unwrap();
+        assert_eq!(Some(PathBuf::from("/private/package.json")), resolution.package_json);
+        assert_eq!(None, resolution.package_version);
+        assert!(matches!(
+            resolution.warnings.as_slice(),
+            [Warning::PermissionDenied { path, .. }, ..]
+                if path == Path::new("/private/package.json")
+        ));
+        let strict = private.strict_permissions(true);
+        assert!(strict.resolve("./private/util.js").is_ok());
+        match strict.resolve_full("./private/util.js") {
+            Err(Error::PermissionDenied { path, .. }) => {
+                assert_eq!(PathBuf::from("/private/package.json"), path)
+            }
+            other => panic!("expected PermissionDenied, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn resolves_case_insensitively() {
+        let mut snapshot: SnapshotFileSystem =
+            vec!["/project/src/Button.js", "/project/node_modules/React/lib/main.js"]
+                .into_iter()
+                .collect();
+        snapshot.add_file(
+            "/project/node_modules/React/package.json",
+            Some(r#"{ "main": "./LIB/Main" }"#),
+        );
+        let snapshot = Arc::new(snapshot);
+        let src = PathBuf::from("/project/src");
+        let resolve = |resolver: Resolver, specifier: &str| {
+            resolver.with_basedir(src.clone()).resolve(specifier)
+        };
+
+        let sensitive = Resolver::default().filesystem(snapshot.clone());
+        assert!(resolve(sensitive.clone(), "./button").is_err());
+        assert!(resolve(sensitive.clone(), "react").is_err());
+
+        let insensitive = sensitive.case_insensitive(true);
+        assert_eq!(Ok(src.join("Button.js")), resolve(insensitive.clone(), "./button"));
+        assert_eq!(Ok(src.join("Button.js")), resolve(insensitive.clone(), "./BUTTON.js"));
+        assert_eq!(
+            Ok(PathBuf::from("/project/node_modules/React/lib/main.js")),
+            resolve(insensitive.clone(), "react")
+        );
+        // Paths keep their case if symlinks are not expanded.
+        assert_eq!(
+            Ok(src.join("button.js")),
+            resolve(insensitive.clone().preserve_symlinks(true), "./button")
+        );
+
+        // Replacing the filesystem keeps the mode, and it can be turned off again.
+        let replaced = insensitive.filesystem(snapshot);
+        assert!(resolve(replaced.clone(), "./button").is_ok());
+        assert!(resolve(replaced.case_insensitive(false), "./button").is_err());
+    }
+}
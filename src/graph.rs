@@ -0,0 +1,128 @@
+//! Exporting the module graph of an [`ImportScan`] for visualization.
+
+use crate::pure::node_modules_package;
+use crate::resolution::package_name;
+use crate::ImportScan;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+impl ImportScan {
+    /// Export the module graph in the Graphviz DOT format.
+    ///
+    /// Every scanned and imported file is a node, named by its absolute path. Files inside
+    /// node_modules folders are drawn as boxes, and labeled with the name and version of their
+    /// package and their path inside it. Every import is an edge, labeled with its specifier.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let scan = Resolver::default().scan_imports(entries, extract_imports)?;
+    /// std::fs::write("modules.dot", scan.to_dot())?;
+    /// // Render it with `dot -Tsvg modules.dot > modules.svg`.
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph modules {\n");
+        for path in self.nodes() {
+            match self.package_label(path) {
+                Some(label) => {
+                    let _ = writeln!(
+                        dot,
+                        "  {} [label={}, shape=box];",
+                        dot_string(&path.display().to_string()),
+                        dot_string(&label)
+                    );
+                }
+                None => {
+                    let _ = writeln!(dot, "  {};", dot_string(&path.display().to_string()));
+                }
+            }
+        }
+        for (from, imports) in &self.graph {
+            for (specifier, to) in imports {
+                let _ = writeln!(
+                    dot,
+                    "  {} -> {} [label={}];",
+                    dot_string(&from.display().to_string()),
+                    dot_string(&to.display().to_string()),
+                    dot_string(specifier)
+                );
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Export the module graph as a JSON object with an entry for every scanned and imported
+    /// file, by absolute path.
+    ///
+    /// Files inside node_modules folders have `"package"` and `"version"` keys, if their
+    /// package.json file has those fields. Scanned files have an `"imports"` object, which maps
+    /// each specifier to the absolute path it resolved to. Files that were not scanned, like
+    /// the files of installed packages, do not have it.
+    ///
+    /// ```json
+    /// {
+    ///   "/project/src/main.js": {
+    ///     "imports": { "react": "/project/node_modules/react/index.js" }
+    ///   },
+    ///   "/project/node_modules/react/index.js": { "package": "react", "version": "18.2.0" }
+    /// }
+    /// ```
+    pub fn to_json(&self) -> Value {
+        let mut modules = Map::new();
+        for path in self.nodes() {
+            let mut module = Map::new();
+            if let Some(pkg_dir) = node_modules_package(path) {
+                if let Some(name) = package_name(&pkg_dir) {
+                    module.insert(String::from("package"), Value::String(name));
+                }
+                if let Some(version) = self.versions.get(&pkg_dir) {
+                    module.insert(String::from("version"), Value::String(version.clone()));
+                }
+            }
+            if let Some(imports) = self.graph.get(path) {
+                let imports = imports
+                    .iter()
+                    .map(|(specifier, to)| {
+                        (specifier.clone(), Value::String(to.display().to_string()))
+                    })
+                    .collect();
+                module.insert(String::from("imports"), Value::Object(imports));
+            }
+            modules.insert(path.display().to_string(), Value::Object(module));
+        }
+        Value::Object(modules)
+    }
+
+    /// Get every scanned and imported file, sorted by path.
+    fn nodes(&self) -> BTreeSet<&PathBuf> {
+        self.graph
+            .iter()
+            .flat_map(|(from, imports)| Some(from).into_iter().chain(imports.values()))
+            .collect()
+    }
+
+    /// Get a label like `react@18.2.0 index.js` for a file inside a node_modules folder.
+    fn package_label(&self, path: &Path) -> Option<String> {
+        let pkg_dir = node_modules_package(path)?;
+        let mut label = package_name(&pkg_dir)?;
+        if let Some(version) = self.versions.get(&pkg_dir) {
+            label.push('@');
+            label.push_str(version);
+        }
+        if let Ok(subpath) = path.strip_prefix(&pkg_dir) {
+            label.push(' ');
+            label.push_str(&subpath.display().to_string());
+        }
+        Some(label)
+    }
+}
+
+/// Quote a string for use as a DOT identifier.
+fn dot_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
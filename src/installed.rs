@@ -0,0 +1,95 @@
+//! Walk the packages that are installed in a `node_modules` tree.
+
+use crate::{io_error, Access, Error, Resolver};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A callback that receives an installed package directory and its package.json contents.
+type Visitor<'a> = dyn FnMut(&Path, Option<Arc<Value>>) -> Result<(), Error> + 'a;
+
+impl Resolver {
+    /// Call `visit` for every package in `root/node_modules`, and recursively for every package
+    /// in the `node_modules` folders of those packages. `visit` receives the package directory
+    /// and its parsed package.json file, if it has a valid one.
+    ///
+    /// Packages are visited in alphabetical order, and a directory that is reachable through
+    /// several symlinks is only visited once.
+    pub(crate) fn visit_installed_packages<F>(&self, root: &Path, mut visit: F) -> Result<(), Error>
+    where
+        F: FnMut(&Path, Option<Arc<Value>>) -> Result<(), Error>,
+    {
+        let mut seen = HashSet::new();
+        self.visit_node_modules(&root.join("node_modules"), &mut visit, &mut seen)
+    }
+
+    /// Visit all packages in a `node_modules` folder, including scoped packages.
+    fn visit_node_modules(
+        &self,
+        node_modules: &Path,
+        visit: &mut Visitor<'_>,
+        seen: &mut HashSet<PathBuf>,
+    ) -> Result<(), Error> {
+        if !self.is_dir(node_modules) {
+            return Ok(());
+        }
+
+        for entry in self.read_dir_sorted(node_modules)? {
+            let name = match entry.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+            // Skip .bin and package manager metadata folders.
+            if name.starts_with('.') {
+                continue;
+            }
+            if name.starts_with('@') && self.is_dir(&entry) {
+                for scoped in self.read_dir_sorted(&entry)? {
+                    self.visit_package(&scoped, visit, seen)?;
+                }
+            } else {
+                self.visit_package(&entry, visit, seen)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Visit a single package and the packages in its own `node_modules` folder.
+    fn visit_package(
+        &self,
+        dir: &Path,
+        visit: &mut Visitor<'_>,
+        seen: &mut HashSet<PathBuf>,
+    ) -> Result<(), Error> {
+        if !self.is_dir(dir) {
+            return Ok(());
+        }
+        // Symlinked packages can form cycles, so every physical directory is only visited once.
+        let real = self.canonicalize(dir).map_err(|err| io_error(dir, err))?;
+        if !seen.insert(real) {
+            return Ok(());
+        }
+
+        // An invalid package.json does not prevent resolution, so it doesn't stop the walk either.
+        let pkg_path = dir.join("package.json");
+        let pkg = if self.is_file(&pkg_path) {
+            self.read_package_json(&pkg_path).ok()
+        } else {
+            None
+        };
+        visit(dir, pkg)?;
+
+        self.visit_node_modules(&dir.join("node_modules"), visit, seen)
+    }
+
+    fn read_dir_sorted(&self, dir: &Path) -> Result<Vec<PathBuf>, Error> {
+        self.observe(dir, Access::ReadDir);
+        let mut entries = self
+            .retry_io(|| self.fs.read_dir(dir))
+            .map_err(|err| io_error(dir, err))?;
+        entries.sort();
+        Ok(entries)
+    }
+}
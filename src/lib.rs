@@ -9,25 +9,534 @@
 //! // → Ok("/other/path/node_modules/abc/index.js")
 //! ```
 
+mod ambiguity;
+mod bin;
+mod budget;
+mod builder;
+mod cache;
+mod chain;
+mod compiled;
+#[cfg(feature = "conformance")]
+mod conformance;
+mod dedup;
+#[cfg(feature = "miette")]
+mod diagnostic;
+mod duplicates;
+mod environment;
+mod exports;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "url")]
+mod file_url;
+mod fs;
+mod graph;
+mod installed;
+mod observer;
+mod package_json;
+mod pure;
+mod resolution;
+#[cfg(feature = "regex")]
+mod rewrite;
+mod runtime;
+mod scan;
+mod service;
+mod session;
+mod specifier;
+mod stats;
+mod target;
+mod typescript;
+mod verbatim;
+
+pub use budget::{Budget, Limit};
+pub use builder::ResolverBuilder;
+pub use cache::{EntryKind, LruCache, MemoryCache, ResolutionCache};
+pub use chain::FallbackResolver;
+pub use compiled::CompiledResolver;
+#[cfg(feature = "conformance")]
+pub use conformance::{Mismatch, NodeMode, NodeOracle};
+pub use dedup::{DedupResolver, SharedResult};
+pub use duplicates::{DuplicatePackage, PackageInstance};
+pub use environment::{Environment, FixedEnvironment, ProcessEnvironment};
+pub use fs::{CaseInsensitiveFileSystem, FileSystem, RealFileSystem, SnapshotFileSystem};
+pub use observer::{Access, Observer};
+pub use resolution::{InteropHint, Resolution, Warning};
+pub use runtime::Runtime;
+pub use scan::ImportScan;
+pub use service::{PendingResolution, ResolverService};
+pub use session::Session;
+pub use specifier::{parse_specifier, Specifier, SpecifierKind};
+pub use stats::{CacheReport, CacheUsage, ResolverStats, SlowResolution};
+pub use target::Target;
+
+use budget::BudgetUsage;
+use exports::ExportsCache;
+use package_json::parse_package_json;
+use specifier::validate_specifier;
+use typescript::is_typescript_file;
+use stats::Counters;
+
 use node_builtins::BUILTINS;
 use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::default::Default;
 use std::error::Error as StdError;
+use std::ffi::OsStr;
 use std::fmt;
-use std::fs::File;
 use std::io::{Error as IOError, ErrorKind as IOErrorKind};
-use std::path::{Component as PathComponent, Path, PathBuf};
+use std::path::{is_separator, Component as PathComponent, Path, PathBuf};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+use std::thread;
+use std::time::Duration;
 
 static ROOT: &str = "/";
 
+/// The package.json fields that declare dependencies.
+static DEPENDENCY_FIELDS: &[&str] = &["dependencies", "devDependencies", "optionalDependencies"];
+
+/// Errors that can occur while resolving.
+///
+/// New variants may be added in minor releases. Use [`Error::io_kind`] to handle errors in
+/// broad categories.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
-    /// Failed to parse a package.json file.
+    /// Failed to parse JSON. Invalid package.json files are reported as
+    /// [`Error::InvalidPackageJson`] instead.
     JSONError(serde_json::Error),
     /// Could not read a file.
     IOError(IOError),
     /// A Basedir was not configured.
     UnconfiguredBasedir,
+    /// A specifier referenced an environment variable that is not set.
+    UndefinedVariable(String),
+    /// A specifier can never resolve, for example because it contains an invalid package name.
+    InvalidSpecifier {
+        /// The specifier that was rejected.
+        specifier: String,
+        /// Why the specifier was rejected.
+        reason: &'static str,
+    },
+    /// No file or directory was found for a specifier.
+    NotFound {
+        /// The specifier that could not be resolved.
+        specifier: String,
+        /// The directory the specifier was resolved from.
+        basedir: PathBuf,
+        /// The candidate paths that were checked, in order. See [`Error::tried`].
+        tried: Vec<PathBuf>,
+    },
+    /// The "exports" field of a package does not allow importing a subpath, or blocks it with
+    /// a `null` target.
+    PackagePathNotExported {
+        /// The directory of the package.
+        package_dir: PathBuf,
+        /// The subpath that is not exported, like `"./internal/helper"`.
+        subpath: String,
+    },
+    /// A target in the "exports" field of a package is not a valid path inside the package.
+    InvalidPackageTarget {
+        /// The directory of the package.
+        package_dir: PathBuf,
+        /// The invalid target, as JSON.
+        target: String,
+    },
+    /// A `#` specifier is not defined in the "imports" field of the package that contains the
+    /// basedir.
+    PackageImportNotDefined {
+        /// The specifier that is not defined.
+        specifier: String,
+        /// The directory of the package, or `None` if the basedir is not inside a package.
+        package_dir: Option<PathBuf>,
+    },
+    /// A package does not declare a command in its "bin" field.
+    BinNotFound {
+        /// The name of the package.
+        package: String,
+        /// The name of the command.
+        bin: String,
+    },
+    /// A single resolution did more work than its [`Budget`] allows.
+    BudgetExceeded(Limit),
+    /// The resolver was not allowed to read a file or directory.
+    PermissionDenied {
+        /// The path that could not be read.
+        path: PathBuf,
+        /// The error from the filesystem.
+        error: IOError,
+    },
+    /// Reading a file or directory failed with an error that may go away when trying again,
+    /// like `EINTR` or `EAGAIN`, even after the [retries](Resolver::io_retries).
+    TransientIOError {
+        /// The path that could not be read.
+        path: PathBuf,
+        /// The error from the filesystem.
+        error: IOError,
+    },
+    /// A single resolution took longer than the [timeout](Budget::timeout) of its budget.
+    Timeout {
+        /// The specifier that was being resolved.
+        specifier: String,
+        /// How long the resolution had taken when it stopped.
+        elapsed: Duration,
+        /// The candidate paths that were checked before the deadline, in order.
+        tried: Vec<PathBuf>,
+    },
+    /// A package.json file could not be parsed.
+    InvalidPackageJson {
+        /// The path to the package.json file.
+        path: PathBuf,
+        /// The parse error.
+        error: serde_json::Error,
+    },
+    /// A package.json file has a valid syntax, but contents that Node.js would reject.
+    InvalidPackageConfig {
+        /// The path to the package.json file.
+        path: PathBuf,
+        /// What is wrong with the file.
+        reason: &'static str,
+    },
+    /// A specifier could not be resolved, and one of the paths that was checked is a symlink to
+    /// something that does not exist. This usually means that an install was interrupted or
+    /// corrupted.
+    BrokenSymlink {
+        /// The path of the symlink.
+        link: PathBuf,
+        /// Where the symlink points to.
+        target: PathBuf,
+    },
+    /// A relative specifier reaches into another package in a node_modules folder. This is
+    /// only reported when [strict package boundaries](Resolver::strict_package_boundaries)
+    /// are enabled.
+    PackageBoundary {
+        /// The specifier that crosses the boundary.
+        specifier: String,
+        /// The directory of the package it reaches into.
+        package_dir: PathBuf,
+    },
+    /// A bare specifier names a package that is on the [deny list](Resolver::deny).
+    DeniedPackage {
+        /// The specifier that was rejected.
+        specifier: String,
+        /// The pattern on the deny list that matches the package name.
+        pattern: String,
+    },
+    /// The "main" fields of package.json files point to each other's folders in a cycle, or
+    /// through more than [`MAX_MAIN_INDIRECTIONS`] folders.
+    ManifestCycle {
+        /// The package.json files that were followed, in order. For a cycle, the last one is
+        /// the file that was reached again.
+        chain: Vec<PathBuf>,
+    },
+}
+
+/// The number of package.json files whose "main" field points to the folder of another
+/// package.json file that a single resolution follows, before it fails with
+/// [`Error::ManifestCycle`].
+pub const MAX_MAIN_INDIRECTIONS: usize = 32;
+
+impl Error {
+    /// Get the [`std::io::ErrorKind`] that best describes this error, so it can be handled
+    /// like a filesystem error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    /// use std::io::ErrorKind;
+    ///
+    /// let err = Resolver::default()
+    ///     .with_basedir(PathBuf::from("./fixtures"))
+    ///     .resolve("./does-not-exist")
+    ///     .unwrap_err();
+    /// assert_eq!(ErrorKind::NotFound, err.io_kind());
+    /// ```
+    pub fn io_kind(&self) -> IOErrorKind {
+        match self {
+            Error::IOError(err) => err.kind(),
+            Error::PermissionDenied { .. } => IOErrorKind::PermissionDenied,
+            Error::TransientIOError { error, .. } => error.kind(),
+            Error::NotFound { .. }
+            | Error::PackagePathNotExported { .. }
+            | Error::PackageImportNotDefined { .. }
+            | Error::BinNotFound { .. }
+            | Error::BrokenSymlink { .. } => IOErrorKind::NotFound,
+            Error::UnconfiguredBasedir
+            | Error::UndefinedVariable(_)
+            | Error::InvalidSpecifier { .. }
+            | Error::PackageBoundary { .. } => IOErrorKind::InvalidInput,
+            Error::JSONError(_)
+            | Error::InvalidPackageTarget { .. }
+            | Error::InvalidPackageJson { .. }
+            | Error::InvalidPackageConfig { .. }
+            | Error::ManifestCycle { .. } => IOErrorKind::InvalidData,
+            Error::BudgetExceeded(_) => IOErrorKind::Other,
+            Error::Timeout { .. } => IOErrorKind::TimedOut,
+            Error::DeniedPackage { .. } => IOErrorKind::PermissionDenied,
+        }
+    }
+
+    /// Get the specifier that failed to resolve, if the error is about a specifier.
+    pub fn specifier(&self) -> Option<&str> {
+        match self {
+            Error::InvalidSpecifier { specifier, .. }
+            | Error::NotFound { specifier, .. }
+            | Error::PackageImportNotDefined { specifier, .. }
+            | Error::PackageBoundary { specifier, .. }
+            | Error::DeniedPackage { specifier, .. }
+            | Error::Timeout { specifier, .. } => Some(specifier),
+            _ => None,
+        }
+    }
+
+    /// Get the directory that a specifier was resolved from, for [`Error::NotFound`] errors.
+    pub fn basedir(&self) -> Option<&Path> {
+        match self {
+            Error::NotFound { basedir, .. } => Some(basedir),
+            _ => None,
+        }
+    }
+
+    /// Get the candidate paths that were checked, in order, for [`Error::NotFound`] and
+    /// [`Error::Timeout`] errors. This is empty for other errors.
+    ///
+    /// The order only depends on the options and the files on disk, so it is the same for
+    /// every run. With several basedirs, the paths tried from the first basedir come first,
+    /// and a [`FallbackResolver`] lists the paths of its resolvers in the order they were
+    /// added. Within one basedir, the node_modules folders are listed from the innermost one
+    /// outwards, and the extensions and package.json fields in the order they were
+    /// configured. A path that was checked more than once is only listed the first time.
+    pub fn tried(&self) -> &[PathBuf] {
+        match self {
+            Error::NotFound { tried, .. } | Error::Timeout { tried, .. } => tried,
+            _ => &[],
+        }
+    }
+
+    /// Get the directory of the package whose package.json caused the error, if any.
+    pub fn package_dir(&self) -> Option<&Path> {
+        match self {
+            Error::PackagePathNotExported { package_dir, .. }
+            | Error::InvalidPackageTarget { package_dir, .. }
+            | Error::PackageBoundary { package_dir, .. } => Some(package_dir),
+            Error::PackageImportNotDefined { package_dir, .. } => package_dir.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Errors are compared by their fields. Wrapped IO errors are equal if they have the same kind
+/// and message, and JSON errors are equal if they have the same message.
+impl PartialEq for Error {
+    fn eq(&self, other: &Error) -> bool {
+        match (self, other) {
+            (Error::JSONError(a), Error::JSONError(b)) => a.to_string() == b.to_string(),
+            (Error::IOError(a), Error::IOError(b)) => {
+                a.kind() == b.kind() && a.to_string() == b.to_string()
+            }
+            (
+                Error::PermissionDenied { path, error },
+                Error::PermissionDenied { path: other_path, error: other_error },
+            )
+            | (
+                Error::TransientIOError { path, error },
+                Error::TransientIOError { path: other_path, error: other_error },
+            ) => {
+                path == other_path
+                    && error.kind() == other_error.kind()
+                    && error.to_string() == other_error.to_string()
+            }
+            (Error::UnconfiguredBasedir, Error::UnconfiguredBasedir) => true,
+            (Error::UndefinedVariable(a), Error::UndefinedVariable(b)) => a == b,
+            (
+                Error::InvalidSpecifier { specifier, reason },
+                Error::InvalidSpecifier { specifier: other_specifier, reason: other_reason },
+            ) => specifier == other_specifier && reason == other_reason,
+            (
+                Error::NotFound { specifier, basedir, tried },
+                Error::NotFound {
+                    specifier: other_specifier,
+                    basedir: other_basedir,
+                    tried: other_tried,
+                },
+            ) => specifier == other_specifier && basedir == other_basedir && tried == other_tried,
+            (
+                Error::PackagePathNotExported { package_dir, subpath },
+                Error::PackagePathNotExported {
+                    package_dir: other_package_dir,
+                    subpath: other_subpath,
+                },
+            ) => package_dir == other_package_dir && subpath == other_subpath,
+            (
+                Error::InvalidPackageTarget { package_dir, target },
+                Error::InvalidPackageTarget {
+                    package_dir: other_package_dir,
+                    target: other_target,
+                },
+            ) => package_dir == other_package_dir && target == other_target,
+            (
+                Error::PackageImportNotDefined { specifier, package_dir },
+                Error::PackageImportNotDefined {
+                    specifier: other_specifier,
+                    package_dir: other_package_dir,
+                },
+            ) => specifier == other_specifier && package_dir == other_package_dir,
+            (
+                Error::BinNotFound { package, bin },
+                Error::BinNotFound { package: other_package, bin: other_bin },
+            ) => package == other_package && bin == other_bin,
+            (Error::BudgetExceeded(a), Error::BudgetExceeded(b)) => a == b,
+            (
+                Error::Timeout { specifier, elapsed, tried },
+                Error::Timeout {
+                    specifier: other_specifier,
+                    elapsed: other_elapsed,
+                    tried: other_tried,
+                },
+            ) => specifier == other_specifier && elapsed == other_elapsed && tried == other_tried,
+            (
+                Error::InvalidPackageJson { path, error },
+                Error::InvalidPackageJson { path: other_path, error: other_error },
+            ) => path == other_path && error.to_string() == other_error.to_string(),
+            (
+                Error::InvalidPackageConfig { path, reason },
+                Error::InvalidPackageConfig { path: other_path, reason: other_reason },
+            ) => path == other_path && reason == other_reason,
+            (
+                Error::BrokenSymlink { link, target },
+                Error::BrokenSymlink { link: other_link, target: other_target },
+            ) => link == other_link && target == other_target,
+            (
+                Error::PackageBoundary { specifier, package_dir },
+                Error::PackageBoundary {
+                    specifier: other_specifier,
+                    package_dir: other_package_dir,
+                },
+            ) => specifier == other_specifier && package_dir == other_package_dir,
+            (
+                Error::DeniedPackage { specifier, pattern },
+                Error::DeniedPackage { specifier: other_specifier, pattern: other_pattern },
+            ) => specifier == other_specifier && pattern == other_pattern,
+            (Error::ManifestCycle { chain }, Error::ManifestCycle { chain: other_chain }) => {
+                chain == other_chain
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::JSONError(err) => write!(f, "invalid JSON: {}", err),
+            Error::IOError(err) => write!(f, "{}", err),
+            Error::PermissionDenied { path, error } => {
+                write!(f, "permission denied for {}: {}", path.display(), error)
+            }
+            Error::TransientIOError { path, error } => {
+                write!(f, "could not read {}: {}", path.display(), error)
+            }
+            Error::UnconfiguredBasedir => write!(f, "basedir is not configured"),
+            Error::UndefinedVariable(name) => {
+                write!(f, "environment variable {} is not defined", name)
+            }
+            Error::InvalidSpecifier { specifier, reason } => {
+                write!(f, "invalid specifier \"{}\": {}", specifier, reason)
+            }
+            Error::NotFound { specifier, basedir, .. } => write!(
+                f,
+                "cannot find module \"{}\" from {}",
+                specifier,
+                basedir.display()
+            ),
+            Error::PackagePathNotExported { package_dir, subpath } => write!(
+                f,
+                "subpath \"{}\" is not exported by the package in {}",
+                subpath,
+                package_dir.display()
+            ),
+            Error::InvalidPackageTarget { package_dir, target } => write!(
+                f,
+                "invalid target {} in the package in {}",
+                target,
+                package_dir.display()
+            ),
+            Error::PackageImportNotDefined { specifier, package_dir: Some(package_dir) } => write!(
+                f,
+                "\"{}\" is not defined in the imports of the package in {}",
+                specifier,
+                package_dir.display()
+            ),
+            Error::PackageImportNotDefined { specifier, package_dir: None } => write!(
+                f,
+                "\"{}\" cannot be imported outside of a package",
+                specifier
+            ),
+            Error::BinNotFound { package, bin } => {
+                write!(f, "package \"{}\" does not have a \"{}\" command", package, bin)
+            }
+            Error::BudgetExceeded(limit) => write!(f, "resolution exceeded the {}", limit),
+            Error::Timeout { specifier, elapsed, .. } => {
+                write!(f, "resolving \"{}\" timed out after {:?}", specifier, elapsed)
+            }
+            Error::InvalidPackageJson { path, error } => {
+                write!(f, "invalid {}: {}", path.display(), error)
+            }
+            Error::InvalidPackageConfig { path, reason } => {
+                write!(f, "invalid {}: {}", path.display(), reason)
+            }
+            Error::BrokenSymlink { link, target } => write!(
+                f,
+                "{} is a symlink to {}, which does not exist",
+                link.display(),
+                target.display()
+            ),
+            Error::PackageBoundary { specifier, package_dir } => write!(
+                f,
+                "\"{}\" reaches into the package in {}",
+                specifier,
+                package_dir.display()
+            ),
+            Error::DeniedPackage { specifier, pattern } => write!(
+                f,
+                "\"{}\" is not allowed, it matches \"{}\" on the deny list",
+                specifier, pattern
+            ),
+            Error::ManifestCycle { chain } => {
+                write!(f, "the \"main\" fields of package.json files form a cycle: ")?;
+                for (i, path) in chain.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " -> ")?;
+                    }
+                    write!(f, "{}", path.display())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::JSONError(err) => Some(err),
+            Error::IOError(err) => Some(err),
+            Error::PermissionDenied { error, .. } | Error::TransientIOError { error, .. } => {
+                Some(error)
+            }
+            Error::InvalidPackageJson { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for IOError {
+    fn from(err: Error) -> IOError {
+        match err {
+            Error::IOError(err) => err,
+            err => IOError::new(err.io_kind(), err),
+        }
+    }
 }
 
 impl From<serde_json::Error> for Error {
@@ -50,7 +559,15 @@ enum InternalError {
 }
 
 impl InternalError {
-    fn to_public(self) -> Error {
+    /// Check if this error must be reported, instead of falling back to another candidate.
+    fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            InternalError::Public(Error::ManifestCycle { .. } | Error::TransientIOError { .. })
+        )
+    }
+
+    fn into_public(self) -> Error {
         match self {
             InternalError::Public(err) => err,
             InternalError::Private(err) => panic!("leaking internal error: {}", err),
@@ -88,13 +605,194 @@ impl fmt::Display for RecoverableError {
 
 impl StdError for RecoverableError {}
 
+/// State that is tracked during a single resolution.
+#[derive(Debug, Default)]
+struct Context {
+    /// The paths that the result depends on, in the order they were checked, and whether
+    /// they were candidates for the result. This may contain duplicates.
+    checked: Vec<(PathBuf, bool)>,
+    /// Problems that did not prevent resolution.
+    warnings: Vec<Warning>,
+    /// The part of the budget that was used so far.
+    budget: BudgetUsage,
+    /// The package.json files whose "main" field is being followed, outermost first.
+    manifests: Vec<PathBuf>,
+    /// The package.json files and the main fields in them that led to the result.
+    main_fields: Vec<(PathBuf, String)>,
+    /// The first filesystem error that fails the resolution once it is done.
+    io_error: Option<Error>,
+}
+
+impl Context {
+    /// Remember that `path` was checked as a possible result.
+    fn try_path(&mut self, path: &Path) {
+        self.checked.push((path.to_path_buf(), true));
+    }
+
+    /// Get the candidate paths that were checked, in order, without duplicates.
+    fn tried(&self) -> Vec<PathBuf> {
+        first_occurrences(
+            self.checked
+                .iter()
+                .filter(|(_, candidate)| *candidate)
+                .map(|(path, _)| path.clone()),
+        )
+    }
+
+    /// Fail if the resolution went over its budget, or ran into a filesystem error, even if it
+    /// found something.
+    fn check_aborted(&mut self, target: &str) -> Result<(), Error> {
+        if let Some(error) = self.io_error.take() {
+            return Err(error);
+        }
+        if let Some(elapsed) = self.budget.timed_out() {
+            return Err(Error::Timeout {
+                specifier: target.to_string(),
+                elapsed,
+                tried: self.tried(),
+            });
+        }
+        match self.budget.exceeded() {
+            Some(limit) => Err(Error::BudgetExceeded(limit)),
+            None => Ok(()),
+        }
+    }
+
+    /// Report a problem that does not prevent resolution.
+    fn warn(&mut self, warning: Warning) {
+        self.warnings.push(warning);
+    }
+
+    /// Record that the result depends on whether `path` exists, or on its contents.
+    fn watch(&mut self, path: &Path) {
+        self.checked.push((path.to_path_buf(), false));
+    }
+}
+
+/// How the resolver deals with symlinks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymlinkStrategy {
+    /// Keep symlinks in the resolved path, like `node --preserve-symlinks`. Only `./` and `../`
+    /// segments are removed from the result.
+    Preserve,
+    /// Walk directories as they are given, and expand symlinks in the final result. This is
+    /// the default.
+    RealpathResult,
+    /// Expand symlinks in the basedir and in every directory that is entered while resolving,
+    /// and in the final result. node_modules lookups and relative specifiers then start from
+    /// the real location of a symlinked folder, like Node.js does for the files it loads.
+    RealpathDirectories,
+}
+
+/// Where node_modules lookups start when the basedir is inside a symlinked folder, like a
+/// workspace package that is linked into the node_modules folder of another package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AscentOrigin {
+    /// Start from the real location with [`SymlinkStrategy::RealpathDirectories`], and from
+    /// the location of the symlink otherwise. This is the default.
+    Strategy,
+    /// Start from the location of the symlink, so packages that are installed next to the
+    /// link are found. This is what package managers that hoist the dependencies of linked
+    /// packages expect.
+    Link,
+    /// Start from the real location of the folder, so only the node_modules folders of the
+    /// linked package and its parents are searched. This is what workspaces that install
+    /// dependencies in every package expect.
+    Real,
+}
+
+/// How ES module specifiers are resolved, like the `--experimental-specifier-resolution` flag of
+/// older Node.js versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecifierResolution {
+    /// Relative and absolute specifiers must include the file extension, and directories are
+    /// not resolved to their index file. This is the default, and what Node.js does.
+    Explicit,
+    /// Extensions are guessed and directories resolve to their index file, like for
+    /// `require()`. Many codebases that were transpiled from TypeScript rely on this.
+    Node,
+}
+
+/// Whether paths are resolved as files or as directories first. See [`Resolver::probe_order`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeOrder {
+    /// Try the path as a file with each extension, and then as a directory. This is the
+    /// default, and what Node.js does.
+    FileFirst,
+    /// Try the path as a directory, and then as a file with each extension.
+    DirectoryFirst,
+    /// Only try the path as a file with each extension.
+    FileOnly,
+    /// Only try the path as a directory.
+    DirectoryOnly,
+}
+
+impl ProbeOrder {
+    /// Get the kinds of entries that a path is tried as, in order.
+    pub(crate) fn kinds(self) -> &'static [EntryKind] {
+        match self {
+            ProbeOrder::FileFirst => &[EntryKind::File, EntryKind::Directory],
+            ProbeOrder::DirectoryFirst => &[EntryKind::Directory, EntryKind::File],
+            ProbeOrder::FileOnly => &[EntryKind::File],
+            ProbeOrder::DirectoryOnly => &[EntryKind::Directory],
+        }
+    }
+}
+
 /// Resolver instances keep track of options.
 #[derive(Clone)]
 pub struct Resolver {
     basedir: Option<PathBuf>,
-    extensions: Vec<String>,
-    preserve_symlinks: bool,
-    main_fields: Vec<String>,
+    fallback_basedirs: Arc<[PathBuf]>,
+    extensions: Arc<[String]>,
+    symlinks: SymlinkStrategy,
+    main_fields: Arc<[String]>,
+    module_directories: Arc<[String]>,
+    module_paths: Arc<[PathBuf]>,
+    workspace_roots: Arc<[PathBuf]>,
+    scoped_module_directories: Arc<Vec<(PathBuf, Arc<[String]>)>>,
+    use_package_main: bool,
+    use_package_json: bool,
+    tolerant_package_json: bool,
+    conditions: Arc<[String]>,
+    exports_bypass: Arc<[String]>,
+    denied_packages: Arc<[String]>,
+    package_type_extensions: bool,
+    fully_specified: bool,
+    strict_package_boundaries: bool,
+    folder_exports: bool,
+    specifier_resolution: SpecifierResolution,
+    json_modules: bool,
+    expand_env: bool,
+    directories_lib: bool,
+    link_dependencies: bool,
+    file_dependencies: bool,
+    tilde_prefix: bool,
+    partials: bool,
+    typescript_extensions: bool,
+    types_packages: bool,
+    counters: Arc<Counters>,
+    cache: Option<Arc<dyn ResolutionCache>>,
+    fs: Arc<dyn FileSystem>,
+    case_insensitive_fs: Option<Arc<CaseInsensitiveFileSystem>>,
+    observer: Option<Arc<dyn Observer>>,
+    environment: Arc<dyn Environment>,
+    node_path: bool,
+    output_root: Option<PathBuf>,
+    runtime: Runtime,
+    scopes: Arc<Vec<(PathBuf, Resolver)>>,
+    overrides: Arc<Vec<(String, PathBuf)>>,
+    #[cfg(feature = "regex")]
+    rewrites: Arc<Vec<(regex::Regex, String)>>,
+    compiled_exports: Arc<ExportsCache>,
+    detailed_stats: bool,
+    ascent_origin: AscentOrigin,
+    nested_package_json: bool,
+    probe_order: ProbeOrder,
+    budget: Budget,
+    io_retries: usize,
+    strict_permissions: bool,
+    verbatim_paths: bool,
 }
 
 impl Default for Resolver {
@@ -102,22 +800,249 @@ impl Default for Resolver {
     ///
     /// - It resolves .js, .json, and .node files, in that order;
     /// - It expands symlinks;
-    /// - It uses the package.json "main" field for bare specifier lookups.
+    /// - It uses the package.json "main" field for bare specifier lookups;
+    /// - It uses the "node" and "require" conditions in package.json "exports" fields.
     fn default() -> Resolver {
         Resolver {
             basedir: None,
-            extensions: vec![
+            fallback_basedirs: Arc::default(),
+            extensions: Arc::from(vec![
                 String::from(".js"),
                 String::from(".json"),
                 String::from(".node"),
-            ],
-            preserve_symlinks: false,
-            main_fields: vec![String::from("main")],
+            ]),
+            symlinks: SymlinkStrategy::RealpathResult,
+            main_fields: Arc::from(vec![String::from("main")]),
+            module_directories: Arc::new([String::from("node_modules")]),
+            module_paths: Arc::new([]),
+            workspace_roots: Arc::new([]),
+            scoped_module_directories: Arc::default(),
+            use_package_main: true,
+            use_package_json: true,
+            tolerant_package_json: false,
+            conditions: Arc::from(vec![String::from("node"), String::from("require")]),
+            exports_bypass: Arc::from(vec![]),
+            denied_packages: Arc::from(vec![]),
+            package_type_extensions: false,
+            fully_specified: false,
+            strict_package_boundaries: false,
+            folder_exports: false,
+            specifier_resolution: SpecifierResolution::Explicit,
+            json_modules: false,
+            expand_env: false,
+            directories_lib: false,
+            link_dependencies: false,
+            file_dependencies: false,
+            tilde_prefix: false,
+            partials: false,
+            typescript_extensions: false,
+            types_packages: false,
+            counters: Arc::default(),
+            cache: None,
+            fs: Arc::new(RealFileSystem),
+            case_insensitive_fs: None,
+            environment: Arc::new(ProcessEnvironment),
+            node_path: false,
+            output_root: None,
+            observer: None,
+            runtime: Runtime::Node,
+            scopes: Arc::default(),
+            overrides: Arc::default(),
+            #[cfg(feature = "regex")]
+            rewrites: Arc::default(),
+            compiled_exports: Arc::default(),
+            detailed_stats: false,
+            ascent_origin: AscentOrigin::Strategy,
+            nested_package_json: true,
+            probe_order: ProbeOrder::FileFirst,
+            budget: Budget::default(),
+            io_retries: 0,
+            strict_permissions: false,
+            verbatim_paths: false,
         }
     }
 }
 
 impl Resolver {
+    /// Create a resolver that behaves like `require()` in Node.js. This is the same as
+    /// `Resolver::default()`.
+    pub fn node_cjs() -> Self {
+        Resolver::default()
+    }
+
+    /// Create a resolver that behaves like `import` in Node.js.
+    ///
+    /// - It requires relative and absolute specifiers to include the file extension;
+    /// - It expands symlinks;
+    /// - It uses the package.json "main" field for bare specifier lookups;
+    /// - It uses the "node" and "import" conditions in package.json "exports" fields;
+    /// - It marks .json files as [JSON modules](Resolver::json_modules).
+    pub fn node_esm() -> Self {
+        Resolver::default()
+            .extensions(&[".js", ".mjs", ".cjs", ".json", ".node"])
+            .fully_specified(true)
+            .conditions(&["node", "import"])
+            .json_modules(true)
+    }
+
+    /// Create a resolver that behaves like webpack does when bundling for browsers.
+    ///
+    /// - It resolves .js, .json, and .wasm files, in that order;
+    /// - It expands symlinks;
+    /// - It uses the package.json "browser", "module", and "main" fields, in that order;
+    /// - It uses the "browser", "import", and "module" conditions in package.json "exports"
+    ///   fields.
+    pub fn webpack_web() -> Self {
+        Resolver::default()
+            .extensions(&[".js", ".json", ".wasm"])
+            .main_fields(&["browser", "module", "main"])
+            .conditions(&["browser", "import", "module"])
+    }
+
+    /// Create a resolver that picks package entry points like the unpkg and jsDelivr CDNs do.
+    ///
+    /// - It resolves .js and .json files, in that order;
+    /// - It expands symlinks;
+    /// - It uses the package.json "unpkg", "jsdelivr", "browser", and "main" fields, in that
+    ///   order, so packages that publish a separate build for CDNs resolve to it;
+    /// - It uses the "browser" and "import" conditions in package.json "exports" fields.
+    pub fn unpkg() -> Self {
+        Resolver::default()
+            .extensions(&[".js", ".json"])
+            .main_fields(&["unpkg", "jsdelivr", "browser", "main"])
+            .conditions(&["browser", "import"])
+    }
+
+    /// Create a resolver that finds TypeScript sources and declarations, like `tsc` does.
+    ///
+    /// - It resolves .ts, .tsx, .d.ts, .js, .jsx, and .json files, in that order;
+    /// - It expands symlinks;
+    /// - It uses the package.json "types", "typings", and "main" fields, in that order;
+    /// - It uses the `@types` package for packages that do not have typings.
+    pub fn typescript() -> Self {
+        Resolver::default()
+            .extensions(&[".ts", ".tsx", ".d.ts", ".js", ".jsx", ".json"])
+            .main_fields(&["types", "typings", "main"])
+            .types_packages(true)
+    }
+
+    /// Create a resolver that finds TypeScript sources and declarations like `tsc` does with
+    /// `"moduleResolution": "node16"` or `"nodenext"`, for `import` statements in ES modules.
+    ///
+    /// - It requires relative and absolute specifiers to include the file extension;
+    /// - A `.js`, `.mjs`, or `.cjs` file extension also finds the `.ts`, `.mts`, or `.cts` file
+    ///   and the declaration file that compile to it;
+    /// - It uses the package.json "types", "typings", and "main" fields, in that order;
+    /// - It uses the "types", "node", and "import" conditions in package.json "exports"
+    ///   fields. Like in `tsc`, "types" only wins if the package lists it before the others.
+    pub fn ts_node16_import() -> Self {
+        Resolver::typescript()
+            .fully_specified(true)
+            .typescript_extensions(true)
+            .conditions(&["types", "node", "import"])
+    }
+
+    /// Create a resolver that finds TypeScript sources and declarations like `tsc` does with
+    /// `"moduleResolution": "node16"` or `"nodenext"`, for `require()` calls and `import`
+    /// statements in CommonJS modules.
+    ///
+    /// - It resolves .ts, .tsx, .d.ts, .js, .jsx, and .json files, in that order;
+    /// - A `.js`, `.mjs`, or `.cjs` file extension also finds the `.ts`, `.mts`, or `.cts` file
+    ///   and the declaration file that compile to it;
+    /// - It uses the package.json "types", "typings", and "main" fields, in that order;
+    /// - It uses the "types", "node", and "require" conditions in package.json "exports"
+    ///   fields.
+    pub fn ts_node16_require() -> Self {
+        Resolver::typescript()
+            .typescript_extensions(true)
+            .conditions(&["types", "node", "require"])
+    }
+
+    /// Create a resolver that finds TypeScript sources and declarations like `tsc` does with
+    /// `"moduleResolution": "bundler"`.
+    ///
+    /// - It resolves .ts, .tsx, .d.ts, .js, .jsx, and .json files, in that order, so file
+    ///   extensions and index files are optional, like in bundlers;
+    /// - A `.js`, `.mjs`, or `.cjs` file extension also finds the `.ts`, `.mts`, or `.cts` file
+    ///   and the declaration file that compile to it;
+    /// - It uses the package.json "types", "typings", and "main" fields, in that order;
+    /// - It uses the "types" and "import" conditions in package.json "exports" fields.
+    pub fn ts_bundler() -> Self {
+        Resolver::typescript()
+            .typescript_extensions(true)
+            .conditions(&["types", "import"])
+    }
+
+    /// Create a resolver for toolchains that bundle WebAssembly modules.
+    ///
+    /// - It resolves .js, .json, .node, and .wasm files, in that order;
+    /// - It expands symlinks;
+    /// - It uses the package.json "main" field for bare specifier lookups;
+    /// - It uses the "wasm", "node", and "import" conditions in package.json "exports" fields,
+    ///   so packages that ship a WebAssembly build resolve to it.
+    pub fn wasm() -> Self {
+        Resolver::default()
+            .extensions(&[".js", ".json", ".node", ".wasm"])
+            .conditions(&["wasm", "node", "import"])
+    }
+
+    /// Create a resolver that finds stylesheets, like Sass and Less do.
+    ///
+    /// - It resolves .scss, .sass, .less, and .css files, in that order;
+    /// - It tries partial file names, like `_name.scss` for `name`;
+    /// - It resolves `~pkg/file` specifiers in node_modules folders;
+    /// - It uses the package.json "sass", "style", and "main" fields, in that order;
+    /// - It uses the "sass" and "style" conditions in package.json "exports" fields.
+    pub fn style() -> Self {
+        Resolver::default()
+            .extensions(&[".scss", ".sass", ".less", ".css"])
+            .main_fields(&["sass", "style", "main"])
+            .conditions(&["sass", "style"])
+            .tilde_prefix(true)
+            .partials(true)
+    }
+
+    /// Create a resolver that finds static assets, like images and fonts.
+    ///
+    /// - It resolves files with the given extensions, in order;
+    /// - It expands symlinks;
+    /// - It does not read package.json files, so bare specifiers like `pkg/fonts/a.woff2` resolve
+    ///   to files in the package folder, even if its "exports" field does not list them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::assets(&[".svg", ".png"]);
+    /// ```
+    pub fn assets<T>(extensions: T) -> Self
+    where
+        T: IntoIterator,
+        T::Item: ToString,
+    {
+        Resolver::default()
+            .extensions(extensions)
+            .use_package_json(false)
+    }
+
+    /// Create a resolver that behaves like Jest does by default.
+    ///
+    /// - It resolves .js, .mjs, .cjs, .jsx, .ts, .tsx, .json, and .node files, in that order;
+    /// - It expands symlinks;
+    /// - It uses the package.json "main" field, and ignores the "browser" field;
+    /// - It uses the "require", "node", and "node-addons" conditions in package.json "exports"
+    ///   fields, like the default `node` test environment.
+    ///
+    /// Use [`Resolver::module_directories`] and [`Resolver::module_paths`] for Jest's
+    /// `moduleDirectories` and `modulePaths` options. With the `regex` feature,
+    /// [`Resolver::rewrite`] works like the `moduleNameMapper` option.
+    pub fn jest() -> Self {
+        Resolver::default()
+            .extensions(&[".js", ".mjs", ".cjs", ".jsx", ".ts", ".tsx", ".json", ".node"])
+            .conditions(&["require", "node", "node-addons"])
+    }
+
     #[deprecated(since = "2.3.0", note = "use Resolver::default() instead")]
     pub fn new() -> Self {
         Resolver::default()
@@ -126,14 +1051,52 @@ impl Resolver {
     fn get_basedir(&self) -> Result<&Path, Error> {
         self.basedir
             .as_ref()
-            .ok_or_else(|| Error::UnconfiguredBasedir)
+            .ok_or(Error::UnconfiguredBasedir)
             .map(PathBuf::as_path)
     }
 
+    /// Get the root directory that `/`-prefixed specifiers are resolved against. This is the
+    /// filesystem root on Unix, and the root of the basedir's drive or share on Windows.
+    fn get_root(&self) -> PathBuf {
+        self.basedir
+            .as_deref()
+            .map(root_of)
+            .filter(|root| root.has_root())
+            .unwrap_or_else(|| PathBuf::from(ROOT))
+    }
+
     /// Create a new resolver with a different basedir.
     pub fn with_basedir(&self, basedir: PathBuf) -> Self {
         Resolver {
             basedir: Some(basedir),
+            fallback_basedirs: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    /// Create a new resolver that tries several basedirs in order, and uses the first one that
+    /// the specifier resolves from.
+    ///
+    /// This is useful for compilers with several source roots, or servers with several view
+    /// folders. If a specifier is not found from any of the basedirs, the error lists the paths
+    /// that were tried from all of them. Other errors are returned right away.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().with_basedirs(vec![
+    ///     PathBuf::from("/project/views"),
+    ///     PathBuf::from("/project/shared/views"),
+    /// ]);
+    /// resolver.resolve("./layout");
+    /// ```
+    pub fn with_basedirs(&self, basedirs: Vec<PathBuf>) -> Self {
+        let mut basedirs = basedirs.into_iter();
+        Resolver {
+            basedir: basedirs.next(),
+            fallback_basedirs: basedirs.collect(),
             ..self.clone()
         }
     }
@@ -158,10 +1121,9 @@ impl Resolver {
         T: IntoIterator,
         T::Item: ToString,
     {
-        Resolver {
-            extensions: normalize_extensions(extensions),
-            ..self
-        }
+        self.configure(|builder| {
+            builder.extensions(extensions);
+        })
     }
 
     /// Use a different set of main fields. Consumes the Resolver instance.
@@ -190,383 +1152,3235 @@ impl Resolver {
         T: IntoIterator,
         T::Item: ToString,
     {
-        Resolver {
-            main_fields: main_fields
-                .into_iter()
-                .map(|field| field.to_string())
-                .collect(),
-            ..self
-        }
+        self.configure(|builder| {
+            builder.main_fields(main_fields);
+        })
     }
 
-    /// Configure whether symlinks should be preserved. Consumes the Resolver instance.
+    /// Use a different set of module directory names. Consumes the Resolver instance.
+    /// The default is `&["node_modules"]`.
+    ///
+    /// Bare specifiers are looked up in folders with these names in the basedir and each of
+    /// its parents. When several names are given, they are checked in order in each folder
+    /// before moving on to the parent folder.
     ///
     /// # Examples
     ///
     /// ```rust
     /// use node_resolve::Resolver;
     ///
-    /// assert_eq!(Ok(PathBuf::from("./fixtures/symlink/node_modules/dep/main.js").canonicalize()),
-    ///     Resolver::default()
-    ///            .preserve_symlinks(true)
-    ///            .with_basedir(PathBuf::from("./fixtures/symlink"))
-    ///            .resolve("dep")
-    /// );
+    /// let resolver = Resolver::default()
+    ///     .module_directories(&["web_modules", "node_modules"])
+    ///     .with_basedir(PathBuf::from("/project/src"));
+    /// resolver.resolve("some-package");
     /// ```
+    pub fn module_directories<T>(self, module_directories: T) -> Self
+    where
+        T: IntoIterator,
+        T::Item: ToString,
+    {
+        self.configure(|builder| {
+            builder.module_directories(module_directories);
+        })
+    }
+
+    /// Also look up bare specifiers in `paths`, after the node_modules folders. Consumes the
+    /// Resolver instance. There are no extra paths by default.
+    ///
+    /// This is like the `NODE_PATH` environment variable in Node.js, and the `modulePaths`
+    /// option in Jest. Packages are looked up directly inside each path, in order, so
+    /// `/project/lib` finds `some-package` at `/project/lib/some-package`.
+    ///
+    /// # Examples
     ///
     /// ```rust
     /// use node_resolve::Resolver;
     ///
-    /// assert_eq!(Ok(PathBuf::from("./fixtures/symlink/linked/main.js").canonicalize()),
-    ///     Resolver::default()
-    ///            .preserve_symlinks(false)
-    ///            .with_basedir(PathBuf::from("./fixtures/symlink"))
-    ///            .resolve("dep")
-    /// };
+    /// let resolver = Resolver::default()
+    ///     .module_paths(vec![PathBuf::from("/project/lib")])
+    ///     .with_basedir(PathBuf::from("/project/src"));
+    /// resolver.resolve("some-package");
     /// ```
-    pub fn preserve_symlinks(self, preserve_symlinks: bool) -> Self {
-        Resolver {
-            preserve_symlinks,
-            ..self
-        }
+    pub fn module_paths(self, paths: Vec<PathBuf>) -> Self {
+        self.configure(|builder| {
+            builder.module_paths(paths);
+        })
     }
 
-    /// Resolve a `require('target')` argument.
-    pub fn resolve(&self, target: &str) -> Result<PathBuf, Error> {
-        // 1. If X is a core module
-        if is_core_module(target) {
-            // 1.a. Return the core module
-            return Ok(PathBuf::from(target));
-        }
-
-        // 2. If X begins with '/'
-        let basedir = if target.starts_with('/') {
-            // 2.a. Set Y to be the filesystem root
-            Path::new(ROOT)
-        } else {
-            self.get_basedir()?
-        };
-
-        // 3. If X begins with './' or '/' or '../'
-        if target.starts_with("./") || target.starts_with('/') || target.starts_with("../") {
-            let path = basedir.join(target);
-            return self
-                .resolve_as_file(&path)
-                .or_else(|_| self.resolve_as_directory(&path))
-                .and_then(|p| self.normalize(&p))
-                .map_err(InternalError::to_public);
-        }
-
-        self.resolve_node_modules(target)
-            .and_then(|p| self.normalize(&p))
-            .map_err(InternalError::to_public)
+    /// Only search the node_modules folders inside the workspace package that contains the
+    /// basedir, given the root folders of every workspace package. Consumes the Resolver
+    /// instance. By default, node_modules folders are searched up to the filesystem root.
+    ///
+    /// With this option, a package that is not a dependency of the workspace package is not
+    /// found, even if it is hoisted to the node_modules folder at the root of the repository.
+    /// This enforces the isolation that pnpm's `hoist=false` setting provides. When workspace
+    /// packages are nested, the innermost one is used. Basedirs outside every workspace
+    /// package are not restricted. Paths are compared as they are given, so the roots should
+    /// be absolute if the basedir is.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default()
+    ///     .workspace_packages(vec![
+    ///         PathBuf::from("/repo/packages/app"),
+    ///         PathBuf::from("/repo/packages/ui"),
+    ///     ])
+    ///     .with_basedir(PathBuf::from("/repo/packages/app/src"));
+    /// // Does not find /repo/node_modules/react unless the app depends on it.
+    /// resolver.resolve("react");
+    /// ```
+    pub fn workspace_packages(self, roots: Vec<PathBuf>) -> Self {
+        self.configure(|builder| {
+            builder.workspace_packages(roots);
+        })
     }
 
-    /// Normalize a path to a module. If symlinks should be preserved, this only removes
-    /// unnecessary `./`s and `../`s from the path. Else it does `realpath()`.
-    fn normalize(&self, path: &Path) -> Result<PathBuf, InternalError> {
-        if self.preserve_symlinks {
-            Ok(normalize_path(path))
-        } else {
-            path.canonicalize().map_err(Error::IOError).map_err(Into::into)
-        }
+    /// Use a different set of module directory names in the folder `dir` and its subfolders.
+    /// Consumes the Resolver instance.
+    ///
+    /// Unlike [`Resolver::scope`], this depends on the folder that is being searched, not on
+    /// the basedir. Resolving from `frontend/src` with `web_modules` configured for `frontend`
+    /// checks `frontend/src/web_modules` and `frontend/web_modules`, but uses the default
+    /// [module directories](Resolver::module_directories) outside `frontend`. When several
+    /// folders contain the searched folder, the most specific one is used. Paths are compared
+    /// as they are given, so `dir` should be absolute if the basedir is.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default()
+    ///     .module_directories_in(PathBuf::from("/project/frontend"), &["web_modules"])
+    ///     .with_basedir(PathBuf::from("/project/frontend/src"));
+    /// resolver.resolve("some-package");
+    /// ```
+    pub fn module_directories_in<T>(self, dir: PathBuf, module_directories: T) -> Self
+    where
+        T: IntoIterator,
+        T::Item: ToString,
+    {
+        self.configure(|builder| {
+            builder.module_directories_in(dir, module_directories);
+        })
     }
 
-    /// Resolve a path as a file. If `path` refers to a file, it is returned;
-    /// otherwise the `path` + each extension is tried.
-    fn resolve_as_file(&self, path: &Path) -> Result<PathBuf, InternalError> {
-        // 1. If X is a file, load X as JavaScript text.
-        if path.is_file() {
-            return Ok(path.to_path_buf());
-        }
+    /// Choose where node_modules lookups start when the basedir is inside a symlinked folder.
+    /// Consumes the Resolver instance. The default is [`AscentOrigin::Strategy`].
+    ///
+    /// Files that were resolved through a workspace symlink are usually resolved from again,
+    /// to find their own imports. This decides whether those lookups see the packages that are
+    /// installed next to the symlink, or the ones around the real folder. Resolved paths are
+    /// still expanded according to the [symlink strategy](Resolver::symlinks).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::{AscentOrigin, Resolver};
+    ///
+    /// let resolver = Resolver::default()
+    ///     .ascent_origin(AscentOrigin::Real)
+    ///     .with_basedir(PathBuf::from("/project/node_modules/workspace-package"));
+    /// resolver.resolve("its-own-dependency");
+    /// ```
+    pub fn ascent_origin(self, ascent_origin: AscentOrigin) -> Self {
+        self.configure(|builder| {
+            builder.ascent_origin(ascent_origin);
+        })
+    }
 
-        // 1. If X.js is a file, load X.js as JavaScript text.
-        // 2. If X.json is a file, parse X.json to a JavaScript object.
-        // 3. If X.node is a file, load X.node as binary addon.
-        let mut ext_path = path.to_path_buf();
-        if let Some(file_name) = ext_path.file_name().and_then(|name| name.to_str()).map(String::from) {
-            for ext in &self.extensions {
-                ext_path.set_file_name(format!("{}{}", file_name, ext));
-                if ext_path.is_file() {
-                    return Ok(ext_path);
-                }
-            }
-        }
+    /// Choose whether paths are tried as files or as directories first, or only as one of
+    /// them. Consumes the Resolver instance. The default is [`ProbeOrder::FileFirst`].
+    ///
+    /// This applies to relative and absolute specifiers, and to subpaths of packages without
+    /// an "exports" field. A bare specifier like `pkg` still finds the package folder.
+    /// Specifiers that end in `/` are always resolved as directories, and fully specified ones
+    /// as files. Routers that map a name to a folder of views can try the
+    /// folder first, and use a resolver with this option for those calls only.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::{ProbeOrder, Resolver};
+    ///
+    /// let resolver = Resolver::default()
+    ///     .probe_order(ProbeOrder::DirectoryFirst)
+    ///     .with_basedir(PathBuf::from("/project/views"));
+    /// // Finds /project/views/home/index.js, even if /project/views/home.js exists.
+    /// resolver.resolve("./home");
+    /// ```
+    pub fn probe_order(self, probe_order: ProbeOrder) -> Self {
+        self.configure(|builder| {
+            builder.probe_order(probe_order);
+        })
+    }
 
-        Err(Error::IOError(IOError::new(IOErrorKind::NotFound, "Not Found")).into())
+    /// Configure whether package.json files are used to find the entry point of a directory.
+    /// Consumes the Resolver instance. The default is `true`.
+    ///
+    /// When disabled, directories always resolve to their index file, and their package.json
+    /// files are not read. Setting empty [main fields](Resolver::main_fields) has the same
+    /// effect. The "exports" field of packages in node_modules is still used.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// assert_eq!(Ok(PathBuf::from("./fixtures/package-json/main-and-index/index.js")),
+    ///     Resolver::default()
+    ///         .use_package_main(false)
+    ///         .with_basedir(PathBuf::from("./fixtures"))
+    ///         .resolve("./package-json/main-and-index")
+    /// );
+    /// ```
+    pub fn use_package_main(self, use_package_main: bool) -> Self {
+        self.configure(|builder| {
+            builder.use_package_main(use_package_main);
+        })
     }
 
-    /// Resolve a path as a directory, using the "main" key from a package.json file if it
-    /// exists, or resolving to the index.EXT file if it exists.
-    fn resolve_as_directory(&self, path: &Path) -> Result<PathBuf, InternalError> {
-        if !path.is_dir() {
-            return Err(Error::IOError(IOError::new(IOErrorKind::NotFound, "Not Found")).into());
-        }
+    /// Configure whether folders inside a package that has no "exports" field can have their
+    /// own package.json file. Consumes the Resolver instance. The default is `true`.
+    ///
+    /// Like in Node.js, `@scope/pkg/dir` first checks for a `dir` file, then for a
+    /// `dir/package.json` file with a "main" field, and then for an index file. When this is
+    /// disabled, the nested package.json file is skipped, so folders inside packages always
+    /// resolve to their index file. The package folder itself still uses its package.json file.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default()
+    ///     .nested_package_json(false)
+    ///     .with_basedir(PathBuf::from("/project/src"));
+    /// resolver.resolve("@scope/pkg/dir");
+    /// ```
+    pub fn nested_package_json(self, nested_package_json: bool) -> Self {
+        self.configure(|builder| {
+            builder.nested_package_json(nested_package_json);
+        })
+    }
 
-        // 1. If X/package.json is a file, use it.
-        let pkg_path = path.join("package.json");
-        if pkg_path.is_file() {
-            let main = self.resolve_package_main(&pkg_path);
-            if main.is_ok() {
-                return main;
-            }
-        }
+    /// Configure whether package.json files are read at all. Consumes the Resolver instance.
+    /// The default is `true`.
+    ///
+    /// When disabled, the "main", "exports", "imports", and "type" fields are ignored.
+    /// Directories resolve to their index file, and bare specifiers resolve directly to files
+    /// inside packages in node_modules. This is useful for static assets, which are not
+    /// subject to the module rules of their packages.
+    pub fn use_package_json(self, use_package_json: bool) -> Self {
+        self.configure(|builder| {
+            builder.use_package_json(use_package_json);
+        })
+    }
 
-        // 2. LOAD_INDEX(X)
-        self.resolve_index(path)
+    /// Configure whether package.json files with trailing commas are accepted. Consumes the
+    /// Resolver instance. The default is `false`, which rejects them like Node.js does.
+    ///
+    /// A leading byte order mark is always ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().tolerant_package_json(true);
+    /// ```
+    pub fn tolerant_package_json(self, tolerant_package_json: bool) -> Self {
+        self.configure(|builder| {
+            builder.tolerant_package_json(tolerant_package_json);
+        })
     }
 
-    /// Resolve using the package.json "main" key.
-    fn resolve_package_main(&self, pkg_path: &Path) -> Result<PathBuf, InternalError> {
-        let pkg_dir = pkg_path.parent().unwrap_or_else(|| Path::new(ROOT));
-        let file = File::open(pkg_path).map_err(Error::IOError)?;
-        let pkg: Value = serde_json::from_reader(file).map_err(Error::JSONError)?;
-        if !pkg.is_object() {
-            return Err(RecoverableError::NonObjectPackageJson.into());
-        }
+    /// Ignore the package.json "exports" field of the packages named in `packages`. Consumes
+    /// the Resolver instance. By default, the "exports" field of every package is used.
+    ///
+    /// Bare specifiers for these packages resolve to files in the package folder directly,
+    /// like they did before "exports" existed. This is an escape hatch for packages with broken
+    /// "exports" fields, and for tools that need to reach into the internals of a package.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().bypass_exports(&["react-dom"]);
+    /// resolver.resolve("react-dom/cjs/react-dom.development.js");
+    /// ```
+    pub fn bypass_exports<T>(self, packages: T) -> Self
+    where
+        T: IntoIterator,
+        T::Item: ToString,
+    {
+        self.configure(|builder| {
+            builder.bypass_exports(packages);
+        })
+    }
 
-        let main_field = self
-            .main_fields
-            .iter()
-            .find(|name| pkg[name].is_string())
-            .and_then(|name| pkg[name].as_str());
-        match main_field {
-            Some(target) => {
-                let path = pkg_dir.join(target);
-                self.resolve_as_file(&path)
-                    .or_else(|_| self.resolve_as_directory(&path))
-            }
-            None => {
-                Err(RecoverableError::MissingMain.into())
-            }
-        }
+    /// Refuse to resolve bare specifiers for the packages that match `patterns`. Consumes the
+    /// Resolver instance. By default, every package may be used.
+    ///
+    /// A pattern is a package name like `left-pad`, where `*` matches any characters, like
+    /// `internal-*` or `@legacy/*`. Resolving a matching specifier, or a subpath like
+    /// `left-pad/index.js`, fails with [`Error::DeniedPackage`] before any file is checked.
+    /// Specifiers are checked after [environment variables](Resolver::expand_env) are
+    /// expanded. Builtin modules and relative specifiers are not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::{Error, Resolver};
+    ///
+    /// let resolver = Resolver::default()
+    ///     .deny(&["left-pad", "internal-*"])
+    ///     .with_basedir(PathBuf::from("/project"));
+    /// assert!(matches!(resolver.resolve("left-pad"), Err(Error::DeniedPackage { .. })));
+    /// ```
+    pub fn deny<T>(self, patterns: T) -> Self
+    where
+        T: IntoIterator,
+        T::Item: ToString,
+    {
+        self.configure(|builder| {
+            builder.deny(patterns);
+        })
     }
 
-    /// Resolve a directory to its index.EXT.
-    fn resolve_index(&self, path: &Path) -> Result<PathBuf, InternalError> {
-        // 1. If X/index.js is a file, load X/index.js as JavaScript text.
-        // 2. If X/index.json is a file, parse X/index.json to a JavaScript object.
-        // 3. If X/index.node is a file, load X/index.node as binary addon.
-        for ext in self.extensions.iter() {
-            let ext_path = path.join(format!("index{}", ext));
-            if ext_path.is_file() {
-                return Ok(ext_path);
-            }
-        }
+    /// Use a different set of conditions for package.json "exports" fields. Consumes the
+    /// Resolver instance. The default is `&["node", "require"]`.
+    ///
+    /// When a package maps a subpath to different files for different environments, the first
+    /// file whose condition is in this list is used. The `"default"` condition always matches.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().conditions(&["node", "import", "development"]);
+    /// ```
+    pub fn conditions<T>(self, conditions: T) -> Self
+    where
+        T: IntoIterator,
+        T::Item: ToString,
+    {
+        self.configure(|builder| {
+            builder.conditions(conditions);
+        })
+    }
 
-        Err(Error::IOError(IOError::new(
-            IOErrorKind::NotFound,
-            "Not Found",
-        )).into())
+    /// Configure whether symlinks should be preserved. Consumes the Resolver instance.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// assert_eq!(Ok(PathBuf::from("./fixtures/symlink/node_modules/dep/main.js").canonicalize()),
+    ///     Resolver::default()
+    ///            .preserve_symlinks(true)
+    ///            .with_basedir(PathBuf::from("./fixtures/symlink"))
+    ///            .resolve("dep")
+    /// );
+    /// ```
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// assert_eq!(Ok(PathBuf::from("./fixtures/symlink/linked/main.js").canonicalize()),
+    ///     Resolver::default()
+    ///            .preserve_symlinks(false)
+    ///            .with_basedir(PathBuf::from("./fixtures/symlink"))
+    ///            .resolve("dep")
+    /// };
+    /// ```
+    pub fn preserve_symlinks(self, preserve_symlinks: bool) -> Self {
+        self.configure(|builder| {
+            builder.preserve_symlinks(preserve_symlinks);
+        })
     }
 
-    /// Resolve by walking up node_modules folders.
-    fn resolve_node_modules(&self, target: &str) -> Result<PathBuf, InternalError> {
-        let basedir = self.get_basedir()?;
-        let node_modules = basedir.join("node_modules");
-        if node_modules.is_dir() {
-            let path = node_modules.join(target);
-            let result = self
-                .resolve_as_file(&path)
-                .or_else(|_| self.resolve_as_directory(&path));
-            if result.is_ok() {
-                return result;
-            }
-        }
+    /// Configure how symlinks are handled. Consumes the Resolver instance.
+    /// The default is [`SymlinkStrategy::RealpathResult`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::{Resolver, SymlinkStrategy};
+    ///
+    /// assert_eq!(Ok(PathBuf::from("./fixtures/symlink-walk/packages/node_modules/helper/index.js").canonicalize()),
+    ///     Resolver::default()
+    ///            .symlinks(SymlinkStrategy::RealpathDirectories)
+    ///            .with_basedir(PathBuf::from("./fixtures/symlink-walk/app/node_modules/lib"))
+    ///            .resolve("helper")
+    /// );
+    /// ```
+    pub fn symlinks(self, symlinks: SymlinkStrategy) -> Self {
+        self.configure(|builder| {
+            builder.symlinks(symlinks);
+        })
+    }
 
-        match basedir.parent() {
-            Some(parent) => self
-                .with_basedir(parent.to_path_buf())
-                .resolve_node_modules(target),
-            None => Err(Error::IOError(IOError::new(
-                IOErrorKind::NotFound,
-                "Not Found",
-            )).into()),
-        }
+    /// Configure whether the extension order depends on the package `"type"`. Consumes the
+    /// Resolver instance. This is disabled by default.
+    ///
+    /// When enabled, files inside a package with `"type": "module"` try the `.mjs` extension
+    /// before all configured extensions, and files in other packages try `.cjs` first. The
+    /// package is found by looking for the nearest package.json file.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// assert_eq!(Ok(PathBuf::from("./fixtures/package-type/module/file.mjs")),
+    ///     Resolver::default()
+    ///         .package_type_extensions(true)
+    ///         .with_basedir(PathBuf::from("./fixtures/package-type/module"))
+    ///         .resolve("./file")
+    /// );
+    /// ```
+    pub fn package_type_extensions(self, package_type_extensions: bool) -> Self {
+        self.configure(|builder| {
+            builder.package_type_extensions(package_type_extensions);
+        })
     }
-}
 
-/// Remove excess components like `/./` and `/../` from a `Path`.
-fn normalize_path(p: &Path) -> PathBuf {
-    let mut normalized = PathBuf::from("/");
-    for part in p.components() {
-        match part {
-            PathComponent::Prefix(ref prefix) => {
-                normalized.push(prefix.as_os_str());
-            }
-            PathComponent::RootDir => {
-                normalized.push("/");
-            }
-            PathComponent::ParentDir => {
-                normalized.pop();
-            }
-            PathComponent::CurDir => {
-                // Nothing
-            }
-            PathComponent::Normal(name) => {
-                normalized.push(name);
-            }
-        }
+    /// Configure whether relative and absolute specifiers must point to a file exactly.
+    /// Consumes the Resolver instance. This is disabled by default.
+    ///
+    /// When enabled, no extensions are appended and directories are not resolved to their
+    /// index file, matching the behaviour of ES modules in Node.js. Bare specifiers for a
+    /// package root still use the package.json "main" field.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default()
+    ///     .fully_specified(true)
+    ///     .with_basedir(PathBuf::from("./fixtures/extensions"));
+    /// assert!(resolver.resolve("./js-file").is_err());
+    /// assert!(resolver.resolve("./js-file.js").is_ok());
+    /// ```
+    pub fn fully_specified(self, fully_specified: bool) -> Self {
+        self.configure(|builder| {
+            builder.fully_specified(fully_specified);
+        })
     }
-    normalized
-}
 
-fn normalize_extensions<T>(extensions: T) -> Vec<String>
-where
-    T: IntoIterator,
-    T::Item: ToString,
-{
-    extensions
-        .into_iter()
-        .map(|ext| ext.to_string())
-        .map(|ext| {
-            if ext.starts_with('.') {
-                ext
-            } else {
-                format!(".{}", ext)
-            }
+    /// Configure how ES module specifiers are resolved. Consumes the Resolver instance. The
+    /// default is [`SpecifierResolution::Explicit`].
+    ///
+    /// This is meant for resolvers created with [`Resolver::node_esm`], and for
+    /// `resolve_import_meta()`. [`SpecifierResolution::Node`] re-enables extension guessing
+    /// and index lookups, like `node --experimental-specifier-resolution=node` did. It also
+    /// sets [`Resolver::fully_specified`] accordingly.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::{Resolver, SpecifierResolution};
+    ///
+    /// let resolver = Resolver::node_esm()
+    ///     .specifier_resolution(SpecifierResolution::Node)
+    ///     .with_basedir(PathBuf::from("./fixtures/extensions"));
+    /// assert!(resolver.resolve("./js-file").is_ok());
+    /// ```
+    pub fn specifier_resolution(self, specifier_resolution: SpecifierResolution) -> Self {
+        self.configure(|builder| {
+            builder.specifier_resolution(specifier_resolution);
         })
-        .collect()
-}
+    }
+
+    /// Configure whether resolved .json files are marked as JSON modules. Consumes the
+    /// Resolver instance. This is disabled by default, and enabled by [`Resolver::node_esm`].
+    ///
+    /// ES modules can only import JSON files with an import attribute, like
+    /// `import data from './data.json' with { type: 'json' }`. When enabled,
+    /// [`Resolution::needs_json_module`] is set for .json files, so loaders can add the
+    /// attribute or report a missing one. Resolution itself is not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolution = Resolver::node_esm()
+    ///     .with_basedir(PathBuf::from("./fixtures/extensions"))
+    ///     .resolve_full("./json-file.json")?;
+    /// assert!(resolution.needs_json_module);
+    /// ```
+    pub fn json_modules(self, json_modules: bool) -> Self {
+        self.configure(|builder| {
+            builder.json_modules(json_modules);
+        })
+    }
+
+    /// Configure whether `~/` and `${VAR}` are expanded in absolute specifiers. Consumes the
+    /// Resolver instance. This is disabled by default.
+    ///
+    /// When enabled, a leading `~/` is replaced by the home directory of the current user, and
+    /// `${VAR}` is replaced by the value of the `VAR` environment variable in specifiers that
+    /// start with `~/`, `/`, or `${`. An unset variable results in an
+    /// [`Error::UndefinedVariable`]. This is meant for tools that accept paths from users; it
+    /// is not something Node.js does.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// Resolver::default()
+    ///     .expand_env(true)
+    ///     .resolve("${PROJECT_ROOT}/lib/index.js");
+    /// ```
+    pub fn expand_env(self, expand_env: bool) -> Self {
+        self.configure(|builder| {
+            builder.expand_env(expand_env);
+        })
+    }
+
+    /// Configure whether relative specifiers may reach into other packages in node_modules
+    /// folders. Consumes the Resolver instance. This is allowed by default, like in Node.js.
+    ///
+    /// When enabled, specifiers like `../node_modules/foo/lib/private.js` fail with
+    /// [`Error::PackageBoundary`] without checking the filesystem, so lint tools can insist that
+    /// packages are imported by name. Relative specifiers inside a package in node_modules may
+    /// still refer to files in the same package.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::{Error, Resolver};
+    ///
+    /// let result = Resolver::default()
+    ///     .strict_package_boundaries(true)
+    ///     .with_basedir(PathBuf::from("/project/src"))
+    ///     .resolve("../node_modules/foo/lib/private.js");
+    /// assert!(matches!(result, Err(Error::PackageBoundary { .. })));
+    /// ```
+    pub fn strict_package_boundaries(self, strict_package_boundaries: bool) -> Self {
+        self.configure(|builder| {
+            builder.strict_package_boundaries(strict_package_boundaries);
+        })
+    }
+
+    /// Configure whether folder mappings ending in `/` in the "exports" and "imports" fields are
+    /// supported. Consumes the Resolver instance. This is disabled by default.
+    ///
+    /// Older packages use mappings like `"./lib/": "./lib/"` to export every file in a folder.
+    /// Node.js removed support for these in favour of patterns like `"./lib/*": "./lib/*"`.
+    /// When enabled, [`Resolver::resolve_full`] reports a [`Warning::FolderMapping`] for every
+    /// folder mapping that is used.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default()
+    ///     .folder_exports(true)
+    ///     .with_basedir(PathBuf::from("/project"));
+    /// resolver.resolve("old-package/lib/util.js");
+    /// ```
+    pub fn folder_exports(self, folder_exports: bool) -> Self {
+        self.configure(|builder| {
+            builder.folder_exports(folder_exports);
+        })
+    }
+
+    /// Configure whether the legacy package.json `"directories": { "lib": "..." }` field is
+    /// used. Consumes the Resolver instance. This is disabled by default.
+    ///
+    /// When enabled, a package without a usable main field resolves to the index file inside
+    /// its `directories.lib` folder, before falling back to its own index file.
+    pub fn directories_lib(self, directories_lib: bool) -> Self {
+        self.configure(|builder| {
+            builder.directories_lib(directories_lib);
+        })
+    }
+
+    /// Configure whether dependencies that are declared with the Yarn `link:` and `portal:`
+    /// protocols are resolved from their package.json declaration. Consumes the Resolver
+    /// instance. This is disabled by default.
+    ///
+    /// Package managers usually create symlinks for these dependencies in node_modules folders,
+    /// which are followed like any other package. When there is no such symlink, for example
+    /// with Yarn Plug'n'Play, this finds the folder that the package.json files above the
+    /// basedir point to instead. Specifiers inside the linked folder are then resolved from its
+    /// own location, using its own node_modules folders.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// // With "local-pkg": "link:../local-pkg" in /project/package.json
+    /// let resolver = Resolver::default()
+    ///     .link_dependencies(true)
+    ///     .with_basedir(PathBuf::from("/project/src"));
+    /// resolver.resolve("local-pkg");
+    /// ```
+    pub fn link_dependencies(self, link_dependencies: bool) -> Self {
+        self.configure(|builder| {
+            builder.link_dependencies(link_dependencies);
+        })
+    }
+
+    /// Configure whether dependencies that are declared with the `file:` protocol are resolved
+    /// from their package.json declaration. Consumes the Resolver instance. This is disabled by
+    /// default.
+    ///
+    /// Like [`Resolver::link_dependencies`], this is only used when a package is not found in
+    /// any node_modules folder, so workspace tools can resolve straight to the source folders
+    /// that the manifests point to. `file:` dependencies that point to tarballs are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// // With "sibling": "file:../sibling" in /project/package.json
+    /// let resolver = Resolver::default()
+    ///     .file_dependencies(true)
+    ///     .with_basedir(PathBuf::from("/project/src"));
+    /// resolver.resolve("sibling");
+    /// ```
+    pub fn file_dependencies(self, file_dependencies: bool) -> Self {
+        self.configure(|builder| {
+            builder.file_dependencies(file_dependencies);
+        })
+    }
+
+    /// Configure whether JavaScript file extensions in specifiers and "exports" targets also
+    /// find the TypeScript files that compile to them. Consumes the Resolver instance. This is
+    /// disabled by default.
+    ///
+    /// When enabled, `./file.js` also finds `./file.ts`, `./file.tsx`, and `./file.d.ts`, in
+    /// that order, before `./file.js` itself. `.mjs` finds `.mts` and `.d.mts` files, and
+    /// `.cjs` finds `.cts` and `.d.cts` files. This is what `tsc` does.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::typescript()
+    ///     .typescript_extensions(true)
+    ///     .with_basedir(PathBuf::from("/project/src"));
+    /// // Finds /project/src/helper.ts
+    /// resolver.resolve("./helper.js");
+    /// ```
+    pub fn typescript_extensions(self, typescript_extensions: bool) -> Self {
+        self.configure(|builder| {
+            builder.typescript_extensions(typescript_extensions);
+        })
+    }
+
+    /// Configure whether bare specifiers for packages without typings are resolved in their
+    /// DefinitelyTyped `@types` package. Consumes the Resolver instance. This is disabled by
+    /// default, and enabled by the TypeScript presets.
+    ///
+    /// When a bare specifier does not resolve to a TypeScript or declaration file, it is
+    /// resolved again in the `@types` package with the same name, like `@types/pkg/sub` for
+    /// `pkg/sub`. Scoped names are mangled the same way as on DefinitelyTyped, so
+    /// `@scope/pkg` becomes `@types/scope__pkg`. Unlike `tsc`, the package's own folder is
+    /// preferred even if the `@types` package is in a closer node_modules folder.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default()
+    ///     .extensions(&[".d.ts", ".js"])
+    ///     .types_packages(true)
+    ///     .with_basedir(PathBuf::from("/project/src"));
+    /// // Finds /project/node_modules/@types/react/index.d.ts
+    /// resolver.resolve("react");
+    /// ```
+    pub fn types_packages(self, types_packages: bool) -> Self {
+        self.configure(|builder| {
+            builder.types_packages(types_packages);
+        })
+    }
+
+    /// Configure whether a leading `~` marks a bare specifier, like `~pkg/file` in stylesheets.
+    /// Consumes the Resolver instance. This is disabled by default.
+    ///
+    /// `~/` is not affected, so it can still be expanded to the home directory.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().tilde_prefix(true);
+    /// resolver.resolve("~bootstrap/scss/variables");
+    /// ```
+    pub fn tilde_prefix(self, tilde_prefix: bool) -> Self {
+        self.configure(|builder| {
+            builder.tilde_prefix(tilde_prefix);
+        })
+    }
+
+    /// Configure whether partial file names are tried, like `_name.scss` for `name` and
+    /// `_index.scss` for a directory. Consumes the Resolver instance. This is disabled by
+    /// default.
+    ///
+    /// Partials are tried after the file names without an underscore.
+    pub fn partials(self, partials: bool) -> Self {
+        self.configure(|builder| {
+            builder.partials(partials);
+        })
+    }
+
+    /// Cache filesystem lookups in `cache`. Consumes the Resolver instance. By default, nothing
+    /// is cached.
+    ///
+    /// The cache remembers which files and directories exist and the contents of package.json
+    /// files. Changes to the filesystem are not noticed until the cache is cleared. A cache can
+    /// be shared by several resolvers, even if they have different options.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::{MemoryCache, Resolver};
+    /// use std::sync::Arc;
+    ///
+    /// let cache = Arc::new(MemoryCache::default());
+    /// let resolver = Resolver::default().cache(cache.clone());
+    /// ```
+    pub fn cache(self, cache: Arc<dyn ResolutionCache>) -> Self {
+        self.configure(|builder| {
+            builder.cache(cache);
+        })
+    }
+
+    /// Cache filesystem lookups in an [`LruCache`] that holds up to `capacity` entries of each
+    /// kind. Consumes the Resolver instance.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().cache_capacity(10_000);
+    /// ```
+    pub fn cache_capacity(self, capacity: usize) -> Self {
+        self.configure(|builder| {
+            builder.cache_capacity(capacity);
+        })
+    }
+
+    /// Use a different filesystem. Consumes the Resolver instance. The default is the real
+    /// filesystem.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::{Resolver, SnapshotFileSystem};
+    /// use std::sync::Arc;
+    ///
+    /// let snapshot: SnapshotFileSystem = vec!["/project/index.js"].into_iter().collect();
+    /// assert_eq!(Ok(PathBuf::from("/project/index.js")),
+    ///     Resolver::default()
+    ///         .filesystem(Arc::new(snapshot))
+    ///         .resolve("/project")
+    /// );
+    /// ```
+    pub fn filesystem(self, fs: Arc<dyn FileSystem>) -> Self {
+        self.configure(|builder| {
+            builder.filesystem(fs);
+        })
+    }
+
+    /// Configure whether files and folders are found regardless of the case of their names.
+    /// Consumes the Resolver instance. This is disabled by default.
+    ///
+    /// This emulates the default filesystems of macOS and Windows on a case-sensitive
+    /// filesystem, by searching directory listings for names that only differ in case, so a
+    /// project can be checked on Linux for imports that only work there. The filesystem is
+    /// wrapped in a [`CaseInsensitiveFileSystem`], also if it is replaced later. There is no
+    /// need to enable this on a filesystem that is already case-insensitive.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// // Resolves to src/Button.js, like it would on macOS.
+    /// let resolver = Resolver::default().case_insensitive(true);
+    /// let path = resolver.with_basedir(PathBuf::from("./src")).resolve("./button");
+    /// ```
+    pub fn case_insensitive(self, case_insensitive: bool) -> Self {
+        self.configure(|builder| {
+            builder.case_insensitive(case_insensitive);
+        })
+    }
+
+    /// Use a different environment for environment variables and the current working
+    /// directory. Consumes the Resolver instance. The default is the environment of the
+    /// process.
+    ///
+    /// The environment is used to expand `~/` and `${VAR}` with [`Resolver::expand_env`], to
+    /// read `NODE_PATH` with [`Resolver::node_path`], and to make relative paths absolute. Use
+    /// an empty [`FixedEnvironment`] for hermetic builds that must not depend on ambient state.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::{FixedEnvironment, Resolver};
+    /// use std::sync::Arc;
+    ///
+    /// let resolver = Resolver::default().environment(Arc::new(FixedEnvironment::new()));
+    /// ```
+    pub fn environment(self, environment: Arc<dyn Environment>) -> Self {
+        self.configure(|builder| {
+            builder.environment(environment);
+        })
+    }
+
+    /// Configure whether the folders in the `NODE_PATH` environment variable are searched for
+    /// packages. Consumes the Resolver instance. This is disabled by default.
+    ///
+    /// Like in Node.js, the folders are separated by `:`, or by `;` on Windows, and they are
+    /// searched after all node_modules folders and [module paths](Resolver::module_paths).
+    /// The variable is read from the [environment](Resolver::environment) on every resolution.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().node_path(true);
+    /// ```
+    pub fn node_path(self, node_path: bool) -> Self {
+        self.configure(|builder| {
+            builder.node_path(node_path);
+        })
+    }
+
+    /// Return resolved paths relative to `root`, instead of absolute paths. Consumes the
+    /// Resolver instance. By default, resolved paths are absolute.
+    ///
+    /// This applies to every method that resolves paths: [`Resolver::resolve`],
+    /// [`Resolver::resolve_full`] (for the resolved, canonical, package.json and watched paths
+    /// of the [`Resolution`]), [`Resolver::resolve_directory`], [`Resolver::resolve_in_package`],
+    /// [`Resolver::resolve_bin`], [`Resolver::interpretations`], and
+    /// [`Resolver::candidates`]. Lookup folders, like the ones from
+    /// [`Resolver::node_modules_paths`], and the paths in errors and warnings stay absolute.
+    ///
+    /// The relative paths are normalized and use `/` as the separator on every platform, like
+    /// `src/index.js`, or `../shared/index.js` for files outside the root, so they can be
+    /// embedded in generated code as they are. Builtin modules are returned as they are. A
+    /// relative root is relative to the current working directory. When symlinks are
+    /// expanded, the root should not contain symlinks either, or results inside it will be
+    /// reached through `..` segments.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default()
+    ///     .output_relative_to(PathBuf::from("/project"))
+    ///     .with_basedir(PathBuf::from("/project/src"));
+    /// assert_eq!(Ok(PathBuf::from("node_modules/react/index.js")), resolver.resolve("react"));
+    /// ```
+    pub fn output_relative_to(self, root: PathBuf) -> Self {
+        self.configure(|builder| {
+            builder.output_relative_to(root);
+        })
+    }
+
+    /// Report every filesystem access to `observer`. Consumes the Resolver instance. By
+    /// default, accesses are not reported.
+    ///
+    /// See [`Observer`] for an example.
+    pub fn observer(self, observer: Arc<dyn Observer>) -> Self {
+        self.configure(|builder| {
+            builder.observer(observer);
+        })
+    }
+
+    /// Set the JavaScript runtime that determines which specifiers are builtin modules.
+    /// Consumes the Resolver instance. The default is [`Runtime::Node`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::{Resolver, Runtime};
+    ///
+    /// assert_eq!(Ok(PathBuf::from("electron")),
+    ///     Resolver::default()
+    ///         .target_runtime(Runtime::Electron)
+    ///         .resolve("electron")
+    /// );
+    /// ```
+    pub fn target_runtime(self, runtime: Runtime) -> Self {
+        self.configure(|builder| {
+            builder.target_runtime(runtime);
+        })
+    }
+
+    /// Configure the conditions, main fields, extensions, and builtin modules for the
+    /// environment that resolved modules will run in. Consumes the Resolver instance.
+    ///
+    /// Conditions that do not identify an environment, like `"import"` or `"development"`, are
+    /// kept, so this can be combined with presets like [`Resolver::node_esm`]. See [`Target`]
+    /// for what each target configures.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::{Resolver, Target};
+    ///
+    /// let resolver = Resolver::node_esm().target(Target::Browser);
+    /// ```
+    pub fn target(self, target: Target) -> Self {
+        self.configure(|builder| {
+            builder.target(target);
+        })
+    }
+
+    /// Configure whether the hottest packages and the slowest resolutions are recorded for the
+    /// [cache report](Resolver::cache_report). Consumes the Resolver instance. This is
+    /// disabled by default.
+    ///
+    /// Recording them takes a lock for every resolution, so this is meant for tuning, not for
+    /// production builds.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().detailed_stats(true);
+    /// ```
+    pub fn detailed_stats(self, detailed_stats: bool) -> Self {
+        self.configure(|builder| {
+            builder.detailed_stats(detailed_stats);
+        })
+    }
+
+    /// Limit the work that a single resolution may do. Consumes the Resolver instance. The
+    /// default is no limits.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::{Budget, Resolver};
+    ///
+    /// let resolver = Resolver::default().budget(Budget::default().max_package_json_reads(16));
+    /// ```
+    pub fn budget(self, budget: Budget) -> Self {
+        self.configure(|builder| {
+            builder.budget(budget);
+        })
+    }
+
+    /// Configure how many times a filesystem operation is retried after a transient error.
+    /// Consumes the Resolver instance. The default is `0`, which fails right away.
+    ///
+    /// Network filesystems can fail with errors like `EINTR` or `EAGAIN` that go away when
+    /// trying again. Those are retried after a short pause, which doubles every time. If the
+    /// last attempt fails too, resolution fails with [`Error::TransientIOError`]. Other errors,
+    /// like [`Error::PermissionDenied`], are never retried.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().io_retries(3);
+    /// ```
+    pub fn io_retries(self, io_retries: usize) -> Self {
+        self.configure(|builder| {
+            builder.io_retries(io_retries);
+        })
+    }
+
+    /// Configure whether a missing permission to look at a path fails the resolution.
+    /// Consumes the Resolver instance. This is disabled by default.
+    ///
+    /// By default, paths that can not be checked, for example because a parent directory is
+    /// not readable, count as missing, like they do in Node.js. Resolution then moves on to
+    /// the next candidate, and [`Resolver::resolve_full`] reports a
+    /// [`Warning::PermissionDenied`]. When enabled, resolution fails with
+    /// [`Error::PermissionDenied`] instead, which shows the real cause of a failure in
+    /// sandboxes and CI environments with unusual permissions.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().strict_permissions(true);
+    /// ```
+    pub fn strict_permissions(self, strict_permissions: bool) -> Self {
+        self.configure(|builder| {
+            builder.strict_permissions(strict_permissions);
+        })
+    }
+
+    /// Configure whether expanded symlinks keep the `\\?\` prefix on Windows. Consumes the
+    /// Resolver instance. This is disabled by default.
+    ///
+    /// Expanding symlinks on Windows returns verbatim paths like `\\?\C:\project\index.js`,
+    /// or `\\?\UNC\server\share\index.js` on network shares, which many JavaScript tools do
+    /// not accept. By default, such paths are turned back into `C:\project\index.js` and
+    /// `\\server\share\index.js` if they mean the same without the prefix. Long paths are
+    /// still accessed with the prefix internally. Enable this to get the verbatim paths as they
+    /// are. This does nothing on other platforms.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().verbatim_paths(true);
+    /// ```
+    pub fn verbatim_paths(self, verbatim_paths: bool) -> Self {
+        self.configure(|builder| {
+            builder.verbatim_paths(verbatim_paths);
+        })
+    }
+
+    /// Use a different resolver for basedirs inside `dir`. Consumes the Resolver instance.
+    ///
+    /// This lets one resolver handle projects that need different options in different
+    /// places, like a monorepo with browser and server packages. When several scopes contain
+    /// the basedir, the most specific one is used. The scoped resolver gets the basedir of this
+    /// resolver. Paths are compared as they are given, so `dir` should be absolute if the
+    /// basedir is.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default()
+    ///     .scope(PathBuf::from("/project/packages/web"), Resolver::webpack_web())
+    ///     .with_basedir(PathBuf::from("/project/packages/web/src"));
+    /// // Resolves using the "browser" field.
+    /// resolver.resolve("some-package");
+    /// ```
+    pub fn scope(self, dir: PathBuf, resolver: Resolver) -> Self {
+        self.configure(|builder| {
+            builder.scope(dir, resolver);
+        })
+    }
+
+    /// Always resolve the package `name` from `dir`, instead of looking for it in node_modules
+    /// folders. Consumes the Resolver instance.
+    ///
+    /// This is useful for test harnesses and tools that swap out the implementation of a
+    /// package. Subpaths like `name/sub` are resolved inside `dir`, using the "exports" field
+    /// of its package.json if there is one. If the target does not exist in `dir`, the
+    /// resolution fails without checking node_modules folders.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default()
+    ///     .override_package("react", PathBuf::from("/project/test/mock-react"))
+    ///     .with_basedir(PathBuf::from("/project/src"));
+    /// resolver.resolve("react");
+    /// ```
+    pub fn override_package<S: ToString>(self, name: S, dir: PathBuf) -> Self {
+        self.configure(|builder| {
+            builder.override_package(name, dir);
+        })
+    }
+
+    /// Resolve a `require('target')` argument.
+    pub fn resolve(&self, target: &str) -> Result<PathBuf, Error> {
+        self.resolve_with_context(target, &mut Context::default())
+            .map(|path| self.output_path(path))
+    }
+
+    /// Make a resolved path relative to the [output root](Resolver::output_relative_to), if
+    /// there is one.
+    pub(crate) fn output_path(&self, path: PathBuf) -> PathBuf {
+        let root = match &self.output_root {
+            Some(root) if path.is_absolute() => root,
+            _ => return path,
+        };
+        let root = match self.environment.current_dir() {
+            Some(cwd) if root.is_relative() => normalize(&cwd.join(root)),
+            _ => normalize(root),
+        };
+        relative_path(&normalize(&path), &root).unwrap_or(path)
+    }
+
+    /// Get the node_modules folders that bare specifiers are looked up in from `basedir`,
+    /// nearest first, like `module.paths` in Node.js.
+    ///
+    /// A relative basedir is relative to the current working directory. Symlinks in the
+    /// basedir are expanded like they are during resolution, depending on the
+    /// [`SymlinkStrategy`]. The folders do not need to exist; see
+    /// [`Resolver::existing_node_modules_paths`] to only get the ones that do.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let paths = Resolver::default().node_modules_paths(Path::new("/project/src"));
+    /// assert_eq!(PathBuf::from("/project/src/node_modules"), paths[0]);
+    /// assert_eq!(PathBuf::from("/project/node_modules"), paths[1]);
+    /// ```
+    pub fn node_modules_paths(&self, basedir: &Path) -> Vec<PathBuf> {
+        let basedir = match self.environment.current_dir() {
+            Some(cwd) if basedir.is_relative() => normalize(&cwd.join(basedir)),
+            _ => basedir.to_path_buf(),
+        };
+        let basedir = self.ascent_start(&basedir, &mut Context::default());
+        self.node_modules_dirs(&basedir)
+            .map(|(_, node_modules)| node_modules)
+            .collect()
+    }
+
+    /// Get the node_modules folders that exist in the search path from `basedir`, nearest
+    /// first. This uses the cache if there is one, so the folders that are found can be
+    /// scanned ahead of time without checking them twice.
+    pub fn existing_node_modules_paths(&self, basedir: &Path) -> Vec<PathBuf> {
+        let mut paths = self.node_modules_paths(basedir);
+        paths.retain(|node_modules| self.is_dir(node_modules));
+        paths
+    }
+
+    /// Find the most specific scoped resolver that applies to the basedir.
+    fn scoped_resolver(&self) -> Option<Resolver> {
+        let basedir = self.basedir.as_ref()?;
+        self.scopes
+            .iter()
+            .filter(|(dir, _)| basedir.starts_with(dir))
+            .max_by_key(|(dir, _)| dir.components().count())
+            .map(|(_, resolver)| resolver.with_basedir(basedir.clone()))
+    }
+
+    fn resolve_inner(&self, target: &str, ctx: &mut Context) -> Result<PathBuf, Error> {
+        let target = &*self.expand(target)?;
+        let target = match target.strip_prefix('~') {
+            Some(bare) if self.tilde_prefix && !bare.starts_with('/') => bare,
+            _ => target,
+        };
+
+        // 1. If X is a core module
+        if self.runtime.is_builtin(target) {
+            // 1.a. Return the core module
+            return Ok(PathBuf::from(target));
+        }
+        validate_specifier(target)?;
+
+        // 2. If X begins with '/'
+        let basedir = if target.starts_with('/') {
+            // 2.a. Set Y to be the filesystem root. On Windows, this is the root of the drive
+            // that the basedir is on.
+            Cow::Owned(self.get_root())
+        } else {
+            self.enter_directory(self.get_basedir()?, ctx)
+        };
+
+        // 3. If X begins with './' or '/' or '../', or is '.' or '..'
+        let result = if is_path_specifier(target) {
+            let path = basedir.join(target);
+            if self.strict_package_boundaries && !target.starts_with('/') {
+                if let Some(package_dir) = pure::node_modules_package(&normalize(&path)) {
+                    if pure::node_modules_package(&basedir).as_ref() != Some(&package_dir) {
+                        return Err(Error::PackageBoundary {
+                            specifier: target.to_string(),
+                            package_dir,
+                        });
+                    }
+                }
+            }
+            if is_directory_specifier(target) {
+                self.resolve_as_directory(&path, ctx)
+            } else {
+                self.resolve_path(&path, self.probe_order, ctx)
+            }
+        // 4. If X begins with '#'
+        } else if target.starts_with('#') {
+            // 4.a. LOAD_PACKAGE_IMPORTS(X, dirname(Y))
+            return self.resolve_package_imports(target, &basedir, ctx);
+        } else {
+            self.check_denied(target)?;
+            let result = self.resolve_node_modules(target, ctx);
+            match result {
+                Ok(ref path) if !self.types_packages || is_typescript_file(path) => result,
+                _ => self.resolve_types_package(target, ctx).map_or(result, Ok),
+            }
+        };
+
+        result
+            .and_then(|p| self.normalize(&p))
+            .map_err(|err| match not_found(err, target, &basedir, ctx) {
+                err @ Error::NotFound { .. } => self.find_broken_symlink(ctx).unwrap_or(err),
+                err => err,
+            })
+    }
+
+    /// Reject a bare specifier if its package matches a pattern on the deny list.
+    fn check_denied(&self, target: &str) -> Result<(), Error> {
+        let package_name = parse_specifier(target).package_name.unwrap_or(target);
+        match self
+            .denied_packages
+            .iter()
+            .find(|pattern| pure::matches_glob(pattern, package_name))
+        {
+            Some(pattern) => Err(Error::DeniedPackage {
+                specifier: target.to_string(),
+                pattern: pattern.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Find a symlink to a missing target among the paths that were checked. This is only done
+    /// when resolution failed, so successful resolutions do not pay for it.
+    fn find_broken_symlink(&self, ctx: &Context) -> Option<Error> {
+        let mut seen = HashSet::new();
+        ctx.checked
+            .iter()
+            .map(|(path, _)| path)
+            .filter(|path| seen.insert(*path))
+            .find_map(|link| {
+                let target = self.fs.read_link(link).ok()?;
+                self.observe(link, Access::ReadLink);
+                let dir = link.parent().unwrap_or_else(|| Path::new(ROOT));
+                let target = normalize(&dir.join(target));
+                if self.fs.entry_kind(&target) == EntryKind::Missing {
+                    Some(Error::BrokenSymlink {
+                        link: normalize(link),
+                        target,
+                    })
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Resolve a specifier to a directory instead of a file, like webpack's `resolveToContext`.
+    ///
+    /// Relative and absolute specifiers must point to an existing directory. Bare specifiers
+    /// are looked up in node_modules folders, so `"pkg"` returns the root folder of the package
+    /// and `"pkg/lib"` returns a folder inside it. Core modules do not have a directory and
+    /// return an error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// assert_eq!(Ok(PathBuf::from("./fixtures/node-modules/package-json/node_modules/dep")),
+    ///     Resolver::default()
+    ///         .with_basedir(PathBuf::from("./fixtures/node-modules/package-json"))
+    ///         .resolve_directory("dep")
+    /// );
+    /// ```
+    pub fn resolve_directory(&self, target: &str) -> Result<PathBuf, Error> {
+        self.find_directory(target).map(|path| self.output_path(path))
+    }
+
+    /// Resolve `target` to a directory like [`Resolver::resolve_directory`], but return the
+    /// path without making it relative to the output root.
+    pub(crate) fn find_directory(&self, target: &str) -> Result<PathBuf, Error> {
+        if let Some(scoped) = self.scoped_resolver() {
+            return scoped.find_directory(target);
+        }
+        let target = &*self.expand(target)?;
+        let mut ctx = Context::default();
+        let as_directory = |path: &Path, ctx: &mut Context| {
+            ctx.try_path(path);
+            if self.probe_dir(path, ctx) {
+                Ok(path.to_path_buf())
+            } else {
+                Err(Error::IOError(IOError::new(IOErrorKind::NotFound, "Not Found")).into())
+            }
+        };
+
+        let basedir = if target.starts_with('/') {
+            Cow::Owned(self.get_root())
+        } else {
+            self.enter_directory(self.get_basedir()?, &mut ctx)
+        };
+
+        let result = if self.runtime.is_builtin(target) {
+            Err(Error::IOError(IOError::new(IOErrorKind::NotFound, "Not Found")).into())
+        } else if let Err(err) = validate_specifier(target) {
+            Err(err.into())
+        } else if is_path_specifier(target) {
+            as_directory(&basedir.join(target), &mut ctx)
+        } else if let Err(err) = self.check_denied(target) {
+            Err(err.into())
+        } else {
+            self.walk_node_modules(&mut ctx, |node_modules, ctx| {
+                Ok(as_directory(&node_modules.join(target), ctx).ok())
+            })
+        };
+
+        ctx.check_aborted(target)?;
+        result
+            .and_then(|p| self.normalize(&p))
+            .map_err(|err| not_found(err, target, &basedir, &mut ctx))
+    }
+
+    /// Resolve `subpath` inside the package in `package_dir`, without looking for the package
+    /// in node_modules folders. The basedir is not used.
+    ///
+    /// This is meant for tools that already know where a package is, like registries,
+    /// documentation generators, and CDN servers that map `name@version` to a folder. The
+    /// subpath is resolved like the part after the package name in a bare specifier: it uses
+    /// the "exports" field of the package if it has one, and the "main" field for an empty
+    /// subpath or `"."`. A leading `./` is allowed. Subpaths that leave the package folder fail
+    /// with [`Error::InvalidSpecifier`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let package_dir = PathBuf::from("/cdn/packages/preact@10.19.3");
+    /// let resolver = Resolver::default().conditions(&["browser", "import"]);
+    /// resolver.resolve_in_package(&package_dir, "")?;
+    /// resolver.resolve_in_package(&package_dir, "hooks")?;
+    /// ```
+    pub fn resolve_in_package<P: AsRef<Path>>(
+        &self,
+        package_dir: P,
+        subpath: &str,
+    ) -> Result<PathBuf, Error> {
+        let package_dir = package_dir.as_ref();
+        let subpath = match subpath.strip_prefix("./").unwrap_or(subpath) {
+            "" | "." => None,
+            subpath => Some(subpath),
+        };
+        let key = match subpath {
+            Some(subpath) => format!("./{}", subpath),
+            None => String::from("."),
+        };
+        if let Some(subpath) = subpath {
+            if !normalize(&package_dir.join(subpath)).starts_with(normalize(package_dir)) {
+                return Err(Error::InvalidSpecifier {
+                    specifier: key,
+                    reason: "subpath is outside of the package",
+                });
+            }
+        }
+
+        let mut ctx = Context::default();
+        let pkg_dir = self.enter_directory(package_dir, &mut ctx).into_owned();
+        let result = match self.resolve_package_exports(&pkg_dir, &key, &mut ctx) {
+            Some(result) => result,
+            None => match subpath {
+                Some(subpath) if !is_directory_specifier(subpath) => {
+                    self.resolve_path(&pkg_dir.join(subpath), self.probe_order, &mut ctx)
+                }
+                Some(subpath) => self.resolve_as_directory(&pkg_dir.join(subpath), &mut ctx),
+                None => self.resolve_as_directory(&pkg_dir, &mut ctx),
+            },
+        };
+
+        ctx.check_aborted(&key)?;
+        result
+            .and_then(|path| self.normalize(&path))
+            .map(|path| self.output_path(path))
+            .map_err(|err| not_found(err, &key, package_dir, &mut ctx))
+    }
+
+    /// Expand `~/` and environment variables in a specifier, if enabled, and apply the
+    /// rewrite rules.
+    fn expand<'a>(&self, target: &'a str) -> Result<Cow<'a, str>, Error> {
+        let target = if self.expand_env {
+            expand_specifier(target, &*self.environment)?
+        } else {
+            Cow::Borrowed(target)
+        };
+        #[cfg(feature = "regex")]
+        let target = self.rewrite_specifier(target);
+        Ok(target)
+    }
+
+    /// Normalize a path to a module. If symlinks should be preserved, this only removes
+    /// unnecessary `./`s and `../`s from the path. Else it does `realpath()`.
+    fn normalize(&self, path: &Path) -> Result<PathBuf, InternalError> {
+        if self.symlinks == SymlinkStrategy::Preserve {
+            Ok(normalize(path))
+        } else {
+            self.canonicalize(path).map_err(|err| io_error(path, err).into())
+        }
+    }
+
+    /// Expand symlinks in a directory that is about to be entered, if the symlink strategy
+    /// asks for it.
+    fn enter_directory<'a>(&self, dir: &'a Path, ctx: &mut Context) -> Cow<'a, Path> {
+        if self.symlinks == SymlinkStrategy::RealpathDirectories {
+            self.expand_directory(dir, ctx)
+        } else {
+            Cow::Borrowed(dir)
+        }
+    }
+
+    /// Expand symlinks in a directory. If that fails, the directory is used as it is.
+    fn expand_directory<'a>(&self, dir: &'a Path, ctx: &mut Context) -> Cow<'a, Path> {
+        match self.canonicalize(dir) {
+            Ok(real) => {
+                if dir.is_absolute() && real != normalize(dir) {
+                    // An exceeded budget is reported once resolution is done.
+                    let _ = ctx.budget.follow_symlink(&self.budget);
+                }
+                Cow::Owned(real)
+            }
+            Err(_) => Cow::Borrowed(dir),
+        }
+    }
+
+    /// Get the directory that node_modules lookups from `basedir` start in.
+    fn ascent_start<'a>(&self, basedir: &'a Path, ctx: &mut Context) -> Cow<'a, Path> {
+        match self.ascent_origin {
+            AscentOrigin::Strategy => self.enter_directory(basedir, ctx),
+            AscentOrigin::Link => Cow::Borrowed(basedir),
+            AscentOrigin::Real => self.expand_directory(basedir, ctx),
+        }
+    }
+
+    /// Resolve a path as a file and as a directory, in the given order. If specifiers must be
+    /// fully specified, only an exact file path is accepted.
+    fn resolve_path(
+        &self,
+        path: &Path,
+        probe_order: ProbeOrder,
+        ctx: &mut Context,
+    ) -> Result<PathBuf, InternalError> {
+        if self.fully_specified {
+            if let Some(path) = self.try_typescript_file(path, ctx) {
+                return Ok(path);
+            }
+            return if self.try_file(path, ctx) {
+                Ok(path.to_path_buf())
+            } else {
+                Err(Error::IOError(IOError::new(IOErrorKind::NotFound, "Not Found")).into())
+            };
+        }
+
+        match probe_order {
+            ProbeOrder::FileFirst => self
+                .resolve_as_file(path, ctx)
+                .or_else(|_| self.resolve_as_directory(path, ctx)),
+            // Problems with the directory, like an invalid package.json file, are more useful
+            // than a missing file.
+            ProbeOrder::DirectoryFirst => self
+                .resolve_as_directory(path, ctx)
+                .or_else(|err| self.resolve_as_file(path, ctx).map_err(|_| err)),
+            ProbeOrder::FileOnly => self.resolve_as_file(path, ctx),
+            ProbeOrder::DirectoryOnly => self.resolve_as_directory(path, ctx),
+        }
+    }
+
+    /// Resolve a path as a file. If `path` refers to a file, it is returned;
+    /// otherwise the `path` + each extension is tried.
+    fn resolve_as_file(&self, path: &Path, ctx: &mut Context) -> Result<PathBuf, InternalError> {
+        if let Some(path) = self.try_typescript_file(path, ctx) {
+            return Ok(path);
+        }
+
+        // 1. If X is a file, load X as JavaScript text.
+        if self.try_file(path, ctx) {
+            return Ok(path.to_path_buf());
+        }
+
+        // 1. If X.js is a file, load X.js as JavaScript text.
+        // 2. If X.json is a file, parse X.json to a JavaScript object.
+        // 3. If X.node is a file, load X.node as binary addon.
+        let dir = path.parent().unwrap_or_else(|| Path::new(ROOT));
+        let extensions = self.extensions_for(dir, ctx);
+        pure::find_file(path, &extensions, self.partials, |candidate| {
+            self.try_file(candidate, ctx)
+        })
+        .ok_or_else(|| Error::IOError(IOError::new(IOErrorKind::NotFound, "Not Found")).into())
+    }
+
+    /// Resolve a path as a directory, using the "main" key from a package.json file if it
+    /// exists, or resolving to the index.EXT file if it exists.
+    fn resolve_as_directory(
+        &self,
+        path: &Path,
+        ctx: &mut Context,
+    ) -> Result<PathBuf, InternalError> {
+        if !self.probe_dir(path, ctx) {
+            return Err(Error::IOError(IOError::new(IOErrorKind::NotFound, "Not Found")).into());
+        }
+        let path = &*self.enter_directory(path, ctx);
+
+        // 1. If X/package.json is a file, use it.
+        let pkg_path = path.join("package.json");
+        let mut main = None;
+        if self.uses_package_main() && self.probe_file(&pkg_path, ctx) {
+            match self.resolve_package_main(&pkg_path, ctx) {
+                Ok(resolved) => return Ok(resolved),
+                Err(err) if err.is_fatal() => return Err(err),
+                Err(err) => {
+                    if let InternalError::Public(Error::InvalidPackageJson { path, error }) = &err {
+                        ctx.warn(Warning::InvalidPackageJson {
+                            path: path.clone(),
+                            message: error.to_string(),
+                        });
+                    }
+                    main = Some(err);
+                }
+            }
+        }
+
+        // 2. LOAD_INDEX(X)
+        self.resolve_index(path, ctx).map_err(|err| match main {
+            // If there is no index either, the broken package.json file is the real problem.
+            Some(err @ InternalError::Public(Error::InvalidPackageJson { .. })) => err,
+            _ => err,
+        })
+    }
+
+    /// Check if package.json files can point to the entry point of a directory at all.
+    fn uses_package_main(&self) -> bool {
+        self.use_package_json
+            && self.use_package_main
+            && (!self.main_fields.is_empty() || self.directories_lib)
+    }
+
+    /// Resolve using the package.json "main" key.
+    fn resolve_package_main(
+        &self,
+        pkg_path: &Path,
+        ctx: &mut Context,
+    ) -> Result<PathBuf, InternalError> {
+        let pkg_dir = pkg_path.parent().unwrap_or_else(|| Path::new(ROOT));
+        let pkg = self.load_package_json(pkg_path, ctx)?;
+        if !pkg.is_object() {
+            return Err(RecoverableError::NonObjectPackageJson.into());
+        }
+
+        let main_field = self
+            .main_fields
+            .iter()
+            .find(|name| pkg[name].is_string())
+            .and_then(|name| Some((name, pkg[name].as_str()?)));
+        let main = match main_field {
+            Some((field, target)) => {
+                // A main folder can have a package.json file whose main points back here.
+                let manifest = normalize(pkg_path);
+                if ctx.manifests.contains(&manifest)
+                    || ctx.manifests.len() >= MAX_MAIN_INDIRECTIONS
+                {
+                    let mut chain = ctx.manifests.clone();
+                    chain.push(manifest);
+                    return Err(Error::ManifestCycle { chain }.into());
+                }
+                ctx.manifests.push(manifest);
+                let path = pkg_dir.join(target);
+                let main = self.resolve_as_file(&path, ctx).or_else(|_| {
+                    // A main like "." or "./" points to the package's own index file.
+                    if normalize(&path) == normalize(pkg_dir) {
+                        self.resolve_index(&path, ctx)
+                    } else {
+                        self.resolve_main_directory(&path, ctx)
+                    }
+                });
+                ctx.manifests.pop();
+                if main.as_ref().is_err_and(InternalError::is_fatal) {
+                    return main;
+                }
+                if main.is_ok() {
+                    ctx.main_fields.push((pkg_path.to_path_buf(), field.clone()));
+                } else {
+                    ctx.warn(Warning::MainNotFound {
+                        path: pkg_path.to_path_buf(),
+                        main: target.to_string(),
+                    });
+                }
+                main
+            }
+            None => {
+                Err(RecoverableError::MissingMain.into())
+            }
+        };
+
+        // Old packages point to their entry folder with "directories": { "lib": "..." }.
+        if main.is_err() && self.directories_lib {
+            if let Some(lib) = pkg["directories"]["lib"].as_str() {
+                let lib_index = self.resolve_index(&pkg_dir.join(lib), ctx);
+                if lib_index.is_ok() {
+                    return lib_index;
+                }
+            }
+        }
+
+        main
+    }
+
+    /// Resolve a directory that a main field points to. If the directory has a package.json
+    /// file with an "exports" field, its `"."` entry point is used. Otherwise, the directory is
+    /// resolved like any other.
+    fn resolve_main_directory(
+        &self,
+        path: &Path,
+        ctx: &mut Context,
+    ) -> Result<PathBuf, InternalError> {
+        if self.probe_dir(path, ctx) {
+            let dir = self.enter_directory(path, ctx);
+            if let Some(result) = self.resolve_package_exports(&dir, ".", ctx) {
+                return result;
+            }
+        }
+        self.resolve_as_directory(path, ctx)
+    }
+
+    /// Check if a file exists.
+    fn is_file(&self, path: &Path) -> bool {
+        self.entry_kind(path) == EntryKind::File
+    }
+
+    /// Check if a directory exists.
+    fn is_dir(&self, path: &Path) -> bool {
+        self.entry_kind(path) == EntryKind::Directory
+    }
+
+    /// Check if a candidate for the result of a resolution is a file.
+    fn try_file(&self, path: &Path, ctx: &mut Context) -> bool {
+        if !ctx.budget.has_time(&self.budget) {
+            return false;
+        }
+        ctx.try_path(path);
+        self.probe_entry_kind(path, ctx) == EntryKind::File
+    }
+
+    /// Check if a file exists as part of a resolution, which then depends on it.
+    fn probe_file(&self, path: &Path, ctx: &mut Context) -> bool {
+        if !ctx.budget.has_time(&self.budget) {
+            return false;
+        }
+        ctx.watch(path);
+        self.probe_entry_kind(path, ctx) == EntryKind::File
+    }
+
+    /// Check if a directory exists as part of a resolution, which then depends on it.
+    fn probe_dir(&self, path: &Path, ctx: &mut Context) -> bool {
+        if !ctx.budget.has_time(&self.budget) {
+            return false;
+        }
+        ctx.watch(path);
+        self.probe_entry_kind(path, ctx) == EntryKind::Directory
+    }
+
+    /// Find out what exists at `path` as part of a resolution. If that fails, the path counts
+    /// as missing. Permission errors are reported as warnings, or fail the resolution once it
+    /// is done if [permissions are strict](Resolver::strict_permissions). Other errors always
+    /// fail it.
+    fn probe_entry_kind(&self, path: &Path, ctx: &mut Context) -> EntryKind {
+        if ctx.io_error.is_some() {
+            return EntryKind::Missing;
+        }
+        match self.checked_entry_kind(path) {
+            Ok(kind) => kind,
+            Err(error) => {
+                if error.kind() == IOErrorKind::PermissionDenied {
+                    self.permission_denied(path, &error, ctx);
+                } else {
+                    ctx.io_error = Some(io_error(path, error));
+                }
+                EntryKind::Missing
+            }
+        }
+    }
+
+    /// Report that `path` could not be checked or read because of a missing permission, as a
+    /// warning or, if [permissions are strict](Resolver::strict_permissions), as the error of
+    /// the resolution.
+    fn permission_denied(&self, path: &Path, error: &IOError, ctx: &mut Context) {
+        if self.strict_permissions {
+            ctx.io_error.get_or_insert_with(|| Error::PermissionDenied {
+                path: path.to_path_buf(),
+                error: IOError::new(error.kind(), error.to_string()),
+            });
+        } else {
+            ctx.warn(Warning::PermissionDenied {
+                path: path.to_path_buf(),
+                message: error.to_string(),
+            });
+        }
+    }
+
+    /// Find out what exists at `path`, using the cache if there is one. Errors count as
+    /// missing entries.
+    fn entry_kind(&self, path: &Path) -> EntryKind {
+        self.checked_entry_kind(path).unwrap_or(EntryKind::Missing)
+    }
+
+    /// Find out what exists at `path`, using the cache if there is one.
+    fn checked_entry_kind(&self, path: &Path) -> Result<EntryKind, IOError> {
+        let kind = self.uncached_entry_kind(path);
+        let observed = *kind.as_ref().unwrap_or(&EntryKind::Missing);
+        self.observe(path, Access::Entry(observed));
+        kind
+    }
+
+    fn uncached_entry_kind(&self, path: &Path) -> Result<EntryKind, IOError> {
+        if let Some(cache) = &self.cache {
+            let cached = cache.get_entry(path);
+            self.counters.count_entry_lookup(cached.is_some());
+            if let Some(kind) = cached {
+                return Ok(kind);
+            }
+        }
+
+        self.counters.count_stat();
+        // Errors are not cached, so they are reported by every resolution that runs into them.
+        let kind = self.retry_io(|| self.fs.try_entry_kind(path))?;
+        if let Some(cache) = &self.cache {
+            cache.set_entry(path, kind);
+        }
+        Ok(kind)
+    }
+
+    /// Expand all symlinks in a path.
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, IOError> {
+        self.observe(path, Access::Canonicalize);
+        let real = self.retry_io(|| self.fs.canonicalize(path))?;
+        if self.verbatim_paths {
+            Ok(real)
+        } else {
+            Ok(verbatim::strip_verbatim_prefix(real))
+        }
+    }
+
+    /// Run a filesystem operation, and retry it after transient errors, as often as
+    /// configured.
+    fn retry_io<T, F>(&self, mut operation: F) -> Result<T, IOError>
+    where
+        F: FnMut() -> Result<T, IOError>,
+    {
+        let mut attempt = 0;
+        loop {
+            match operation() {
+                Err(err) if is_transient(err.kind()) && attempt < self.io_retries => {
+                    thread::sleep(Duration::from_millis(1 << attempt.min(8)));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Report a filesystem access to the observer, if there is one.
+    fn observe(&self, path: &Path, access: Access) {
+        if let Some(observer) = &self.observer {
+            observer.observe(path, access);
+        }
+    }
+
+    /// Read and parse a package.json file as part of a resolution, and charge it to the
+    /// resolution's budget.
+    fn load_package_json(&self, pkg_path: &Path, ctx: &mut Context) -> Result<Arc<Value>, Error> {
+        ctx.budget.read_package_json(&self.budget)?;
+        if !ctx.budget.has_time(&self.budget) {
+            // The resolution fails with a timeout once it is done.
+            return Err(Error::IOError(IOError::new(IOErrorKind::TimedOut, "Timed out")));
+        }
+        ctx.watch(pkg_path);
+        if self.detailed_stats {
+            if let Some(pkg_dir) = pkg_path.parent() {
+                self.counters.count_package_use(pkg_dir);
+            }
+        }
+        let pkg = self.read_package_json(pkg_path);
+        if let Err(Error::PermissionDenied { path, error }) = &pkg {
+            self.permission_denied(path, error, ctx);
+        }
+        pkg
+    }
+
+    /// Read and parse a package.json file, using the cache if there is one.
+    fn read_package_json(&self, pkg_path: &Path) -> Result<Arc<Value>, Error> {
+        self.observe(pkg_path, Access::Read);
+        if let Some(cache) = &self.cache {
+            let cached = cache.get_package_json(pkg_path);
+            self.counters.count_package_json_lookup(cached.is_some());
+            if let Some(pkg) = cached {
+                return Ok(pkg);
+            }
+        }
+
+        self.counters.count_package_json_read();
+        let contents = self
+            .retry_io(|| self.fs.read_to_string(pkg_path))
+            .map_err(|err| io_error(pkg_path, err))?;
+        let pkg = parse_package_json(&contents, self.tolerant_package_json).map_err(|error| {
+            Error::InvalidPackageJson {
+                path: pkg_path.to_path_buf(),
+                error,
+            }
+        })?;
+        let pkg = Arc::new(pkg);
+        if let Some(cache) = &self.cache {
+            cache.set_package_json(pkg_path, Arc::clone(&pkg));
+        }
+        Ok(pkg)
+    }
+
+    /// Find the package.json file for the package that contains `dir`. The search stops at
+    /// `node_modules` folders, so files in a dependency never use the manifest of the parent
+    /// project.
+    fn find_package_scope(&self, dir: &Path, ctx: &mut Context) -> Option<PathBuf> {
+        if !self.use_package_json {
+            return None;
+        }
+        for scope in dir.ancestors() {
+            if scope.file_name().is_some_and(|name| name == "node_modules") {
+                return None;
+            }
+            let pkg_path = scope.join("package.json");
+            if self.probe_file(&pkg_path, ctx) {
+                return Some(pkg_path);
+            }
+        }
+        None
+    }
+
+    /// Get the extensions to try, in order, for files inside `dir`.
+    fn extensions_for(&self, dir: &Path, ctx: &mut Context) -> Cow<'_, [String]> {
+        if !self.package_type_extensions {
+            return Cow::Borrowed(&self.extensions[..]);
+        }
+
+        let is_module = self
+            .find_package_scope(dir, ctx)
+            .and_then(|pkg_path| self.load_package_json(&pkg_path, ctx).ok())
+            .is_some_and(|pkg| pkg["type"] == "module");
+        let preferred = if is_module { ".mjs" } else { ".cjs" };
+
+        let mut extensions = vec![String::from(preferred)];
+        extensions.extend(self.extensions.iter().filter(|ext| *ext != preferred).cloned());
+        Cow::Owned(extensions)
+    }
+
+    /// Resolve a directory to its index.EXT.
+    fn resolve_index(&self, path: &Path, ctx: &mut Context) -> Result<PathBuf, InternalError> {
+        // 1. If X/index.js is a file, load X/index.js as JavaScript text.
+        // 2. If X/index.json is a file, parse X/index.json to a JavaScript object.
+        // 3. If X/index.node is a file, load X/index.node as binary addon.
+        let extensions = self.extensions_for(path, ctx);
+        pure::find_index(path, &extensions, self.partials, |candidate| {
+            self.try_file(candidate, ctx)
+        })
+        .ok_or_else(|| Error::IOError(IOError::new(IOErrorKind::NotFound, "Not Found")).into())
+    }
+
+    /// Resolve by walking up node_modules folders.
+    fn resolve_node_modules(
+        &self,
+        target: &str,
+        ctx: &mut Context,
+    ) -> Result<PathBuf, InternalError> {
+        let specifier = parse_specifier(target);
+        let package_name = specifier.package_name.unwrap_or(target);
+
+        if let Some((_, pkg_dir)) = self.overrides.iter().find(|(name, _)| name == package_name) {
+            let path = match specifier.subpath {
+                Some(subpath) => pkg_dir.join(subpath),
+                None => pkg_dir.clone(),
+            };
+            return match self.find_in_package(pkg_dir, &path, target, ctx)? {
+                Some(path) => Ok(path),
+                None => Err(Error::IOError(IOError::new(
+                    IOErrorKind::NotFound,
+                    "Not Found",
+                ))
+                .into()),
+            };
+        }
+
+        let result = self.walk_node_modules(ctx, |node_modules, ctx| {
+            let pkg_dir = node_modules.join(package_name);
+            self.find_in_package(&pkg_dir, &node_modules.join(target), target, ctx)
+        });
+        // Errors from a package that was found, like a subpath that it does not export, are
+        // reported as they are.
+        let missing = matches!(
+            &result,
+            Err(InternalError::Public(Error::IOError(err))) if err.kind() == IOErrorKind::NotFound
+        );
+        if missing && (self.link_dependencies || self.file_dependencies) {
+            if let Some(pkg_dir) = self.find_linked_package(package_name, ctx) {
+                let path = match specifier.subpath {
+                    Some(subpath) => pkg_dir.join(subpath),
+                    None => pkg_dir.clone(),
+                };
+                if let Some(path) = self.find_in_package(&pkg_dir, &path, target, ctx)? {
+                    return Ok(path);
+                }
+            }
+        }
+        result
+    }
+
+    /// Resolve `target` in the package folder `pkg_dir`, using its "exports" field if it has
+    /// one, or else `path`, which is the target inside the package folder.
+    fn find_in_package(
+        &self,
+        pkg_dir: &Path,
+        path: &Path,
+        target: &str,
+        ctx: &mut Context,
+    ) -> Result<Option<PathBuf>, InternalError> {
+        let specifier = parse_specifier(target);
+        let package_name = specifier.package_name.unwrap_or(target);
+        let pkg_dir = self.enter_directory(pkg_dir, ctx).into_owned();
+        if !self.exports_bypass.iter().any(|name| name == package_name) {
+            let subpath = match specifier.subpath {
+                Some(subpath) => format!("./{}", subpath),
+                None => String::from("."),
+            };
+            if let Some(result) = self.resolve_package_exports(&pkg_dir, &subpath, ctx) {
+                return result.map(Some);
+            }
+        }
+
+        // Folders inside the package may have their own package.json file with a "main" field.
+        let flat;
+        let resolver = if specifier.subpath.is_some() && !self.nested_package_json {
+            flat = self.clone().use_package_main(false);
+            &flat
+        } else {
+            self
+        };
+        let result = if is_directory_specifier(target)
+            || (self.fully_specified && specifier.subpath.is_none())
+        {
+            resolver.resolve_as_directory(path, ctx)
+        } else {
+            resolver.resolve_path(path, self.package_probe_order(&specifier), ctx)
+        };
+        match result {
+            Ok(path) => Ok(Some(path)),
+            Err(err) if err.is_fatal() => Err(err),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Get the probe order for a path in a package. The probe order applies to subpaths, but
+    /// package folders are always found, like Node.js does.
+    fn package_probe_order(&self, specifier: &Specifier) -> ProbeOrder {
+        match specifier.subpath {
+            Some(_) => self.probe_order,
+            None => ProbeOrder::FileFirst,
+        }
+    }
+
+    /// Find the folder of a package that is declared as a `link:`, `portal:` or `file:`
+    /// dependency in the package.json files above the basedir, depending on which protocols
+    /// are enabled.
+    fn find_linked_package(&self, package_name: &str, ctx: &mut Context) -> Option<PathBuf> {
+        let basedir = self.get_basedir().ok()?.to_path_buf();
+        for dir in basedir.ancestors() {
+            if dir.file_name().is_some_and(|name| name == "node_modules") {
+                return None;
+            }
+            let pkg_path = dir.join("package.json");
+            if !self.probe_file(&pkg_path, ctx) {
+                continue;
+            }
+            let pkg = match self.load_package_json(&pkg_path, ctx) {
+                Ok(pkg) => pkg,
+                Err(_) => continue,
+            };
+            let declared = DEPENDENCY_FIELDS
+                .iter()
+                .find_map(|field| pkg[field][package_name].as_str());
+            let linked = declared.and_then(|version| {
+                let link = || {
+                    version
+                        .strip_prefix("link:")
+                        .or_else(|| version.strip_prefix("portal:"))
+                };
+                let file = || version.strip_prefix("file:");
+                match (self.link_dependencies, self.file_dependencies) {
+                    (true, true) => link().or_else(file),
+                    (true, false) => link(),
+                    (false, true) => file(),
+                    (false, false) => None,
+                }
+            });
+            if let Some(linked) = linked {
+                let pkg_dir = dir.join(linked);
+                return if self.probe_dir(&pkg_dir, ctx) {
+                    Some(normalize(&pkg_dir))
+                } else {
+                    None
+                };
+            }
+        }
+        None
+    }
+
+    /// Get the names of the module directories to check in `dir`.
+    fn module_directories_for(&self, dir: &Path) -> &[String] {
+        self.scoped_module_directories
+            .iter()
+            .filter(|(scope, _)| dir.starts_with(scope))
+            .max_by_key(|(scope, _)| scope.components().count())
+            .map_or(&self.module_directories, |(_, names)| names)
+    }
+
+    /// Walk up node_modules folders starting at the basedir, and call `resolve` for each of
+    /// them. `resolve` returns `Ok(None)` to continue with the next folder; a result or an
+    /// error ends the walk.
+    fn walk_node_modules<F>(
+        &self,
+        ctx: &mut Context,
+        mut resolve: F,
+    ) -> Result<PathBuf, InternalError>
+    where
+        F: FnMut(&Path, &mut Context) -> Result<Option<PathBuf>, InternalError>,
+    {
+        let basedir = self.ascent_start(self.get_basedir()?, ctx);
+        for (ascent, node_modules) in self.node_modules_dirs(&basedir) {
+            ctx.budget.ascend(&self.budget, ascent)?;
+            if self.probe_dir(&node_modules, ctx) {
+                if let Some(path) = resolve(&node_modules, ctx)? {
+                    return Ok(path);
+                }
+            }
+        }
+
+        Err(Error::IOError(IOError::new(
+            IOErrorKind::NotFound,
+            "Not Found",
+        )).into())
+    }
+}
+
+/// Convert an internal error to a public one. If nothing was found for `target`, the error
+/// lists the candidates that were checked.
+fn not_found(err: InternalError, target: &str, basedir: &Path, ctx: &mut Context) -> Error {
+    match err.into_public() {
+        Error::IOError(ref err) if err.kind() == IOErrorKind::NotFound => Error::NotFound {
+            specifier: target.to_string(),
+            basedir: basedir.to_path_buf(),
+            tried: ctx.tried(),
+        },
+        err => err,
+    }
+}
+
+/// Check if an I/O error may go away when the operation is tried again.
+fn is_transient(kind: IOErrorKind) -> bool {
+    matches!(
+        kind,
+        IOErrorKind::Interrupted | IOErrorKind::WouldBlock | IOErrorKind::TimedOut
+    )
+}
+
+/// Classify an I/O error for `path`, so permission problems and transient failures get their
+/// own error variants.
+pub(crate) fn io_error(path: &Path, error: IOError) -> Error {
+    if error.kind() == IOErrorKind::PermissionDenied {
+        Error::PermissionDenied {
+            path: path.to_path_buf(),
+            error,
+        }
+    } else if is_transient(error.kind()) {
+        Error::TransientIOError {
+            path: path.to_path_buf(),
+            error,
+        }
+    } else {
+        Error::IOError(error)
+    }
+}
+
+/// Remove repeated paths, keeping the first occurrence of each so the order stays the same.
+pub(crate) fn first_occurrences<I: IntoIterator<Item = PathBuf>>(paths: I) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    paths.into_iter().filter(|path| seen.insert(path.clone())).collect()
+}
+
+/// Get the prefix and root directory of a path, like `C:\` on Windows or `/` on Unix.
+fn root_of(path: &Path) -> PathBuf {
+    path.components()
+        .take_while(|part| matches!(part, PathComponent::Prefix(_) | PathComponent::RootDir))
+        .collect()
+}
+
+/// Remove excess components like `/./` and `/../` from a path, like Node's `path.normalize`.
+///
+/// This only looks at the path itself, and does not touch the filesystem. `..` segments
+/// remove the segment before them. At the start of a relative path there is nothing to
+/// remove, so leading `..` segments are kept. An empty path becomes `.`, and a trailing
+/// separator is kept. On Windows, both `/` and `\\` are separators, and the result uses `\\`.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::normalize;
+///
+/// assert_eq!(normalize(Path::new("/a/./b/../c/")), PathBuf::from("/a/c/"));
+/// assert_eq!(normalize(Path::new("../a/../../b")), PathBuf::from("../../b"));
+/// assert_eq!(normalize(Path::new("a/..")), PathBuf::from("."));
+/// ```
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    let mut parts: Vec<&OsStr> = vec![];
+    for part in path.components() {
+        match part {
+            PathComponent::Prefix(_) | PathComponent::RootDir => {
+                normalized.push(part.as_os_str());
+            }
+            PathComponent::CurDir => {
+                // Nothing
+            }
+            PathComponent::ParentDir => {
+                if parts.last().is_some_and(|last| *last != "..") {
+                    parts.pop();
+                } else if !normalized.has_root() {
+                    parts.push(part.as_os_str());
+                }
+            }
+            PathComponent::Normal(name) => {
+                parts.push(name);
+            }
+        }
+    }
+
+    let is_root = normalized.has_root() && parts.is_empty();
+    normalized.extend(parts);
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+    let has_trailing_separator = path
+        .to_string_lossy()
+        .ends_with(is_separator);
+    if has_trailing_separator && !is_root {
+        // Pushing an empty segment adds a separator.
+        normalized.push("");
+    }
+    normalized
+}
+
+/// Get the path to `path` from the directory `root`, with `/` separators. Both paths must be
+/// normalized. Returns `None` if there is no such path, like for relative paths or paths on
+/// different drives.
+fn relative_path(path: &Path, root: &Path) -> Option<PathBuf> {
+    let path: Vec<PathComponent> = path.components().collect();
+    let root: Vec<PathComponent> = root.components().collect();
+    let common = path
+        .iter()
+        .zip(&root)
+        .take_while(|(part, root_part)| part == root_part)
+        .count();
+    if !root[..common].contains(&PathComponent::RootDir) {
+        return None;
+    }
+    let parts: Vec<Cow<str>> = root[common..]
+        .iter()
+        .map(|_| Cow::Borrowed(".."))
+        .chain(path[common..].iter().map(|part| part.as_os_str().to_string_lossy()))
+        .collect();
+    if parts.is_empty() {
+        Some(PathBuf::from("."))
+    } else {
+        Some(PathBuf::from(parts.join("/")))
+    }
+}
+
+/// Replace a leading `~/` by the home directory and `${VAR}` by the value of environment
+/// variables.
+fn expand_specifier<'a>(
+    target: &'a str,
+    environment: &dyn Environment,
+) -> Result<Cow<'a, str>, Error> {
+    if !(target.starts_with("~/") || target.starts_with('/') || target.starts_with("${")) {
+        return Ok(Cow::Borrowed(target));
+    }
+
+    let mut expanded = String::new();
+    let mut rest = target;
+    if let Some(path) = target.strip_prefix("~/") {
+        let home_var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+        let home = environment
+            .var(home_var)
+            .ok_or_else(|| Error::UndefinedVariable(home_var.to_string()))?;
+        expanded.push_str(home.trim_end_matches('/'));
+        expanded.push('/');
+        rest = path;
+    }
+
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = &rest[start + 2..end];
+        let value = environment
+            .var(name)
+            .ok_or_else(|| Error::UndefinedVariable(name.to_string()))?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+
+    Ok(Cow::Owned(expanded))
+}
+
+/// Check if a specifier is a relative or absolute path, rather than a bare specifier.
+fn is_path_specifier(target: &str) -> bool {
+    target == "."
+        || target == ".."
+        || target.starts_with("./")
+        || target.starts_with('/')
+        || target.starts_with("../")
+}
+
+/// Check if a specifier can only refer to a directory, because it ends in a slash or a `.` or
+/// `..` segment. Node.js skips the file lookup for these.
+fn is_directory_specifier(target: &str) -> bool {
+    target == "."
+        || target == ".."
+        || target.ends_with('/')
+        || target.ends_with("/.")
+        || target.ends_with("/..")
+}
+
+fn normalize_extensions<T>(extensions: T) -> Vec<String>
+where
+    T: IntoIterator,
+    T::Item: ToString,
+{
+    extensions
+        .into_iter()
+        .map(|ext| ext.to_string())
+        .map(|ext| {
+            if ext.starts_with('.') {
+                ext
+            } else {
+                format!(".{}", ext)
+            }
+        })
+        .collect()
+}
+
+/// Check if a string references a core module, such as "events".
+pub fn is_core_module(target: &str) -> bool {
+    BUILTINS.iter().any(|builtin| builtin == &target)
+}
+
+/// The number of filesystem lookups the default resolver and compiled resolvers cache.
+const DEFAULT_CACHE_CAPACITY: usize = 4096;
+
+/// The resolver used by the free functions. It is created on first use.
+static DEFAULT_RESOLVER: OnceLock<RwLock<Resolver>> = OnceLock::new();
+
+fn default_resolver() -> &'static RwLock<Resolver> {
+    DEFAULT_RESOLVER.get_or_init(|| {
+        RwLock::new(Resolver::default().cache_capacity(DEFAULT_CACHE_CAPACITY))
+    })
+}
+
+/// Replace the resolver used by [`resolve`] and [`resolve_from`]. Its basedir is ignored.
+///
+/// By default, the free functions use `Resolver::default()` with a bounded cache that is shared
+/// by all calls. Applications that call the free functions heavily can configure the cache or
+/// other options here, and applications whose files change while they run can pass a resolver
+/// without a cache.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::{resolve, set_default_resolver, Resolver};
+///
+/// set_default_resolver(Resolver::node_esm());
+/// resolve("./lib/index.js");
+/// ```
+pub fn set_default_resolver(resolver: Resolver) {
+    replace_resolver(default_resolver(), resolver);
+}
+
+/// Replace the resolver in `shared`, even if a panic poisoned the lock.
+fn replace_resolver(shared: &RwLock<Resolver>, resolver: Resolver) {
+    *shared.write().unwrap_or_else(PoisonError::into_inner) = resolver;
+}
+
+/// Resolve `target` from `basedir` with the resolver in `shared`.
+fn resolve_shared(
+    shared: &RwLock<Resolver>,
+    target: &str,
+    basedir: PathBuf,
+) -> Result<PathBuf, Error> {
+    let resolver = shared
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .with_basedir(basedir);
+    resolver.resolve(target)
+}
+
+/// Resolve a node.js module path relative to the current working directory.
+/// Returns the absolute path to the module, or an error.
+///
+/// This uses a shared resolver, see [`set_default_resolver`].
+///
+/// ```rust
+/// match resolve("./lib") {
+///     Ok(path) => println!("Path is: {:?}", path),
+///     Err(err) => panic!("Failed: {:?}", err),
+/// }
+/// ```
+pub fn resolve(target: &str) -> Result<PathBuf, Error> {
+    resolve_from(target, PathBuf::from("."))
+}
+
+/// Resolve a node.js module path relative to `basedir`.
+/// Returns the absolute path to the module, or an error.
+///
+/// This uses a shared resolver, see [`set_default_resolver`].
+///
+/// ```rust
+/// match resolve_from("./index.js", env::current_dir().unwrap()) {
+///     Ok(path) => println!("Path is: {:?}", path),
+///     Err(err) => panic!("Failed: {:?}", err),
+/// }
+/// ```
+pub fn resolve_from(target: &str, basedir: PathBuf) -> Result<PathBuf, Error> {
+    resolve_shared(default_resolver(), target, basedir)
+}
+
+/// Get the node_modules folders that bare specifiers are looked up in from `basedir`, nearest
+/// first, like `module.paths` in Node.js.
+///
+/// This uses a shared resolver, see [`set_default_resolver`].
+///
+/// ```rust
+/// for path in node_modules_paths(env::current_dir().unwrap()) {
+///     println!("{}", path.display());
+/// }
+/// ```
+pub fn node_modules_paths(basedir: PathBuf) -> Vec<PathBuf> {
+    default_resolver()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .node_modules_paths(&basedir)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::PathBuf;
+    use super::*;
+
+    fn fixture(part: &str) -> PathBuf {
+        env::current_dir().unwrap().join("fixtures").join(part)
+    }
+    fn resolve_fixture(target: &str) -> PathBuf {
+        resolve_from(target, fixture("")).unwrap()
+    }
+
+    #[test]
+    fn appends_extensions() {
+        assert_eq!(
+            fixture("extensions/js-file.js"),
+            resolve_fixture("./extensions/js-file")
+        );
+        assert_eq!(
+            fixture("extensions/json-file.json"),
+            resolve_fixture("./extensions/json-file")
+        );
+        assert_eq!(
+            fixture("extensions/native-file.node"),
+            resolve_fixture("./extensions/native-file")
+        );
+        assert_eq!(
+            fixture("extensions/other-file.ext"),
+            resolve_fixture("./extensions/other-file.ext")
+        );
+        assert_eq!(
+            fixture("extensions/no-ext"),
+            resolve_fixture("./extensions/no-ext")
+        );
+        assert_eq!(
+            fixture("extensions/other-file.ext"),
+            Resolver::default()
+                .extensions(&[".ext"])
+                .with_basedir(fixture(""))
+                .resolve("./extensions/other-file")
+                .unwrap()
+        );
+        assert_eq!(
+            fixture("extensions/module.mjs"),
+            Resolver::default()
+                .extensions(&[".mjs"])
+                .with_basedir(fixture(""))
+                .resolve("./extensions/module")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn resolves_package_json() {
+        assert_eq!(
+            fixture("package-json/main-file/whatever.js"),
+            resolve_fixture("./package-json/main-file")
+        );
+        assert_eq!(
+            fixture("package-json/main-file-noext/whatever.js"),
+            resolve_fixture("./package-json/main-file-noext")
+        );
+        assert_eq!(
+            fixture("package-json/main-dir/subdir/index.js"),
+            resolve_fixture("./package-json/main-dir")
+        );
+        assert_eq!(
+            fixture("package-json/not-object/index.js"),
+            resolve_fixture("./package-json/not-object")
+        );
+        assert_eq!(
+            fixture("package-json/invalid/index.js"),
+            resolve_fixture("./package-json/invalid")
+        );
+        assert_eq!(
+            fixture("package-json/main-none/index.js"),
+            resolve_fixture("./package-json/main-none")
+        );
+        assert_eq!(
+            fixture("package-json/main-file/whatever.js"),
+            Resolver::default()
+                .main_fields(&["module", "main"])
+                .with_basedir(fixture(""))
+                .resolve("./package-json/main-file")
+                .unwrap()
+        );
+        assert_eq!(
+            fixture("package-json/module/index.mjs"),
+            Resolver::default()
+                .extensions(&[".mjs", ".js"])
+                .main_fields(&["module", "main"])
+                .with_basedir(fixture(""))
+                .resolve("./package-json/module")
+                .unwrap()
+        );
+        assert_eq!(
+            fixture("package-json/module-main/main.mjs"),
+            Resolver::default()
+                .extensions(&[".mjs", ".js"])
+                .main_fields(&["module", "main"])
+                .with_basedir(fixture(""))
+                .resolve("./package-json/module-main")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn package_type_extensions() {
+        let resolver = Resolver::default()
+            .package_type_extensions(true)
+            .with_basedir(fixture("package-type"));
+        assert_eq!(
+            fixture("package-type/module/file.mjs"),
+            resolver.resolve("./module/file").unwrap()
+        );
+        assert_eq!(
+            fixture("package-type/module/index.mjs"),
+            resolver.resolve("./module").unwrap()
+        );
+        assert_eq!(
+            fixture("package-type/commonjs/file.cjs"),
+            resolver.resolve("./commonjs/file").unwrap()
+        );
+        assert_eq!(
+            fixture("package-type/module/file.js"),
+            resolve_fixture("./package-type/module/file")
+        );
+    }
+
+    #[test]
+    fn presets() {
+        let esm = Resolver::node_esm().with_basedir(fixture(""));
+        assert!(esm.resolve("./extensions/js-file").is_err());
+        assert!(esm.resolve("./package-json/main-file").is_err());
+        assert_eq!(
+            fixture("extensions/js-file.js"),
+            esm.resolve("./extensions/js-file.js").unwrap()
+        );
+        assert_eq!(
+            fixture("node-modules/package-json/node_modules/dep/lib/index.js"),
+            esm.with_basedir(fixture("node-modules/package-json"))
+                .resolve("dep")
+                .unwrap()
+        );
+        assert_eq!(
+            fixture("extensions/js-file.js"),
+            esm.clone()
+                .specifier_resolution(SpecifierResolution::Node)
+                .resolve("./extensions/js-file")
+                .unwrap()
+        );
+        assert_eq!(
+            fixture("package-json/browser/browser.js"),
+            Resolver::webpack_web()
+                .with_basedir(fixture(""))
+                .resolve("./package-json/browser")
+                .unwrap()
+        );
+        assert_eq!(
+            fixture("package-json/types/index.d.ts"),
+            Resolver::typescript()
+                .with_basedir(fixture(""))
+                .resolve("./package-json/types")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn resolves_like_unpkg() {
+        let unpkg = Resolver::unpkg().with_basedir(fixture("unpkg"));
+        assert_eq!(
+            Ok(fixture("unpkg/node_modules/widget/dist/widget.umd.js")),
+            unpkg.resolve("widget")
+        );
+        assert_eq!(
+            Ok(fixture("unpkg/node_modules/chart/dist/chart.min.js")),
+            unpkg.resolve("chart")
+        );
+        assert_eq!(
+            Ok(fixture("package-json/browser/browser.js")),
+            unpkg.resolve("../package-json/browser")
+        );
+        let unpkg = unpkg.with_basedir(fixture("exports"));
+        assert_eq!(Ok(fixture("exports/node_modules/nested/browser.js")), unpkg.resolve("nested"));
+        assert_eq!(
+            Ok(fixture("exports/node_modules/exports/import.js")),
+            unpkg.resolve("exports/conditional")
+        );
+    }
+
+    #[test]
+    fn resolves_like_jest() {
+        let jest = Resolver::jest()
+            .module_paths(vec![fixture("jest/lib")])
+            .with_basedir(fixture("jest/src"));
+        assert_eq!(Ok(fixture("jest/src/button.tsx")), jest.resolve("./button"));
+        assert_eq!(Ok(fixture("jest/node_modules/dual/cjs.js")), jest.resolve("dual"));
+        assert_eq!(Ok(fixture("jest/node_modules/legacy/main.js")), jest.resolve("legacy"));
+        assert_eq!(Ok(fixture("jest/lib/shared/index.js")), jest.resolve("shared"));
+        assert_eq!(
+            Some(&fixture("jest/lib")),
+            jest.node_modules_chain().unwrap().last()
+        );
+        assert!(Resolver::jest().with_basedir(fixture("jest/src")).resolve("shared").is_err());
+    }
+
+    #[test]
+    fn replaces_default_resolver() {
+        // The default resolver is shared with tests that run in parallel, so this uses a
+        // resolver of its own.
+        let shared = RwLock::new(Resolver::default());
+        assert!(resolve_shared(&shared, "./other-file", fixture("extensions")).is_err());
+        let extensions = [".js", ".json", ".node", ".ext"];
+        replace_resolver(&shared, Resolver::default().extensions(&extensions));
+        assert_eq!(
+            Ok(fixture("extensions/other-file.ext")),
+            resolve_shared(&shared, "./other-file", fixture("extensions"))
+        );
+    }
+
+    #[test]
+    fn converts_errors() {
+        let resolver = Resolver::default().with_basedir(fixture(""));
+        let err = resolver.resolve("./does-not-exist").unwrap_err();
+        assert_eq!(IOErrorKind::NotFound, err.io_kind());
+        assert_eq!(Some("./does-not-exist"), err.specifier());
+        assert_eq!(Some(fixture("").as_path()), err.basedir());
+        assert!(err.tried().contains(&fixture("does-not-exist.js")));
+        assert!(err.to_string().starts_with("cannot find module \"./does-not-exist\""));
+        assert_eq!(IOErrorKind::NotFound, IOError::from(err).kind());
+
+        let err = resolver.resolve("./package-json/invalid-no-index").unwrap_err();
+        assert_eq!(IOErrorKind::InvalidData, err.io_kind());
+        assert!(err.source().is_some());
+
+        let err = Error::from(IOError::new(IOErrorKind::PermissionDenied, "denied"));
+        assert_eq!(Error::from(IOError::new(IOErrorKind::PermissionDenied, "denied")), err);
+        assert_eq!(IOErrorKind::PermissionDenied, IOError::from(err).kind());
+        assert_eq!(
+            IOErrorKind::InvalidInput,
+            Resolver::default().resolve("./a").unwrap_err().io_kind()
+        );
+    }
+
+    #[test]
+    fn resolves_wasm() {
+        let resolver = Resolver::wasm().with_basedir(fixture("wasm"));
+        assert_eq!(
+            fixture("wasm/node_modules/hasher/pkg/hasher.wasm"),
+            resolver.resolve("hasher").unwrap()
+        );
+        assert_eq!(fixture("wasm/module.wasm"), resolver.resolve("./module").unwrap());
+        assert_eq!(
+            fixture("wasm/node_modules/hasher/pkg/hasher.js"),
+            Resolver::default().with_basedir(fixture("wasm")).resolve("hasher").unwrap()
+        );
+    }
+
+    #[test]
+    fn resolves_stylesheets() {
+        let resolver = Resolver::style().with_basedir(fixture("style"));
+        assert_eq!(
+            fixture("style/node_modules/theme/scss/main.scss"),
+            resolver.resolve("~theme").unwrap()
+        );
+        assert_eq!(
+            fixture("style/node_modules/plain/style.css"),
+            resolver.resolve("plain").unwrap()
+        );
+        assert_eq!(
+            fixture("style/node_modules/theme/scss/_variables.scss"),
+            resolver.resolve("~theme/scss/variables").unwrap()
+        );
+        assert_eq!(
+            fixture("style/node_modules/theme/scss/_variables.scss"),
+            resolver.resolve("~theme/scss/variables.scss").unwrap()
+        );
+        assert_eq!(fixture("style/_mixins.scss"), resolver.resolve("./mixins").unwrap());
+        assert_eq!(
+            fixture("style/components/_index.scss"),
+            resolver.resolve("./components").unwrap()
+        );
+        assert!(Resolver::default()
+            .with_basedir(fixture("style"))
+            .resolve("./mixins")
+            .is_err());
+    }
+
+    #[test]
+    fn resolves_assets() {
+        let resolver = Resolver::assets(&[".svg", ".png"]).with_basedir(fixture("assets"));
+        assert_eq!(
+            fixture("assets/images/logo.png"),
+            resolver.resolve("./images/logo").unwrap()
+        );
+        assert_eq!(
+            fixture("assets/node_modules/fonts/files/inter.woff2"),
+            resolver.resolve("fonts/files/inter.woff2").unwrap()
+        );
+        assert_eq!(
+            fixture("assets/node_modules/fonts/files/inter.woff2"),
+            Resolver::default()
+                .bypass_exports(&["fonts"])
+                .with_basedir(fixture("assets"))
+                .resolve("fonts/files/inter.woff2")
+                .unwrap()
+        );
+        assert_eq!(
+            Err(Error::PackagePathNotExported {
+                package_dir: fixture("assets/node_modules/fonts"),
+                subpath: String::from("./files/inter.woff2"),
+            }),
+            Resolver::default()
+                .with_basedir(fixture("assets"))
+                .resolve("fonts/files/inter.woff2")
+        );
+    }
+
+    #[test]
+    fn resolves_from_multiple_basedirs() {
+        let resolver =
+            Resolver::default().with_basedirs(vec![fixture("extensions"), fixture("package-json")]);
+        assert_eq!(
+            fixture("extensions/js-file.js"),
+            resolver.resolve("./js-file").unwrap()
+        );
+        assert_eq!(
+            fixture("package-json/main-file/whatever.js"),
+            resolver.resolve("./main-file").unwrap()
+        );
+
+        let err = resolver.resolve("./missing").unwrap_err();
+        assert_eq!(Some(fixture("extensions").as_path()), err.basedir());
+        assert!(err.tried().contains(&fixture("extensions/missing.js")));
+        assert!(err.tried().contains(&fixture("package-json/missing.js")));
+
+        // Both basedirs share the node_modules folder of their parent, which is listed once.
+        let resolver = Resolver::default().with_basedirs(vec![
+            fixture("module-dirs/frontend/src"),
+            fixture("module-dirs/frontend"),
+        ]);
+        let err = resolver.resolve("missing-package").unwrap_err();
+        let missing = fixture("module-dirs/node_modules/missing-package");
+        assert_eq!(
+            vec![
+                missing.clone(),
+                missing.with_extension("js"),
+                missing.with_extension("json"),
+                missing.with_extension("node"),
+            ],
+            err.tried()
+        );
+
+        let resolver = resolver.with_basedir(fixture("extensions"));
+        assert!(resolver.resolve("./main-file").is_err());
+    }
+
+    #[test]
+    fn resolves_module_directories() {
+        let basedir = fixture("module-dirs/frontend/src");
+        let resolver = Resolver::default()
+            .module_directories_in(
+                fixture("module-dirs/frontend"),
+                &["web_modules", "node_modules"],
+            )
+            .with_basedir(basedir.clone());
+        assert_eq!(
+            fixture("module-dirs/frontend/web_modules/widget/index.js"),
+            resolver.resolve("widget").unwrap()
+        );
+        assert_eq!(
+            fixture("module-dirs/node_modules/shared/index.js"),
+            resolver.resolve("shared").unwrap()
+        );
+        assert!(resolver.resolve("outside").is_err());
+
+        let resolver = Resolver::default()
+            .module_directories(&["web_modules"])
+            .with_basedir(basedir);
+        assert_eq!(
+            fixture("module-dirs/web_modules/outside/index.js"),
+            resolver.resolve("outside").unwrap()
+        );
+        assert!(resolver.resolve("shared").is_err());
+    }
+
+    #[test]
+    fn reports_broken_symlinks() {
+        let resolver = Resolver::default().with_basedir(fixture("broken-symlink"));
+        assert_eq!(
+            Err(Error::BrokenSymlink {
+                link: fixture("broken-symlink/node_modules/foo"),
+                target: fixture("broken-symlink/.store/foo"),
+            }),
+            resolver.resolve("foo")
+        );
+        assert_eq!(
+            Err(Error::BrokenSymlink {
+                link: fixture("broken-symlink/file.js"),
+                target: fixture("broken-symlink/missing-target.js"),
+            }),
+            resolver.resolve("./file")
+        );
+        assert!(matches!(
+            resolver.resolve("./other"),
+            Err(Error::NotFound { .. })
+        ));
+        assert!(resolver.resolve("./index").is_ok());
+    }
 
-/// Check if a string references a core module, such as "events".
-pub fn is_core_module(target: &str) -> bool {
-    BUILTINS.iter().any(|builtin| builtin == &target)
-}
+    #[test]
+    fn enforces_package_boundaries() {
+        let resolver = Resolver::default()
+            .strict_package_boundaries(true)
+            .with_basedir(fixture("boundaries/src"));
+        assert!(matches!(
+            resolver.resolve("../node_modules/foo/lib/private.js"),
+            Err(Error::PackageBoundary { ref package_dir, .. })
+                if *package_dir == fixture("boundaries/node_modules/foo")
+        ));
+        assert!(matches!(
+            resolver.resolve("../node_modules/@scope/bar"),
+            Err(Error::PackageBoundary { ref package_dir, .. })
+                if *package_dir == fixture("boundaries/node_modules/@scope/bar")
+        ));
+        assert!(resolver.resolve("./index").is_ok());
+        assert!(resolver.resolve("foo/lib/private").is_ok());
 
-/// Resolve a node.js module path relative to the current working directory.
-/// Returns the absolute path to the module, or an error.
-///
-/// ```rust
-/// match resolve("./lib") {
-///     Ok(path) => println!("Path is: {:?}", path),
-///     Err(err) => panic!("Failed: {:?}", err),
-/// }
-/// ```
-pub fn resolve(target: &str) -> Result<PathBuf, Error> {
-    Resolver::default()
-        .with_basedir(PathBuf::from("."))
-        .resolve(target)
-}
+        let inside = resolver.with_basedir(fixture("boundaries/node_modules/foo/lib"));
+        assert!(inside.resolve("./public").is_ok());
+        assert!(inside.resolve("../lib/private").is_ok());
+        assert!(matches!(
+            inside.resolve("../../@scope/bar"),
+            Err(Error::PackageBoundary { .. })
+        ));
+        assert!(inside.resolve("../../../src/index").is_ok());
 
-/// Resolve a node.js module path relative to `basedir`.
-/// Returns the absolute path to the module, or an error.
-///
-/// ```rust
-/// match resolve_from("./index.js", env::current_dir().unwrap()) {
-///     Ok(path) => println!("Path is: {:?}", path),
-///     Err(err) => panic!("Failed: {:?}", err),
-/// }
-/// ```
-pub fn resolve_from(target: &str, basedir: PathBuf) -> Result<PathBuf, Error> {
-    Resolver::default().with_basedir(basedir).resolve(target)
-}
+        assert!(Resolver::default()
+            .with_basedir(fixture("boundaries/src"))
+            .resolve("../node_modules/foo/lib/private.js")
+            .is_ok());
+    }
 
-#[cfg(test)]
-mod tests {
-    use std::env;
-    use std::path::PathBuf;
-    use super::*;
+    #[test]
+    fn resolves_linked_dependencies() {
+        let resolver = Resolver::default()
+            .link_dependencies(true)
+            .with_basedir(fixture("linked/app/src"));
+        assert_eq!(
+            fixture("linked/local-pkg/main.js"),
+            resolver.resolve("local-pkg").unwrap()
+        );
+        assert_eq!(
+            fixture("linked/portal-pkg/lib/sub.js"),
+            resolver.resolve("portal-pkg/sub").unwrap()
+        );
+        assert!(resolver.resolve("portal-pkg/lib/sub").is_err());
+        assert_eq!(
+            fixture("linked/portal-pkg/node_modules/inner/index.js"),
+            resolver
+                .with_basedir(fixture("linked/portal-pkg/lib"))
+                .resolve("inner")
+                .unwrap()
+        );
+        assert!(Resolver::default()
+            .with_basedir(fixture("linked/app/src"))
+            .resolve("local-pkg")
+            .is_err());
+        assert!(resolver.resolve("sibling").is_err());
+        // Installed packages take precedence, even if they do not export the subpath.
+        assert!(resolver.resolve("exported-pkg/a").is_ok());
+        assert!(matches!(
+            resolver.resolve("exported-pkg/b"),
+            Err(Error::PackagePathNotExported { .. })
+        ));
+    }
 
-    fn fixture(part: &str) -> PathBuf {
-        env::current_dir().unwrap().join("fixtures").join(part)
+    #[test]
+    fn resolves_file_dependencies() {
+        let resolver = Resolver::default()
+            .file_dependencies(true)
+            .with_basedir(fixture("linked/app/src"));
+        assert_eq!(
+            fixture("linked/sibling/src/index.js"),
+            resolver.resolve("sibling").unwrap()
+        );
+        assert!(resolver.resolve("vendored").is_err());
+        assert!(resolver.resolve("local-pkg").is_err());
     }
-    fn resolve_fixture(target: &str) -> PathBuf {
-        resolve_from(target, fixture("")).unwrap()
+
+    #[test]
+    fn overrides_packages() {
+        let resolver = Resolver::default()
+            .override_package("dep", fixture("override/fake-dep"))
+            .override_package("missing", fixture("override/missing"))
+            .with_basedir(fixture("node-modules/package-json"));
+        assert_eq!(
+            fixture("override/fake-dep/index.js"),
+            resolver.resolve("dep").unwrap()
+        );
+        assert_eq!(
+            fixture("override/fake-dep/lib/extra.js"),
+            resolver.resolve("dep/lib/extra").unwrap()
+        );
+        assert!(resolver.resolve("dep/lib").is_err());
+        assert!(resolver.resolve("missing").is_err());
     }
 
     #[test]
-    fn appends_extensions() {
+    fn resolves_directories() {
+        let resolver = Resolver::default().with_basedir(fixture("node-modules/package-json"));
         assert_eq!(
-            fixture("extensions/js-file.js"),
-            resolve_fixture("./extensions/js-file")
+            fixture("node-modules/package-json/node_modules/dep"),
+            resolver.resolve_directory("dep").unwrap()
         );
         assert_eq!(
-            fixture("extensions/json-file.json"),
-            resolve_fixture("./extensions/json-file")
+            fixture("node-modules/package-json/node_modules/dep/lib"),
+            resolver.resolve_directory("dep/lib").unwrap()
         );
         assert_eq!(
-            fixture("extensions/native-file.node"),
-            resolve_fixture("./extensions/native-file")
+            fixture("node-modules/package-json"),
+            resolver.resolve_directory("./").unwrap()
+        );
+        assert!(resolver.resolve_directory("dep/package.json").is_err());
+        assert!(resolver.resolve_directory("events").is_err());
+    }
+
+    #[test]
+    fn resolves_in_packages() {
+        // The basedir does not matter.
+        let resolver = Resolver::default().with_basedir(fixture("extensions"));
+        let exports = fixture("exports/node_modules/exports");
+        assert_eq!(Ok(exports.join("main.js")), resolver.resolve_in_package(&exports, ""));
+        assert_eq!(Ok(exports.join("main.js")), resolver.resolve_in_package(&exports, "."));
+        assert_eq!(
+            Ok(exports.join("lib/feature.js")),
+            resolver.resolve_in_package(&exports, "feature")
         );
         assert_eq!(
-            fixture("extensions/other-file.ext"),
-            resolve_fixture("./extensions/other-file.ext")
+            Ok(exports.join("lib/utils/string.js")),
+            resolver.resolve_in_package(&exports, "./utils/string")
         );
         assert_eq!(
-            fixture("extensions/no-ext"),
-            resolve_fixture("./extensions/no-ext")
+            Err(Error::PackagePathNotExported {
+                package_dir: exports.clone(),
+                subpath: String::from("./lib/feature.js"),
+            }),
+            resolver.resolve_in_package(&exports, "lib/feature.js")
         );
+        assert!(matches!(
+            resolver.resolve_in_package(&exports, "../patterns/package.json"),
+            Err(Error::InvalidSpecifier { .. })
+        ));
+
+        let dep = fixture("node-modules/package-json/node_modules/dep");
+        assert_eq!(Ok(dep.join("lib/index.js")), resolver.resolve_in_package(&dep, ""));
+        assert_eq!(Ok(dep.join("lib/index.js")), resolver.resolve_in_package(&dep, "lib/"));
         assert_eq!(
-            fixture("extensions/other-file.ext"),
-            Resolver::default()
-                .extensions(&[".ext"])
-                .with_basedir(fixture(""))
-                .resolve("./extensions/other-file")
-                .unwrap()
+            Ok(dep.join("package.json")),
+            resolver.resolve_in_package(&dep, "./package")
+        );
+        assert!(matches!(
+            resolver.resolve_in_package(&dep, "missing"),
+            Err(Error::NotFound { ref specifier, .. }) if specifier == "./missing"
+        ));
+    }
+
+    #[test]
+    fn resolves_main_directories() {
+        assert_eq!(
+            fixture("package-json/main-nested-main/lib/entry.js"),
+            resolve_fixture("./package-json/main-nested-main")
         );
         assert_eq!(
-            fixture("extensions/module.mjs"),
+            fixture("package-json/main-exports-dir/lib/cjs.js"),
+            resolve_fixture("./package-json/main-exports-dir")
+        );
+        assert_eq!(
+            fixture("package-json/main-exports-dir/lib/esm.mjs"),
             Resolver::default()
-                .extensions(&[".mjs"])
+                .conditions(&["import"])
                 .with_basedir(fixture(""))
-                .resolve("./extensions/module")
+                .resolve("./package-json/main-exports-dir")
                 .unwrap()
         );
+        // The "exports" field takes precedence over the folder's own main field, even if none
+        // of its conditions match.
+        assert!(Resolver::default()
+            .conditions(Vec::<String>::new())
+            .with_basedir(fixture(""))
+            .resolve("./package-json/main-exports-dir")
+            .is_err());
     }
 
     #[test]
-    fn resolves_package_json() {
+    fn detects_main_cycles() {
+        let cycle = fixture("package-json/main-cycle");
         assert_eq!(
-            fixture("package-json/main-file/whatever.js"),
-            resolve_fixture("./package-json/main-file")
+            Err(Error::ManifestCycle {
+                chain: vec![
+                    cycle.join("package.json"),
+                    cycle.join("a/package.json"),
+                    cycle.join("b/package.json"),
+                    cycle.join("a/package.json"),
+                ],
+            }),
+            resolve_from("./package-json/main-cycle", fixture(""))
         );
+        // A cycle inside a package is reported instead of moving on to the next
+        // node_modules folder.
+        let cyclic = cycle.join("node_modules/cyclic");
         assert_eq!(
-            fixture("package-json/main-file-noext/whatever.js"),
-            resolve_fixture("./package-json/main-file-noext")
+            Err(Error::ManifestCycle {
+                chain: vec![
+                    cyclic.join("package.json"),
+                    cyclic.join("lib/package.json"),
+                    cyclic.join("package.json"),
+                ],
+            }),
+            resolve_from("cyclic", cycle.join("a"))
         );
+
         assert_eq!(
-            fixture("package-json/main-dir/subdir/index.js"),
-            resolve_fixture("./package-json/main-dir")
+            fixture("package-json/main-self/index.js"),
+            resolve_fixture("./package-json/main-self")
         );
+    }
+
+    #[test]
+    fn reports_invalid_package_json() {
         assert_eq!(
-            fixture("package-json/not-object/index.js"),
-            resolve_fixture("./package-json/not-object")
+            fixture("package-json/trailing-comma/index.js"),
+            resolve_fixture("./package-json/trailing-comma")
         );
         assert_eq!(
-            fixture("package-json/invalid/index.js"),
-            resolve_fixture("./package-json/invalid")
+            fixture("package-json/trailing-comma/main.js"),
+            Resolver::default()
+                .tolerant_package_json(true)
+                .with_basedir(fixture(""))
+                .resolve("./package-json/trailing-comma")
+                .unwrap()
         );
+        match resolve_from("./package-json/invalid-no-index", fixture("")) {
+            Err(Error::InvalidPackageJson { path, .. }) => {
+                assert_eq!(fixture("package-json/invalid-no-index/package.json"), path)
+            }
+            other => panic!("expected InvalidPackageJson, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn disables_package_main() {
         assert_eq!(
-            fixture("package-json/main-none/index.js"),
-            resolve_fixture("./package-json/main-none")
+            fixture("package-json/main-and-index/main.js"),
+            resolve_fixture("./package-json/main-and-index")
         );
+
+        let resolver = Resolver::default()
+            .use_package_main(false)
+            .with_basedir(fixture(""));
         assert_eq!(
-            fixture("package-json/main-file/whatever.js"),
+            fixture("package-json/main-and-index/index.js"),
+            resolver.resolve("./package-json/main-and-index").unwrap()
+        );
+        assert_eq!(0, resolver.stats().package_json_reads);
+
+        let resolver = Resolver::default()
+            .main_fields(Vec::<String>::new())
+            .with_basedir(fixture(""));
+        assert_eq!(
+            fixture("package-json/main-and-index/index.js"),
+            resolver.resolve("./package-json/main-and-index").unwrap()
+        );
+        assert_eq!(0, resolver.stats().package_json_reads);
+    }
+
+    #[test]
+    fn resolves_directories_lib() {
+        assert_eq!(
+            fixture("package-json/directories-lib/index.js"),
+            resolve_fixture("./package-json/directories-lib")
+        );
+        assert_eq!(
+            fixture("package-json/directories-lib/lib/index.js"),
             Resolver::default()
-                .main_fields(&["module", "main"])
+                .directories_lib(true)
                 .with_basedir(fixture(""))
-                .resolve("./package-json/main-file")
+                .resolve("./package-json/directories-lib")
                 .unwrap()
         );
+    }
+
+    #[test]
+    fn uses_scoped_resolvers() {
+        let resolver = Resolver::default()
+            .scope(fixture("extensions"), Resolver::default().extensions(&[".mjs"]))
+            .scope(fixture(""), Resolver::default().extensions(&[".node"]));
+
+        assert!(resolver
+            .with_basedir(fixture(""))
+            .resolve("./extensions/module")
+            .is_err());
         assert_eq!(
-            fixture("package-json/module/index.mjs"),
-            Resolver::default()
-                .extensions(&[".mjs", ".js"])
-                .main_fields(&["module", "main"])
+            fixture("extensions/native-file.node"),
+            resolver
                 .with_basedir(fixture(""))
-                .resolve("./package-json/module")
+                .resolve("./extensions/native-file")
                 .unwrap()
         );
         assert_eq!(
-            fixture("package-json/module-main/main.mjs"),
-            Resolver::default()
-                .extensions(&[".mjs", ".js"])
-                .main_fields(&["module", "main"])
-                .with_basedir(fixture(""))
-                .resolve("./package-json/module-main")
+            fixture("extensions/module.mjs"),
+            resolver
+                .with_basedir(fixture("extensions"))
+                .resolve("./module")
                 .unwrap()
         );
+        assert_eq!(
+            fixture("extensions/js-file.js"),
+            resolver
+                .with_basedir(PathBuf::from("/"))
+                .resolve(fixture("extensions/js-file").to_str().unwrap())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        let cases = [
+            ("", "."),
+            (".", "."),
+            ("./", "./"),
+            ("a/./b", "a/b"),
+            ("a/b/../c", "a/c"),
+            ("a/..", "."),
+            ("../a/../../b", "../../b"),
+            ("./../a", "../a"),
+            ("a/b/", "a/b/"),
+            ("/a/../../b", "/b"),
+            ("/..", "/"),
+            ("//a//b", "/a/b"),
+        ];
+        for (input, expected) in cases.iter() {
+            assert_eq!(
+                Path::new(expected).as_os_str(),
+                normalize(Path::new(input)).as_os_str(),
+                "normalize({:?})",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn resolves_dot_specifiers() {
+        assert_eq!(
+            fixture("package-json/main-file/whatever.js"),
+            resolve_from(".", fixture("package-json/main-file")).unwrap()
+        );
+        assert_eq!(
+            fixture("dot/lib/index.js"),
+            resolve_from(".", fixture("dot/lib")).unwrap()
+        );
+        assert_eq!(
+            fixture("dot/lib/index.js"),
+            resolve_from("./", fixture("dot/lib")).unwrap()
+        );
+        assert_eq!(
+            fixture("dot/lib/index.js"),
+            resolve_from("..", fixture("dot/lib/sub")).unwrap()
+        );
+        assert_eq!(
+            fixture("dot/lib/index.js"),
+            resolve_from("./lib/", fixture("dot")).unwrap()
+        );
+        assert_eq!(fixture("dot/lib.js"), resolve_from("./lib", fixture("dot")).unwrap());
+    }
+
+    #[test]
+    fn chooses_ascent_origin() {
+        let basedir = fixture("symlink-ascent/app/node_modules/local");
+        let shared = fixture("symlink-ascent/app/node_modules/shared/index.js");
+        let resolve = |resolver: Resolver| resolver.with_basedir(basedir.clone()).resolve("shared");
+
+        assert_eq!(Ok(shared.clone()), resolve(Resolver::default()));
+        assert!(resolve(Resolver::default().ascent_origin(AscentOrigin::Real)).is_err());
+        let realpath_dirs = Resolver::default().symlinks(SymlinkStrategy::RealpathDirectories);
+        assert!(resolve(realpath_dirs.clone()).is_err());
+        assert_eq!(
+            Ok(shared),
+            resolve(realpath_dirs.ascent_origin(AscentOrigin::Link))
+        );
+    }
+
+    #[test]
+    fn resolves_nested_package_json() {
+        let basedir = fixture("nested-manifest");
+        let pkg = fixture("nested-manifest/node_modules/@scope/pkg");
+        let nested = Resolver::default().with_basedir(basedir.clone());
+        assert_eq!(Ok(pkg.join("index.js")), nested.resolve("@scope/pkg"));
+        assert_eq!(Ok(pkg.join("dir/entry.js")), nested.resolve("@scope/pkg/dir"));
+        assert_eq!(Ok(pkg.join("dir/entry.js")), nested.resolve("@scope/pkg/dir/"));
+
+        let flat = nested.nested_package_json(false).with_basedir(basedir);
+        assert_eq!(Ok(pkg.join("index.js")), flat.resolve("@scope/pkg"));
+        assert_eq!(Ok(pkg.join("dir/index.js")), flat.resolve("@scope/pkg/dir"));
+        assert_eq!(Ok(pkg.join("dir/index.js")), flat.resolve("@scope/pkg/dir/"));
+    }
+
+    #[test]
+    fn isolates_workspace_packages() {
+        let root = fixture("workspaces");
+        let app = root.join("packages/app");
+        let resolver = Resolver::default()
+            .workspace_packages(vec![app.clone(), root.join("packages/lib")])
+            .with_basedir(app.join("src"));
+        assert_eq!(Ok(app.join("node_modules/own/index.js")), resolver.resolve("own"));
+        assert_eq!(
+            vec![app.join("src/node_modules"), app.join("node_modules")],
+            resolver.node_modules_chain().unwrap()
+        );
+        assert!(matches!(resolver.resolve("hoisted"), Err(Error::NotFound { .. })));
+
+        // Installed packages can still use the packages next to them.
+        let resolver = resolver.with_basedir(app.join("node_modules/own"));
+        assert_eq!(Ok(app.join("node_modules/own/index.js")), resolver.resolve("own"));
+
+        // Outside the workspace packages, nothing changes.
+        let resolver = resolver.with_basedir(root.join("scripts"));
+        assert_eq!(Ok(root.join("node_modules/hoisted/index.js")), resolver.resolve("hoisted"));
+        assert_eq!(
+            Ok(root.join("node_modules/hoisted/index.js")),
+            Resolver::default().with_basedir(app.join("src")).resolve("hoisted")
+        );
+    }
+
+    #[test]
+    fn denies_packages() {
+        let basedir = fixture("node-modules/package-json");
+        let resolver = Resolver::default()
+            .deny(&["left-pad", "de*"])
+            .with_basedir(basedir.clone());
+        let denied = |specifier: &str| Error::DeniedPackage {
+            specifier: specifier.to_string(),
+            pattern: String::from("de*"),
+        };
+        assert_eq!(Err(denied("dep")), resolver.resolve("dep"));
+        assert_eq!(Err(denied("dep/lib/index.js")), resolver.resolve("dep/lib/index.js"));
+        assert_eq!(Err(denied("dep")), resolver.resolve_directory("dep"));
+        assert_eq!(IOErrorKind::PermissionDenied, resolver.resolve("dep").unwrap_err().io_kind());
+        assert!(matches!(
+            resolver.resolve("left-pad"),
+            Err(Error::DeniedPackage { ref pattern, .. }) if pattern == "left-pad"
+        ));
+        assert!(resolver.resolve("./node_modules/dep").is_ok());
+        assert!(resolver.resolve("fs").is_ok());
+        assert!(resolver.deny(Vec::<String>::new()).with_basedir(basedir).resolve("dep").is_ok());
+    }
+
+    #[test]
+    fn configures_probe_order() {
+        let basedir = fixture("ambiguous");
+        let resolve = |probe_order: ProbeOrder, target: &str| {
+            Resolver::default()
+                .probe_order(probe_order)
+                .with_basedir(basedir.clone())
+                .resolve(target)
+        };
+        assert_eq!(Ok(basedir.join("config.js")), resolve(ProbeOrder::FileFirst, "./config"));
+        assert_eq!(
+            Ok(basedir.join("config/index.js")),
+            resolve(ProbeOrder::DirectoryFirst, "./config")
+        );
+        assert_eq!(Ok(basedir.join("config.js")), resolve(ProbeOrder::FileOnly, "./config"));
+        assert_eq!(
+            Ok(basedir.join("config/index.js")),
+            resolve(ProbeOrder::DirectoryOnly, "./config")
+        );
+        assert_eq!(Ok(basedir.join("single.js")), resolve(ProbeOrder::DirectoryFirst, "./single"));
+        assert!(resolve(ProbeOrder::DirectoryOnly, "./single").is_err());
+        assert!(resolve(ProbeOrder::FileOnly, "./config/").is_ok());
+        assert_eq!(
+            Ok(basedir.join("node_modules/pkg/index.js")),
+            resolve(ProbeOrder::FileOnly, "pkg")
+        );
+
+        let resolver = Resolver::default()
+            .probe_order(ProbeOrder::DirectoryFirst)
+            .with_basedir(basedir.clone());
+        let candidates = resolver.candidates("./config").unwrap();
+        assert_eq!(basedir.join("config/package.json"), candidates[1]);
+        let position = |path: PathBuf| candidates.iter().position(|c| *c == path).unwrap();
+        assert!(position(basedir.join("config/index.js")) < position(basedir.join("config.js")));
+        assert_eq!(
+            vec![
+                basedir.join("config/index.js"),
+                basedir.join("config.js"),
+                basedir.join("config.json"),
+            ],
+            resolver.interpretations("./config").unwrap()
+        );
     }
 
     #[test]
@@ -591,6 +4405,35 @@ mod tests {
             fixture("node-modules/walk/node_modules/ok/index.js"),
             resolve_from("ok", fixture("node-modules/walk/src")).unwrap()
         );
+        // A node_modules folder is never searched for a nested node_modules folder.
+        let nested = fixture("node-modules/nested/node_modules/dep");
+        assert_eq!(
+            fixture("node-modules/nested/node_modules/dep/index.js"),
+            resolve_from("dep", nested.clone()).unwrap()
+        );
+        assert!(resolve_from("doubled", nested).is_err());
+    }
+
+    #[test]
+    fn lists_node_modules_paths() {
+        let basedir = fixture("node-modules/walk/src");
+        let paths = node_modules_paths(basedir.clone());
+        assert_eq!(fixture("node-modules/walk/src/node_modules"), paths[0]);
+        assert_eq!(fixture("node-modules/walk/node_modules"), paths[1]);
+        assert_eq!(PathBuf::from("/node_modules"), *paths.last().unwrap());
+        assert_eq!(paths, node_modules_paths(PathBuf::from("fixtures/node-modules/walk/src")));
+
+        let resolver = Resolver::default().cache(Arc::new(MemoryCache::default()));
+        let existing = resolver.existing_node_modules_paths(&basedir);
+        assert_eq!(
+            vec![
+                fixture("node-modules/walk/src/node_modules"),
+                fixture("node-modules/walk/node_modules"),
+            ],
+            existing
+        );
+        resolver.existing_node_modules_paths(&basedir);
+        assert!(resolver.stats().cache_hits > 0);
     }
 
     #[test]
@@ -617,6 +4460,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn symlink_strategies() {
+        let basedir = fixture("symlink-walk/app/node_modules/lib");
+        assert!(resolve_from("helper", basedir.clone()).is_err());
+        assert_eq!(
+            fixture("symlink-walk/packages/node_modules/helper/index.js"),
+            Resolver::default()
+                .symlinks(SymlinkStrategy::RealpathDirectories)
+                .with_basedir(basedir.clone())
+                .resolve("helper")
+                .unwrap()
+        );
+        assert_eq!(
+            fixture("symlink-walk/packages/sibling.js"),
+            Resolver::default()
+                .symlinks(SymlinkStrategy::RealpathDirectories)
+                .with_basedir(basedir.clone())
+                .resolve("../sibling")
+                .unwrap()
+        );
+        assert_eq!(
+            fixture("symlink-walk/app/node_modules/lib/index.js"),
+            Resolver::default()
+                .symlinks(SymlinkStrategy::Preserve)
+                .with_basedir(basedir)
+                .resolve("./")
+                .unwrap()
+        );
+    }
+
     #[test]
     fn resolves_absolute_specifier() {
         let full_path = fixture("extensions/js-file");
@@ -624,6 +4497,81 @@ mod tests {
         assert_eq!(fixture("extensions/js-file.js"), resolve(id).unwrap());
     }
 
+    #[test]
+    fn outputs_relative_paths() {
+        let resolver = Resolver::default()
+            .output_relative_to(fixture(""))
+            .with_basedir(fixture("extensions"));
+        assert_eq!(Ok(PathBuf::from("extensions/js-file.js")), resolver.resolve("./js-file"));
+        assert_eq!(Ok(PathBuf::from("fs")), resolver.resolve("fs"));
+        let resolution = resolver.resolve_full("./js-file").unwrap();
+        assert_eq!(PathBuf::from("extensions/js-file.js"), resolution.path);
+        assert!(resolution.watched.iter().all(|path| path.is_relative()));
+        assert_eq!(
+            PathBuf::from("extensions/js-file"),
+            resolver.candidates("./js-file").unwrap()[0]
+        );
+        let package = resolver.with_basedir(fixture("node-modules/package-json"));
+        assert_eq!(
+            Ok(PathBuf::from("node-modules/package-json/node_modules/dep")),
+            package.resolve_directory("dep")
+        );
+        assert_eq!(
+            Ok(PathBuf::from("node-modules/package-json/node_modules/dep/lib/index.js")),
+            package.resolve_in_package(fixture("node-modules/package-json/node_modules/dep"), "")
+        );
+
+        let outside = resolver.output_relative_to(fixture("jest/src"));
+        assert_eq!(
+            Ok(PathBuf::from("../../extensions/js-file.js")),
+            outside.resolve("./js-file")
+        );
+        assert_eq!(
+            Ok(PathBuf::from("../../extensions/js-file.js")),
+            outside.output_relative_to(PathBuf::from("fixtures/jest/src")).resolve("./js-file")
+        );
+
+        assert_eq!(Some(PathBuf::from(".")), relative_path(Path::new("/a/b"), Path::new("/a/b")));
+        assert_eq!(None, relative_path(Path::new("a/b"), Path::new("a")));
+    }
+
+    #[test]
+    fn expands_environment_variables() {
+        env::set_var("NODE_RESOLVE_TEST_FIXTURES", fixture(""));
+        let resolver = Resolver::default().expand_env(true);
+        assert_eq!(
+            fixture("extensions/js-file.js"),
+            resolver
+                .resolve("${NODE_RESOLVE_TEST_FIXTURES}/extensions/js-file")
+                .unwrap()
+        );
+        assert!(matches!(
+            resolver.resolve("${NODE_RESOLVE_TEST_UNDEFINED}/js-file"),
+            Err(Error::UndefinedVariable(name)) if name == "NODE_RESOLVE_TEST_UNDEFINED"
+        ));
+        assert!(Resolver::default()
+            .resolve("${NODE_RESOLVE_TEST_FIXTURES}/extensions/js-file")
+            .is_err());
+    }
+
+    #[test]
+    fn root_relative_specifiers_use_the_basedir_root() {
+        assert_eq!(PathBuf::from("/"), root_of(Path::new("/a/b")));
+        assert_eq!(PathBuf::new(), root_of(Path::new("a/b")));
+        assert_eq!(
+            PathBuf::from(ROOT),
+            Resolver::default().with_basedir(PathBuf::from("a/b")).get_root()
+        );
+        #[cfg(windows)]
+        {
+            assert_eq!(PathBuf::from("D:\\"), root_of(Path::new("D:\\a\\b")));
+            assert_eq!(
+                PathBuf::from("\\\\server\\share\\"),
+                root_of(Path::new("\\\\server\\share\\a"))
+            );
+        }
+    }
+
     #[test]
     fn core_modules() {
         assert!(is_core_module("events"));
@@ -0,0 +1,89 @@
+//! Reporting of the filesystem accesses done by a resolver.
+
+use crate::EntryKind;
+use std::path::Path;
+
+/// A filesystem access done by the resolver, reported to an [`Observer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Access {
+    /// The resolver checked what exists at the path. `Missing` means the result depends on the
+    /// path *not* existing.
+    Entry(EntryKind),
+    /// The resolver read the contents of the file, like a package.json file.
+    Read,
+    /// The resolver expanded the symlinks in the path.
+    Canonicalize,
+    /// The resolver listed the entries of the directory.
+    ReadDir,
+    /// The resolver read the target of the symlink.
+    ReadLink,
+}
+
+/// Receives every filesystem access done by a resolver, including ones that are answered by
+/// the cache.
+///
+/// Build systems can use this to record the exact inputs of a resolution, so it can be
+/// redone when one of them changes. Observers are called from every thread that uses the
+/// resolver, so they must do their own synchronization.
+///
+/// Closures that take a path and an [`Access`] are observers.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::{Access, Resolver};
+/// use std::sync::{Arc, Mutex};
+///
+/// let accessed = Arc::new(Mutex::new(Vec::new()));
+/// let log = Arc::clone(&accessed);
+/// let resolver = Resolver::default().observer(Arc::new(move |path: &Path, access: Access| {
+///     log.lock().unwrap().push((path.to_path_buf(), access));
+/// }));
+/// ```
+pub trait Observer: Send + Sync {
+    /// Called after the resolver accessed `path`.
+    fn observe(&self, path: &Path, access: Access);
+}
+
+impl<F> Observer for F
+where
+    F: Fn(&Path, Access) + Send + Sync,
+{
+    fn observe(&self, path: &Path, access: Access) {
+        self(path, access)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Access, EntryKind, MemoryCache, Resolver};
+    use std::env;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn observes_accesses() {
+        let fixtures = env::current_dir().unwrap().join("fixtures/node-modules/package-json");
+        let accessed = Arc::new(Mutex::new(Vec::<(PathBuf, Access)>::new()));
+        let log = Arc::clone(&accessed);
+        let resolver = Resolver::default()
+            .cache(Arc::new(MemoryCache::default()))
+            .observer(Arc::new(move |path: &Path, access: Access| {
+                log.lock().unwrap().push((path.to_path_buf(), access));
+            }))
+            .with_basedir(fixtures.clone());
+
+        let dep = fixtures.join("node_modules/dep");
+        for _ in 0..2 {
+            accessed.lock().unwrap().clear();
+            resolver.resolve("dep").unwrap();
+
+            let accessed = accessed.lock().unwrap();
+            assert!(accessed.contains(&(dep.join("package.json"), Access::Read)));
+            assert!(accessed.contains(&(dep.join("lib"), Access::Entry(EntryKind::Directory))));
+            assert!(accessed.contains(&(dep.join("lib.js"), Access::Entry(EntryKind::Missing))));
+            assert!(accessed.contains(&(dep.join("lib/index.js"), Access::Canonicalize)));
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Parsing of package.json files.
+
+use serde_json::Value;
+
+/// Parse the contents of a package.json file. A leading byte order mark is always ignored, like
+/// Node.js does. In tolerant mode, trailing commas in objects and arrays are accepted too.
+pub(crate) fn parse_package_json(contents: &str, tolerant: bool) -> serde_json::Result<Value> {
+    let contents = contents.strip_prefix('\u{feff}').unwrap_or(contents);
+    if tolerant {
+        parse_json(&strip_trailing_commas(contents))
+    } else {
+        parse_json(contents)
+    }
+}
+
+#[cfg(not(feature = "simd-json"))]
+fn parse_json(json: &str) -> serde_json::Result<Value> {
+    serde_json::from_str(json)
+}
+
+/// Parse JSON using SIMD instructions. This requires the `simd-json` feature.
+///
+/// Invalid JSON is parsed again with serde_json, so errors look the same with and without the
+/// feature.
+#[cfg(feature = "simd-json")]
+fn parse_json(json: &str) -> serde_json::Result<Value> {
+    let mut bytes = json.as_bytes().to_vec();
+    simd_json::serde::from_slice(&mut bytes).or_else(|_| serde_json::from_str(json))
+}
+
+/// Remove commas that are directly followed by the end of an object or array, ignoring
+/// whitespace. Commas inside strings are kept.
+fn strip_trailing_commas(json: &str) -> String {
+    let mut stripped = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in json.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = json[i + 1..].trim_start().chars().next();
+            if matches!(next, Some('}') | Some(']')) {
+                continue;
+            }
+        }
+        stripped.push(c);
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_tolerantly() {
+        let contents = "\u{feff}{ \"main\": \"a,]\", \"files\": [\"a\", \"b\",], }";
+        assert!(parse_package_json(contents, false).is_err());
+        assert_eq!(
+            json!({ "main": "a,]", "files": ["a", "b"] }),
+            parse_package_json(contents, true).unwrap()
+        );
+        assert_eq!(
+            json!({ "main": "index.js" }),
+            parse_package_json("\u{feff}{ \"main\": \"index.js\" }", false).unwrap()
+        );
+    }
+
+    #[test]
+    fn keeps_key_order() {
+        let pkg = parse_package_json(r#"{ "z": 1, "a": { "import": 2, "default": 3 } }"#, false);
+        let pkg = pkg.unwrap();
+        let keys: Vec<&str> = pkg.as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(vec!["z", "a"], keys);
+        let keys: Vec<&str> = pkg["a"].as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(vec!["import", "default"], keys);
+
+        let err = parse_package_json("{ \"main\": }", false).unwrap_err();
+        assert_eq!(1, err.line());
+    }
+}
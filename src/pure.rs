@@ -0,0 +1,509 @@
+//! Resolution decisions that do not touch the filesystem.
+//!
+//! These functions decide which paths are tried, and in which order. The caller decides
+//! whether a path exists, so they can be driven by a real filesystem, a snapshot, or synthetic
+//! inputs in tests.
+
+use crate::environment::node_path;
+use crate::specifier::validate_specifier;
+use crate::{is_directory_specifier, is_path_specifier, normalize, parse_specifier};
+use crate::{EntryKind, Error, ProbeOrder, Resolver};
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::iter;
+use std::path::{Path, PathBuf};
+
+impl Resolver {
+    /// Get the paths that resolving `target` may check, in order, without touching the
+    /// filesystem.
+    ///
+    /// This lists the files with each extension, the package.json and index files of
+    /// directories, and for bare specifiers, the node_modules folders that are searched and the
+    /// same candidates inside each of them. Paths that depend on the contents of files, like
+    /// the "main" and "exports" fields of package.json files, are not included. Builtin modules
+    /// and `#` imports have no candidates.
+    ///
+    /// This is useful to set up file watchers before resolving, or to check a directory layout.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let candidates = Resolver::default()
+    ///     .with_basedir(PathBuf::from("/project/src"))
+    ///     .candidates("./utils")
+    ///     .unwrap();
+    /// assert_eq!(PathBuf::from("/project/src/utils"), candidates[0]);
+    /// assert_eq!(PathBuf::from("/project/src/utils.js"), candidates[1]);
+    /// ```
+    pub fn candidates(&self, target: &str) -> Result<Vec<PathBuf>, Error> {
+        let target = &*self.expand(target)?;
+        let target = match target.strip_prefix('~') {
+            Some(bare) if self.tilde_prefix && !bare.starts_with('/') => bare,
+            _ => target,
+        };
+        if self.runtime.is_builtin(target) || target.starts_with('#') {
+            return Ok(Vec::new());
+        }
+        validate_specifier(target)?;
+
+        let mut candidates = Vec::new();
+        if is_path_specifier(target) {
+            let basedir = if target.starts_with('/') {
+                self.get_root()
+            } else {
+                self.get_basedir()?.to_path_buf()
+            };
+            let path = normalize(&basedir.join(target));
+            let as_directory = is_directory_specifier(target);
+            self.path_candidates(&path, as_directory, self.probe_order, &mut candidates);
+        } else {
+            let specifier = parse_specifier(target);
+            let package_name = specifier.package_name.unwrap_or(target);
+            let as_directory = is_directory_specifier(target)
+                || (self.fully_specified && specifier.subpath.is_none());
+            for (_, node_modules) in self.node_modules_dirs(self.get_basedir()?) {
+                candidates.push(node_modules.clone());
+                if self.use_package_json && specifier.subpath.is_some() {
+                    candidates.push(node_modules.join(package_name).join("package.json"));
+                }
+                let path = normalize(&node_modules.join(target));
+                let probe_order = self.package_probe_order(&specifier);
+                self.path_candidates(&path, as_directory, probe_order, &mut candidates);
+            }
+        }
+
+        let mut seen = HashSet::new();
+        candidates.retain(|path| seen.insert(path.clone()));
+        Ok(candidates.into_iter().map(|path| self.output_path(path)).collect())
+    }
+
+    /// Get the node_modules folders that bare specifiers are looked up in from the basedir,
+    /// nearest first, without touching the filesystem.
+    ///
+    /// Like `NODE_MODULES_PATHS` in the Node.js resolution algorithm, a folder that is itself
+    /// named `node_modules` does not get a nested `node_modules/node_modules` folder. This is
+    /// useful to debug why a package is or is not found.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let chain = Resolver::default()
+    ///     .with_basedir(PathBuf::from("/project/node_modules/dep"))
+    ///     .node_modules_chain()
+    ///     .unwrap();
+    /// assert_eq!(PathBuf::from("/project/node_modules/dep/node_modules"), chain[0]);
+    /// assert_eq!(PathBuf::from("/project/node_modules"), chain[1]);
+    /// ```
+    pub fn node_modules_chain(&self) -> Result<Vec<PathBuf>, Error> {
+        Ok(self
+            .node_modules_dirs(self.get_basedir()?)
+            .map(|(_, node_modules)| node_modules)
+            .collect())
+    }
+
+    /// Get the node_modules folders to search from `basedir`, nearest first, with the number
+    /// of directories that were ascended to reach each of them. Inside a
+    /// [workspace package](Resolver::workspace_packages), the search stops at its root. The
+    /// extra [module paths](Resolver::module_paths) and the `NODE_PATH` folders, if
+    /// [enabled](Resolver::node_path), come last, and do not count as ascending.
+    pub(crate) fn node_modules_dirs<'a>(
+        &'a self,
+        basedir: &'a Path,
+    ) -> impl Iterator<Item = (usize, PathBuf)> + 'a {
+        let workspace_root = self
+            .workspace_roots
+            .iter()
+            .filter(|root| basedir.starts_with(root))
+            .max_by_key(|root| root.components().count());
+        basedir
+            .ancestors()
+            .take_while(move |dir| match workspace_root {
+                Some(root) => dir.starts_with(root),
+                None => true,
+            })
+            .enumerate()
+            .flat_map(move |(ascent, dir)| {
+                self.module_directories_for(dir)
+                    .iter()
+                    .filter(move |name| dir.file_name() != Some(OsStr::new(name.as_str())))
+                    .map(move |name| (ascent, dir.join(name)))
+            })
+            .chain(self.module_paths.iter().map(|path| (0, path.clone())))
+            .chain(self.node_path_dirs().into_iter().map(|path| (0, path)))
+    }
+
+    /// Get the folders in `NODE_PATH`, if they should be searched.
+    fn node_path_dirs(&self) -> Vec<PathBuf> {
+        if self.node_path {
+            node_path(&*self.environment)
+        } else {
+            vec![]
+        }
+    }
+
+    /// Add the candidates for a path specifier to `candidates`.
+    fn path_candidates(
+        &self,
+        path: &Path,
+        as_directory: bool,
+        probe_order: ProbeOrder,
+        candidates: &mut Vec<PathBuf>,
+    ) {
+        candidates.push(path.to_path_buf());
+        let kinds: &[EntryKind] = match (as_directory, self.fully_specified) {
+            (true, _) => &[EntryKind::Directory],
+            (false, true) => return,
+            (false, false) => probe_order.kinds(),
+        };
+        for kind in kinds {
+            if *kind == EntryKind::File {
+                find_file(path, &self.extensions, self.partials, |candidate| {
+                    candidates.push(candidate.to_path_buf());
+                    false
+                });
+                continue;
+            }
+            if self.use_package_json {
+                candidates.push(path.join("package.json"));
+            }
+            find_index(path, &self.extensions, self.partials, |candidate| {
+                candidates.push(candidate.to_path_buf());
+                false
+            });
+        }
+    }
+}
+
+/// Get the directory of the package in a node_modules folder that contains `path`, like
+/// `/a/node_modules/@scope/pkg` for `/a/node_modules/@scope/pkg/lib/index.js`. Returns `None`
+/// if `path` is not inside a node_modules folder.
+pub(crate) fn node_modules_package(path: &Path) -> Option<PathBuf> {
+    let components: Vec<_> = path.components().collect();
+    let node_modules = components
+        .iter()
+        .rposition(|component| component.as_os_str() == "node_modules")?;
+    let name = components.get(node_modules + 1)?;
+    let len = if name.as_os_str().as_encoded_bytes().starts_with(b"@") {
+        node_modules + 3
+    } else {
+        node_modules + 2
+    };
+    if len > components.len() {
+        return None;
+    }
+    Some(components[..len].iter().collect())
+}
+
+/// Check if `name` matches `pattern`, where `*` matches any number of characters.
+pub(crate) fn matches_glob(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let mut rest = match name.strip_prefix(parts.next().unwrap_or("")) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    let (last, middle) = match parts.split_last() {
+        Some(split) => split,
+        // There is no `*`, so the name must match exactly.
+        None => return rest.is_empty(),
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Find the first file that `path` can refer to by adding each of the `extensions`, like
+/// `name.js` for `name`. With `partials`, stylesheet partials like `_name` and `_name.scss` are
+/// tried afterwards. `exists` is called with every candidate in order.
+pub(crate) fn find_file<F>(
+    path: &Path,
+    extensions: &[String],
+    partials: bool,
+    mut exists: F,
+) -> Option<PathBuf>
+where
+    F: FnMut(&Path) -> bool,
+{
+    let file_name = path.file_name()?;
+    // All candidates are built in the same buffer.
+    let mut candidate = path.to_path_buf();
+    for ext in extensions {
+        candidate.set_file_name(file_name);
+        candidate.as_mut_os_string().push(ext);
+        if exists(&candidate) {
+            return Some(candidate);
+        }
+    }
+
+    // Stylesheet partials: `name` can refer to `_name` or `_name.scss`.
+    if partials && !file_name.as_encoded_bytes().starts_with(b"_") {
+        for ext in iter::once("").chain(extensions.iter().map(String::as_str)) {
+            candidate.set_file_name("_");
+            candidate.as_mut_os_string().push(file_name);
+            candidate.as_mut_os_string().push(ext);
+            if exists(&candidate) {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+/// Find the first index file in the directory `dir`, trying each of the `extensions`. With
+/// `partials`, `_index` files are tried afterwards. `exists` is called with every candidate in
+/// order.
+pub(crate) fn find_index<F>(
+    dir: &Path,
+    extensions: &[String],
+    partials: bool,
+    mut exists: F,
+) -> Option<PathBuf>
+where
+    F: FnMut(&Path) -> bool,
+{
+    let index_names: &[&str] = if partials { &["index", "_index"] } else { &["index"] };
+    let mut candidate = dir.join("index");
+    for index_name in index_names {
+        for ext in extensions {
+            candidate.set_file_name(index_name);
+            candidate.as_mut_os_string().push(ext);
+            if exists(&candidate) {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{normalize, Resolver, SnapshotFileSystem};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    /// A small deterministic random number generator, so failures can be reproduced.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn below(&mut self, max: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % max as u64) as usize
+        }
+
+        /// Build a string from random pieces that are likely to confuse a resolver.
+        fn hostile_string(&mut self, pieces: &[&str]) -> String {
+            let len = self.below(10);
+            (0..len).map(|_| pieces[self.below(pieces.len())]).collect()
+        }
+    }
+
+    static PIECES: &[&str] = &[
+        "a", ".", "..", "/", "\\", "@", "#", "*", "~", "\0", ":", "%00", "$", "{", "}", "é",
+        "🦀", "\u{202e}", "node_modules", "dep", "lib", "x", "",
+    ];
+
+    #[test]
+    fn orders_candidates() {
+        let extensions = [String::from(".js"), String::from(".json")];
+        let mut tried = Vec::new();
+        let found = find_file(Path::new("/a/name"), &extensions, true, |candidate| {
+            tried.push(candidate.to_path_buf());
+            false
+        });
+        assert_eq!(None, found);
+        assert_eq!(
+            vec![
+                PathBuf::from("/a/name.js"),
+                PathBuf::from("/a/name.json"),
+                PathBuf::from("/a/_name"),
+                PathBuf::from("/a/_name.js"),
+                PathBuf::from("/a/_name.json"),
+            ],
+            tried
+        );
+
+        let found = find_index(Path::new("/a"), &extensions, false, |candidate| {
+            candidate.ends_with("index.json")
+        });
+        assert_eq!(Some(PathBuf::from("/a/index.json")), found);
+    }
+
+    #[test]
+    fn finds_node_modules_packages() {
+        let package = |path| node_modules_package(Path::new(path));
+        assert_eq!(None, package("/a/src/index.js"));
+        assert_eq!(None, package("/a/node_modules"));
+        assert_eq!(None, package("/a/node_modules/@scope"));
+        assert_eq!(
+            Some(PathBuf::from("/a/node_modules/pkg")),
+            package("/a/node_modules/pkg")
+        );
+        assert_eq!(
+            Some(PathBuf::from("/a/node_modules/@scope/pkg")),
+            package("/a/node_modules/@scope/pkg/lib/index.js")
+        );
+        assert_eq!(
+            Some(PathBuf::from("/a/node_modules/pkg/node_modules/dep")),
+            package("/a/node_modules/pkg/node_modules/dep/index.js")
+        );
+    }
+
+    #[test]
+    fn matches_globs() {
+        assert!(matches_glob("left-pad", "left-pad"));
+        assert!(!matches_glob("left-pad", "left-pad2"));
+        assert!(matches_glob("internal-*", "internal-"));
+        assert!(matches_glob("internal-*", "internal-tools"));
+        assert!(!matches_glob("internal-*", "not-internal-tools"));
+        assert!(matches_glob("@legacy/*", "@legacy/ui"));
+        assert!(matches_glob("*-polyfill", "array-polyfill"));
+        assert!(matches_glob("a*b*c", "abbc"));
+        assert!(!matches_glob("ab*b", "ab"));
+        assert!(matches_glob("*", "anything"));
+    }
+
+    #[test]
+    fn lists_candidates() {
+        let resolver = Resolver::default().with_basedir(PathBuf::from("/p/src"));
+        assert_eq!(
+            vec![
+                PathBuf::from("/p/src/a"),
+                PathBuf::from("/p/src/a.js"),
+                PathBuf::from("/p/src/a.json"),
+                PathBuf::from("/p/src/a.node"),
+                PathBuf::from("/p/src/a/package.json"),
+                PathBuf::from("/p/src/a/index.js"),
+                PathBuf::from("/p/src/a/index.json"),
+                PathBuf::from("/p/src/a/index.node"),
+            ],
+            resolver.candidates("./a").unwrap()
+        );
+        assert_eq!(
+            vec![
+                PathBuf::from("/p/lib"),
+                PathBuf::from("/p/lib/package.json"),
+                PathBuf::from("/p/lib/index.js"),
+                PathBuf::from("/p/lib/index.json"),
+                PathBuf::from("/p/lib/index.node"),
+            ],
+            resolver.candidates("../lib/").unwrap()
+        );
+        assert_eq!(
+            vec![PathBuf::from("/p/src/a.js")],
+            Resolver::node_esm()
+                .with_basedir(PathBuf::from("/p/src"))
+                .candidates("./a.js")
+                .unwrap()
+        );
+
+        let candidates = resolver.candidates("dep/sub").unwrap();
+        assert_eq!(30, candidates.len());
+        assert_eq!(PathBuf::from("/p/src/node_modules"), candidates[0]);
+        assert_eq!(
+            PathBuf::from("/p/src/node_modules/dep/package.json"),
+            candidates[1]
+        );
+        assert_eq!(PathBuf::from("/p/src/node_modules/dep/sub"), candidates[2]);
+        assert_eq!(PathBuf::from("/node_modules/dep/sub/index.node"), candidates[29]);
+
+        let chain = Resolver::default()
+            .with_basedir(PathBuf::from("/p/node_modules/dep/node_modules/inner/lib"))
+            .node_modules_chain()
+            .unwrap();
+        assert_eq!(
+            vec![
+                PathBuf::from("/p/node_modules/dep/node_modules/inner/lib/node_modules"),
+                PathBuf::from("/p/node_modules/dep/node_modules/inner/node_modules"),
+                PathBuf::from("/p/node_modules/dep/node_modules"),
+                PathBuf::from("/p/node_modules"),
+                PathBuf::from("/node_modules"),
+            ],
+            chain
+        );
+
+        assert!(resolver.candidates("fs").unwrap().is_empty());
+        assert!(resolver.candidates("#internal").unwrap().is_empty());
+        assert!(resolver.candidates("@scope").is_err());
+        assert!(Resolver::default().candidates("./a").is_err());
+    }
+
+    #[test]
+    fn survives_hostile_specifiers() {
+        let mut snapshot = SnapshotFileSystem::new();
+        snapshot.add_file(
+            "/p/node_modules/dep/package.json",
+            Some(r#"{ "exports": { "./*": "./lib/*.js", ".": { "node": ["./a.js", null] } } }"#),
+        );
+        snapshot.add_file("/p/node_modules/dep/lib/a.js", None::<String>);
+        snapshot.add_file(
+            "/p/package.json",
+            Some(r##"{ "imports": { "#a/*": "dep/*" }, "dependencies": { "l": "link:../../.." } }"##),
+        );
+        let snapshot = Arc::new(snapshot);
+        let resolvers: Vec<Resolver> = vec![
+            Resolver::default(),
+            Resolver::node_esm(),
+            Resolver::style(),
+            Resolver::ts_bundler(),
+            Resolver::default()
+                .folder_exports(true)
+                .link_dependencies(true)
+                .expand_env(true),
+        ]
+        .into_iter()
+        .map(|resolver| {
+            resolver
+                .filesystem(snapshot.clone())
+                .with_basedir(PathBuf::from("/p/src"))
+        })
+        .collect();
+
+        let mut rng = XorShift(0x5eed);
+        for round in 0..2000 {
+            let mut specifier = rng.hostile_string(PIECES);
+            if round % 100 == 0 {
+                specifier.insert_str(0, &"../".repeat(5000));
+            }
+            for resolver in &resolvers {
+                let _ = resolver.resolve(&specifier);
+            }
+            let _ = normalize(Path::new(&specifier));
+        }
+    }
+
+    #[test]
+    fn survives_hostile_exports() {
+        let resolver = Resolver::default().folder_exports(true);
+        let mut rng = XorShift(0xe4b0);
+        for _ in 0..2000 {
+            let mut exports = serde_json::Map::new();
+            for _ in 0..rng.below(4) {
+                let key = format!(".{}", rng.hostile_string(PIECES));
+                let target = match rng.below(4) {
+                    0 => Value::Null,
+                    1 => json!([format!("./{}", rng.hostile_string(PIECES)), null]),
+                    2 => json!({ "node": format!("./{}", rng.hostile_string(PIECES)) }),
+                    _ => Value::String(format!("./{}", rng.hostile_string(PIECES))),
+                };
+                exports.insert(key, target);
+            }
+            let subpath = format!(".{}", rng.hostile_string(PIECES));
+            let exports = Value::Object(exports);
+            if let Ok(path) = resolver.exports_target(Path::new("/pkg"), &exports, &subpath) {
+                // Targets never escape the package.
+                assert!(normalize(&path).starts_with("/pkg"), "{:?} -> {:?}", subpath, path);
+            }
+        }
+    }
+}
@@ -0,0 +1,552 @@
+//! Resolution results with details about how they were found.
+
+use crate::pure::node_modules_package;
+use crate::{io_error, normalize, Context, Error, Resolver, SymlinkStrategy};
+use std::collections::HashSet;
+use std::fmt;
+use std::io::ErrorKind as IOErrorKind;
+use std::path::{Path, PathBuf};
+
+/// The result of [`Resolver::resolve_full`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Resolution {
+    /// The resolved path. For builtin modules, this is the specifier.
+    pub path: PathBuf,
+    /// The resolved path with all symlinks expanded, which identifies the file no matter
+    /// which symlinked path it was reached through. This is the same as
+    /// [`path`](Resolution::path), unless symlinks are
+    /// [preserved](Resolver::preserve_symlinks). Bundlers can use it to include a module only
+    /// once, while still emitting the preserved path.
+    pub canonical_path: PathBuf,
+    /// Problems that were found along the way, but did not prevent resolution.
+    pub warnings: Vec<Warning>,
+    /// The files and directories whose existence or contents the result depends on, in the
+    /// order they were checked. This includes paths that were checked but did not exist, so a
+    /// watcher can redo the resolution when any of them is created, removed, or changed.
+    pub watched: Vec<PathBuf>,
+    /// The name of the package in a node_modules folder that contains the resolved path, like
+    /// `@scope/pkg`. This is `None` for files outside node_modules folders, like the files of
+    /// the project or of linked workspace packages, and for builtin modules.
+    pub package_name: Option<String>,
+    /// The package.json file of the package that contains the resolved path. Inside a
+    /// node_modules folder, this is the one at the root of the package, and elsewhere the
+    /// nearest one. This is `None` for builtin modules, and if there is no such file or
+    /// package.json files are [not used](Resolver::use_package_json).
+    pub package_json: Option<PathBuf>,
+    /// The "version" field of the [package.json file](Resolution::package_json), if it has one.
+    pub package_version: Option<String>,
+    /// Whether the resolved path is a .json file that ES modules must import with a
+    /// `with { type: 'json' }` attribute. This is only set if
+    /// [JSON modules](Resolver::json_modules) are enabled.
+    pub needs_json_module: bool,
+    /// Mismatches between the module system of the importer and of the resolved file, which
+    /// may need interop code. The importer is assumed to be an ES module if the resolver uses
+    /// the "import" condition, and a CommonJS module if it uses the "require" condition.
+    pub interop: Vec<InteropHint>,
+}
+
+impl Resolution {
+    /// Check if the resolved file is inside a node_modules folder, so it belongs to an
+    /// installed dependency. Bundlers can use this to put it in a vendor chunk.
+    pub fn is_external_dependency(&self) -> bool {
+        self.package_name.is_some()
+    }
+
+    /// Get the resolved path with `/` separators on every platform, like
+    /// `C:/project/index.js` on Windows, for source maps and generated import statements. The
+    /// [`path`](Resolution::path) itself is not changed. Builtin modules are returned as they
+    /// are, and characters that are not valid Unicode are replaced.
+    pub fn to_slash_string(&self) -> String {
+        let path = self.path.to_string_lossy();
+        if cfg!(windows) {
+            path.replace('\\', "/")
+        } else {
+            path.into_owned()
+        }
+    }
+}
+
+/// A problem that did not prevent resolution, but that a tool may want to report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// A package.json file could not be parsed, so it was ignored.
+    InvalidPackageJson {
+        /// The path to the package.json file.
+        path: PathBuf,
+        /// Why the file could not be parsed.
+        message: String,
+    },
+    /// The main field of a package.json file points to a file that does not exist, so the
+    /// index file of the package was used instead. Node.js deprecated this fallback.
+    MainNotFound {
+        /// The path to the package.json file.
+        path: PathBuf,
+        /// The value of the main field.
+        main: String,
+    },
+    /// A package.json file uses a folder mapping ending in `/` in its "exports" or "imports"
+    /// field. Node.js removed support for these in favour of subpath patterns.
+    FolderMapping {
+        /// The path to the package.json file.
+        path: PathBuf,
+        /// The key of the folder mapping.
+        key: String,
+    },
+    /// A path could not be checked because of a missing permission, so it was treated as
+    /// missing. See [`Resolver::strict_permissions`].
+    PermissionDenied {
+        /// The path that could not be checked.
+        path: PathBuf,
+        /// The error from the filesystem.
+        message: String,
+    },
+}
+
+/// A mismatch between the module systems of the importer and of the resolved file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InteropHint {
+    /// An ES module was resolved for `require()`: a .mjs file, or a .js file in a package with
+    /// `"type": "module"`. Bundlers need to wrap it, and Node.js versions before 22 can not
+    /// load it at all.
+    EsmFromRequire,
+    /// A CommonJS module was resolved for `import`: a .cjs file, or a .js file in a package
+    /// without `"type": "module"`. Its named exports may need to be detected or wrapped.
+    CjsFromImport,
+    /// The entry point of a package was found through a main field for ES modules, like
+    /// "module", but the importer is a CommonJS module. The package may have a "main" field
+    /// with a CommonJS build that suits the importer better.
+    ModuleFieldFromRequire {
+        /// The path to the package.json file.
+        path: PathBuf,
+        /// The main field that was used.
+        field: String,
+    },
+}
+
+impl fmt::Display for InteropHint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InteropHint::EsmFromRequire => write!(f, "an ES module was resolved for require()"),
+            InteropHint::CjsFromImport => write!(f, "a CommonJS module was resolved for import"),
+            InteropHint::ModuleFieldFromRequire { path, field } => write!(
+                f,
+                "resolved through the \"{}\" field in {}, but the importer is CommonJS",
+                field,
+                path.display()
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::InvalidPackageJson { path, message } => {
+                write!(f, "ignoring invalid {}: {}", path.display(), message)
+            }
+            Warning::MainNotFound { path, main } => write!(
+                f,
+                "main field \"{}\" in {} does not exist, using the index file instead",
+                main,
+                path.display()
+            ),
+            Warning::FolderMapping { path, key } => write!(
+                f,
+                "folder mapping \"{}\" in {} is deprecated, use a \"{}*\" pattern instead",
+                key,
+                path.display(),
+                key
+            ),
+            Warning::PermissionDenied { path, message } => {
+                write!(f, "treating {} as missing: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl Resolver {
+    /// Resolve a `require('target')` argument, and report problems that did not prevent
+    /// resolution.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolution = Resolver::default()
+    ///     .with_basedir(PathBuf::from("./fixtures"))
+    ///     .resolve_full("./package-json/invalid")
+    ///     .unwrap();
+    /// for warning in &resolution.warnings {
+    ///     eprintln!("warning: {}", warning);
+    /// }
+    /// ```
+    pub fn resolve_full(&self, target: &str) -> Result<Resolution, Error> {
+        let mut ctx = Context::default();
+        let path = self.resolve_with_context(target, &mut ctx)?;
+        let canonical_path = self.canonical_path(&path, &mut ctx);
+        let interop = self.interop_hints(&path, &mut ctx);
+        let package_json = self.owning_package_json(&path, &mut ctx);
+        let package_version = match &package_json {
+            Some(pkg_path) => match self.load_package_json(pkg_path, &mut ctx) {
+                Ok(pkg) => pkg["version"].as_str().map(String::from),
+                Err(err @ Error::TransientIOError { .. }) => return Err(err),
+                Err(_) => None,
+            },
+            None => None,
+        };
+        // The lookups after resolution can run out of budget or into filesystem errors too.
+        ctx.check_aborted(target)?;
+        let mut seen = HashSet::new();
+        let watched = ctx
+            .checked
+            .iter()
+            .map(|(path, _)| normalize(path))
+            .filter(|path| seen.insert(path.clone()))
+            .map(|path| self.output_path(path))
+            .collect();
+        let package_name = node_modules_package(&path).and_then(|pkg_dir| package_name(&pkg_dir));
+        let needs_json_module =
+            self.json_modules && path.extension().is_some_and(|extension| extension == "json");
+        Ok(Resolution {
+            path: self.output_path(path),
+            canonical_path: self.output_path(canonical_path),
+            warnings: ctx.warnings,
+            watched,
+            package_name,
+            package_json: package_json.map(|pkg_path| self.output_path(pkg_path)),
+            package_version,
+            needs_json_module,
+            interop,
+        })
+    }
+
+    /// Get the resolved path with symlinks expanded, if they were preserved. Errors are
+    /// reported like the errors of the resolution itself.
+    fn canonical_path(&self, path: &Path, ctx: &mut Context) -> PathBuf {
+        if self.symlinks != SymlinkStrategy::Preserve || !path.is_absolute() {
+            return path.to_path_buf();
+        }
+        match self.canonicalize(path) {
+            Ok(real) => real,
+            Err(error) => {
+                if error.kind() == IOErrorKind::PermissionDenied {
+                    self.permission_denied(path, &error, ctx);
+                } else {
+                    ctx.io_error.get_or_insert_with(|| io_error(path, error));
+                }
+                path.to_path_buf()
+            }
+        }
+    }
+
+    /// Find the package.json file of the package that contains the file at `path`.
+    fn owning_package_json(&self, path: &Path, ctx: &mut Context) -> Option<PathBuf> {
+        if !path.is_absolute() || !self.use_package_json {
+            return None;
+        }
+        match node_modules_package(path) {
+            // Nested package.json files inside the package only redirect entry points.
+            Some(pkg_dir) => {
+                let pkg_path = pkg_dir.join("package.json");
+                Some(pkg_path).filter(|pkg_path| self.probe_file(pkg_path, ctx))
+            }
+            None => self.find_package_scope(path.parent()?, ctx),
+        }
+    }
+
+    /// Find mismatches between the module systems of the importer, which follows from the
+    /// conditions, and of the file at `path`.
+    fn interop_hints(&self, path: &Path, ctx: &mut Context) -> Vec<InteropHint> {
+        let has_condition = |name: &str| self.conditions.iter().any(|condition| condition == name);
+        let importer_is_esm = if has_condition("import") {
+            true
+        } else if has_condition("require") {
+            false
+        } else {
+            return vec![];
+        };
+
+        let mut hints = vec![];
+        if !importer_is_esm {
+            for (pkg_path, field) in &ctx.main_fields {
+                if ESM_MAIN_FIELDS.contains(&field.as_str()) {
+                    hints.push(InteropHint::ModuleFieldFromRequire {
+                        path: pkg_path.clone(),
+                        field: field.clone(),
+                    });
+                }
+            }
+        }
+        match (self.is_esm_file(path, ctx), importer_is_esm) {
+            (Some(true), false) => hints.push(InteropHint::EsmFromRequire),
+            (Some(false), true) => hints.push(InteropHint::CjsFromImport),
+            _ => (),
+        }
+        hints
+    }
+
+    /// Check if the file at `path` is an ES module, or `None` if it is not JavaScript.
+    fn is_esm_file(&self, path: &Path, ctx: &mut Context) -> Option<bool> {
+        match path.extension()?.to_str()? {
+            "mjs" => Some(true),
+            "cjs" => Some(false),
+            "js" => {
+                let is_module = self
+                    .find_package_scope(path.parent()?, ctx)
+                    .and_then(|pkg_path| self.load_package_json(&pkg_path, ctx).ok())
+                    .is_some_and(|pkg| pkg["type"] == "module");
+                Some(is_module)
+            }
+            _ => None,
+        }
+    }
+
+    /// Resolve a `require('target')` argument, using the resolver for the scope of the basedir.
+    pub(crate) fn resolve_with_context(
+        &self,
+        target: &str,
+        ctx: &mut Context,
+    ) -> Result<PathBuf, Error> {
+        if !self.fallback_basedirs.is_empty() {
+            return self.resolve_from_basedirs(target, ctx);
+        }
+        if let Some(scoped) = self.scoped_resolver() {
+            return scoped.resolve_with_context(target, ctx);
+        }
+
+        ctx.budget.start(&self.budget);
+        let result = self.timed(target, || self.resolve_inner(target, ctx));
+        ctx.check_aborted(target)?;
+        result
+    }
+
+    /// Resolve `target` from each basedir in turn, until it is found.
+    fn resolve_from_basedirs(&self, target: &str, ctx: &mut Context) -> Result<PathBuf, Error> {
+        let basedirs = self.basedir.iter().chain(self.fallback_basedirs.iter());
+        let mut result = Err(Error::UnconfiguredBasedir);
+        for basedir in basedirs {
+            result = self
+                .with_basedir(basedir.clone())
+                .resolve_with_context(target, ctx);
+            if !matches!(result, Err(Error::NotFound { .. })) {
+                return result;
+            }
+        }
+
+        // The context is shared, so the last error lists the paths tried from every basedir.
+        match result {
+            Err(Error::NotFound { specifier, tried, .. }) => Err(Error::NotFound {
+                specifier,
+                basedir: self.get_basedir()?.to_path_buf(),
+                tried,
+            }),
+            result => result,
+        }
+    }
+}
+
+/// Main fields that point to builds for ES modules.
+const ESM_MAIN_FIELDS: &[&str] = &["module", "jsnext:main"];
+
+/// Get the name of the package in the node_modules folder `pkg_dir`, like `@scope/pkg` for
+/// `/a/node_modules/@scope/pkg`.
+pub(crate) fn package_name(pkg_dir: &Path) -> Option<String> {
+    let name = pkg_dir.file_name()?.to_str()?;
+    match pkg_dir.parent()?.file_name()?.to_str()? {
+        scope if scope.starts_with('@') => Some(format!("{}/{}", scope, name)),
+        _ => Some(name.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Budget, Limit};
+    use std::env;
+
+    #[test]
+    fn reports_warnings() {
+        let fixtures = env::current_dir().unwrap().join("fixtures/package-json");
+        let resolver = Resolver::default().with_basedir(fixtures.clone());
+
+        let resolution = resolver.resolve_full("./main-file").unwrap();
+        assert_eq!(fixtures.join("main-file/whatever.js"), resolution.path);
+        assert!(resolution.warnings.is_empty());
+
+        let resolution = resolver.resolve_full("./invalid").unwrap();
+        assert_eq!(fixtures.join("invalid/index.js"), resolution.path);
+        assert!(matches!(
+            resolution.warnings.as_slice(),
+            [Warning::InvalidPackageJson { path, .. }]
+                if *path == fixtures.join("invalid/package.json")
+        ));
+
+        let resolution = resolver.resolve_full("./main-file").unwrap();
+        assert_eq!(
+            vec![
+                fixtures.join("main-file"),
+                fixtures.join("main-file.js"),
+                fixtures.join("main-file.json"),
+                fixtures.join("main-file.node"),
+                fixtures.join("main-file/package.json"),
+                fixtures.join("main-file/whatever.js"),
+            ],
+            resolution.watched
+        );
+
+        assert!(!resolution.is_external_dependency());
+
+        let resolution = resolver.resolve_full("./main-missing").unwrap();
+        assert_eq!(fixtures.join("main-missing/index.js"), resolution.path);
+        assert_eq!(
+            vec![Warning::MainNotFound {
+                path: fixtures.join("main-missing/package.json"),
+                main: String::from("./missing.js"),
+            }],
+            resolution.warnings
+        );
+    }
+
+    #[test]
+    fn reports_external_dependencies() {
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let resolver = Resolver::default().with_basedir(fixtures.join("nested-manifest"));
+        let resolution = resolver.resolve_full("@scope/pkg/dir").unwrap();
+        assert!(resolution.is_external_dependency());
+        assert_eq!(Some("@scope/pkg"), resolution.package_name.as_deref());
+
+        let resolver = resolver.with_basedir(fixtures.join("node-modules/package-json"));
+        let resolution = resolver.resolve_full("dep").unwrap();
+        assert_eq!(Some("dep"), resolution.package_name.as_deref());
+        let resolution = resolver.resolve_full("./node_modules/dep/lib").unwrap();
+        assert_eq!(Some("dep"), resolution.package_name.as_deref());
+
+        let resolution = resolver.resolve_full("events").unwrap();
+        assert!(!resolution.is_external_dependency());
+    }
+
+    #[test]
+    fn reports_interop_hints() {
+        let fixtures = env::current_dir().unwrap().join("fixtures/package-type");
+        let cjs = Resolver::default().with_basedir(fixtures.clone());
+        let esm = Resolver::node_esm().with_basedir(fixtures.clone());
+
+        assert!(cjs.resolve_full("./commonjs/file.js").unwrap().interop.is_empty());
+        assert_eq!(
+            vec![InteropHint::EsmFromRequire],
+            cjs.resolve_full("./module/file.js").unwrap().interop
+        );
+        assert_eq!(
+            vec![InteropHint::EsmFromRequire],
+            cjs.resolve_full("./module/file.mjs").unwrap().interop
+        );
+        assert!(esm.resolve_full("./module/file.js").unwrap().interop.is_empty());
+        assert_eq!(
+            vec![InteropHint::CjsFromImport],
+            esm.resolve_full("./commonjs/file.cjs").unwrap().interop
+        );
+        assert!(cjs.resolve_full("fs").unwrap().interop.is_empty());
+
+        assert!(cjs.resolve_full("dual").unwrap().interop.is_empty());
+        let resolution = cjs.main_fields(&["module", "main"]).resolve_full("dual").unwrap();
+        assert_eq!(fixtures.join("node_modules/dual/module.mjs"), resolution.path);
+        assert_eq!(
+            vec![
+                InteropHint::ModuleFieldFromRequire {
+                    path: fixtures.join("node_modules/dual/package.json"),
+                    field: String::from("module"),
+                },
+                InteropHint::EsmFromRequire,
+            ],
+            resolution.interop
+        );
+    }
+
+    #[test]
+    fn marks_json_modules() {
+        let fixtures = env::current_dir().unwrap().join("fixtures/extensions");
+        let esm = Resolver::node_esm().with_basedir(fixtures.clone());
+        assert!(esm.resolve_full("./json-file.json").unwrap().needs_json_module);
+        assert!(!esm.resolve_full("./js-file.js").unwrap().needs_json_module);
+        assert!(!esm.resolve_full("fs").unwrap().needs_json_module);
+
+        let cjs = Resolver::default().with_basedir(fixtures);
+        assert!(!cjs.resolve_full("./json-file.json").unwrap().needs_json_module);
+    }
+
+    #[test]
+    fn reports_package_versions() {
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let scan = Resolver::default().with_basedir(fixtures.join("scan/src"));
+        let react = scan.resolve_full("react/jsx-runtime").unwrap();
+        assert_eq!(Some(fixtures.join("scan/node_modules/react/package.json")), react.package_json);
+        assert_eq!(Some("18.2.0"), react.package_version.as_deref());
+
+        let local = scan.resolve_full("../packages/local").unwrap();
+        assert_eq!(Some(fixtures.join("scan/packages/local/package.json")), local.package_json);
+        assert_eq!(None, local.package_version);
+
+        let nested = Resolver::default()
+            .with_basedir(fixtures.join("nested-manifest"))
+            .resolve_full("@scope/pkg/dir")
+            .unwrap();
+        assert_eq!(
+            Some(fixtures.join("nested-manifest/node_modules/@scope/pkg/package.json")),
+            nested.package_json
+        );
+
+        assert_eq!(None, scan.resolve_full("fs").unwrap().package_json);
+    }
+
+    #[test]
+    fn reports_canonical_paths() {
+        let fixtures = env::current_dir().unwrap().join("fixtures/symlink");
+        let resolver = Resolver::default().with_basedir(fixtures.clone());
+        let real = resolver.resolve_full("dep").unwrap();
+        assert_eq!(fixtures.join("linked/main.js"), real.path);
+        assert_eq!(real.path, real.canonical_path);
+
+        let preserving = resolver.preserve_symlinks(true);
+        let preserved = preserving.resolve_full("dep").unwrap();
+        assert_eq!(fixtures.join("node_modules/dep/main.js"), preserved.path);
+        assert_eq!(real.path, preserved.canonical_path);
+        assert_eq!(PathBuf::from("fs"), preserving.resolve_full("fs").unwrap().canonical_path);
+    }
+
+    #[test]
+    fn budgets_package_lookups() {
+        let fixtures = env::current_dir().unwrap().join("fixtures/scan/src");
+        let resolver = Resolver::default().with_basedir(fixtures);
+        // Resolving react reads package.json files twice, and the details of the result need
+        // two more reads, for the module type and the version.
+        let budget = |reads| Budget::default().max_package_json_reads(reads);
+        assert!(resolver.clone().budget(budget(3)).resolve("react").is_ok());
+        assert_eq!(
+            Err(Error::BudgetExceeded(Limit::PackageJsonReads)),
+            resolver.clone().budget(budget(3)).resolve_full("react")
+        );
+        assert!(resolver.budget(budget(4)).resolve_full("react").is_ok());
+    }
+
+    #[test]
+    fn formats_slash_strings() {
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let resolver = Resolver::default().with_basedir(fixtures.join("extensions"));
+        let resolution = resolver.resolve_full("./js-file").unwrap();
+        let slash_string = resolution.to_slash_string();
+        assert!(slash_string.ends_with("/fixtures/extensions/js-file.js"));
+        assert!(!slash_string.contains('\\'));
+        assert_eq!(fixtures.join("extensions/js-file.js"), resolution.path);
+        assert_eq!("fs", resolver.resolve_full("fs").unwrap().to_slash_string());
+
+        let relative = resolver.output_relative_to(fixtures);
+        assert_eq!(
+            "extensions/js-file.js",
+            relative.resolve_full("./js-file").unwrap().to_slash_string()
+        );
+    }
+}
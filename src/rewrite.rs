@@ -0,0 +1,75 @@
+//! Rewriting specifiers with regular expressions before they are resolved.
+
+use crate::Resolver;
+use regex::Regex;
+use std::borrow::Cow;
+
+impl Resolver {
+    /// Replace specifiers that match `pattern` with `replacement` before resolving them, like
+    /// Jest's `moduleNameMapper` option. Consumes the Resolver instance. There are no rewrite
+    /// rules by default.
+    ///
+    /// The whole specifier is replaced, not only the part that matched. `$1` or `${name}` in
+    /// the replacement insert the text that a group captured. Rules are tried in the order
+    /// they were added, and only the first rule that matches is applied. The new specifier is
+    /// resolved from the same basedir.
+    ///
+    /// This requires the `regex` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    /// use regex::Regex;
+    ///
+    /// let resolver = Resolver::default()
+    ///     .rewrite(Regex::new(r"\.(css|svg)$").unwrap(), "/project/test/stub.js")
+    ///     .rewrite(Regex::new(r"^@/(.*)$").unwrap(), "/project/src/$1")
+    ///     .with_basedir(PathBuf::from("/project/src"));
+    /// resolver.resolve("./styles.css");
+    /// resolver.resolve("@/components/button");
+    /// ```
+    pub fn rewrite<S: ToString>(self, pattern: Regex, replacement: S) -> Self {
+        self.configure(|builder| {
+            builder.rewrite(pattern, replacement);
+        })
+    }
+
+    /// Apply the first rewrite rule that matches `target`.
+    pub(crate) fn rewrite_specifier<'a>(&self, target: Cow<'a, str>) -> Cow<'a, str> {
+        for (pattern, replacement) in self.rewrites.iter() {
+            if let Some(captures) = pattern.captures(&target) {
+                let mut rewritten = String::new();
+                captures.expand(replacement, &mut rewritten);
+                return Cow::Owned(rewritten);
+            }
+        }
+        target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Resolver;
+    use regex::Regex;
+    use std::env;
+
+    #[test]
+    fn rewrites_specifiers() {
+        let root = env::current_dir().unwrap().join("fixtures/rewrite");
+        let resolver = Resolver::default()
+            .rewrite(Regex::new(r"\.(css|svg)$").unwrap(), "./stub")
+            .rewrite(Regex::new(r"^@/(.*)$").unwrap(), "./src/$1")
+            .rewrite(Regex::new(r"^@/.*$").unwrap(), "./never")
+            .with_basedir(root.clone());
+
+        assert_eq!(Ok(root.join("stub.js")), resolver.resolve("./styles.css"));
+        assert_eq!(Ok(root.join("stub.js")), resolver.resolve("pkg/logo.svg"));
+        assert_eq!(Ok(root.join("src/util.js")), resolver.resolve("@/util"));
+        assert_eq!(Ok(root.join("src/util.js")), resolver.resolve("./src/util"));
+        assert_eq!(
+            vec![root.join("src/util"), root.join("src/util.js")],
+            resolver.candidates("@/util").unwrap()[..2]
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Builtin modules of the JavaScript runtimes the resolver can target.
+
+use crate::is_core_module;
+
+/// Modules that Electron provides on top of the Node.js builtins.
+static ELECTRON_BUILTINS: &[&str] = &["electron", "original-fs"];
+
+/// Modules that Bun provides on top of the Node.js builtins.
+static BUN_BUILTINS: &[&str] = &[
+    "bun",
+    "bun:ffi",
+    "bun:jsc",
+    "bun:sqlite",
+    "bun:test",
+    "bun:wrap",
+];
+
+/// The JavaScript runtime that resolved modules will run in. This determines which specifiers
+/// refer to builtin modules instead of files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Runtime {
+    /// Node.js. This is the default.
+    #[default]
+    Node,
+    /// Electron, which adds modules like `electron` to the Node.js builtins.
+    Electron,
+    /// Bun, which adds `bun` and `bun:*` modules to the Node.js builtins.
+    Bun,
+    /// Deno, which only provides the Node.js builtins with the `node:` prefix.
+    Deno,
+    /// Browsers, which do not have builtin modules.
+    Browser,
+}
+
+impl Runtime {
+    /// Check if a specifier refers to a builtin module of this runtime. Node.js builtins can be
+    /// prefixed with `node:` in all runtimes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Runtime;
+    ///
+    /// assert!(Runtime::Node.is_builtin("node:events"));
+    /// assert!(Runtime::Electron.is_builtin("electron"));
+    /// assert!(!Runtime::Node.is_builtin("electron"));
+    /// ```
+    pub fn is_builtin(self, specifier: &str) -> bool {
+        match self {
+            Runtime::Browser => return false,
+            Runtime::Deno => {
+                return specifier.strip_prefix("node:").is_some_and(is_core_module);
+            }
+            _ => {}
+        }
+        if is_core_module(specifier) {
+            return true;
+        }
+        if let Some(name) = specifier.strip_prefix("node:") {
+            return is_core_module(name);
+        }
+
+        let extra = match self {
+            Runtime::Node => return false,
+            Runtime::Electron => ELECTRON_BUILTINS,
+            Runtime::Bun => BUN_BUILTINS,
+            Runtime::Deno | Runtime::Browser => return false,
+        };
+        extra.contains(&specifier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resolver;
+
+    #[test]
+    fn runtime_builtins() {
+        assert!(Runtime::Node.is_builtin("events"));
+        assert!(Runtime::Node.is_builtin("node:events"));
+        assert!(!Runtime::Node.is_builtin("node:acorn"));
+        assert!(!Runtime::Node.is_builtin("bun:sqlite"));
+        assert!(Runtime::Bun.is_builtin("bun:sqlite"));
+        assert!(Runtime::Bun.is_builtin("events"));
+        assert!(Runtime::Electron.is_builtin("electron"));
+        assert!(Runtime::Deno.is_builtin("node:events"));
+        assert!(!Runtime::Deno.is_builtin("events"));
+        assert!(!Runtime::Browser.is_builtin("node:events"));
+
+        let resolver = Resolver::default().with_basedir(".".into());
+        assert!(resolver.resolve("electron").is_err());
+        assert_eq!(
+            "electron",
+            resolver
+                .clone()
+                .target_runtime(Runtime::Electron)
+                .resolve("electron")
+                .unwrap()
+                .to_str()
+                .unwrap()
+        );
+        assert_eq!(
+            "node:events",
+            resolver.resolve("node:events").unwrap().to_str().unwrap()
+        );
+    }
+}
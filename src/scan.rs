@@ -0,0 +1,214 @@
+//! Scanning source files for the packages they import, like dev servers do before they
+//! pre-bundle dependencies.
+
+use crate::pure::node_modules_package;
+use crate::{io_error, is_path_specifier, Access, Context, Error, Resolver};
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+/// The packages that were found by [`Resolver::scan_imports`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportScan {
+    /// The bare specifiers that were imported, like `react` or `lodash/fp`, and the directory
+    /// of the package in node_modules that each of them resolved to.
+    pub imports: BTreeMap<String, PathBuf>,
+    /// The specifiers that could not be resolved, and the first file that imported each of
+    /// them.
+    pub missing: BTreeMap<String, PathBuf>,
+    /// The source files that were scanned, in the order they were scanned.
+    pub scanned: Vec<PathBuf>,
+    /// The files that each scanned file imports, by specifier. Builtin modules and specifiers
+    /// that could not be resolved are left out. See [`ImportScan::to_dot`] and
+    /// [`ImportScan::to_json`] to export it.
+    pub graph: BTreeMap<PathBuf, BTreeMap<String, PathBuf>>,
+    /// The "version" field of each imported package, by package directory.
+    pub versions: BTreeMap<PathBuf, String>,
+}
+
+impl ImportScan {
+    /// Get the directories of the imported packages, without duplicates.
+    pub fn package_roots(&self) -> BTreeSet<&Path> {
+        self.imports.values().map(PathBuf::as_path).collect()
+    }
+}
+
+impl Resolver {
+    /// Find the packages that are imported by `entries` and the source files they import.
+    ///
+    /// `extract` receives the path and contents of a source file, and returns the specifiers
+    /// that it imports. The crate does not parse JavaScript itself. Each specifier is resolved
+    /// from the directory of the file that imports it. Files in node_modules folders are not
+    /// scanned: the bare specifiers that resolve to them are collected instead, with their
+    /// package directories. Other files are scanned in turn, including packages that are
+    /// linked from outside node_modules, like workspace packages. Only files that have one of
+    /// the resolver's extensions are scanned, so imports of stylesheets or images are skipped.
+    /// Builtin modules are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let scan = Resolver::default().scan_imports(
+    ///     vec![PathBuf::from("/project/src/main.js")],
+    ///     |_path, source| extract_imports(source),
+    /// )?;
+    /// for package in scan.package_roots() {
+    ///     println!("pre-bundling {}", package.display());
+    /// }
+    /// ```
+    pub fn scan_imports<I, F>(&self, entries: I, mut extract: F) -> Result<ImportScan, Error>
+    where
+        I: IntoIterator<Item = PathBuf>,
+        F: FnMut(&Path, &str) -> Vec<String>,
+    {
+        let mut scan = ImportScan::default();
+        let mut queue: VecDeque<PathBuf> = entries.into_iter().collect();
+        let mut seen: HashSet<PathBuf> = queue.iter().cloned().collect();
+
+        while let Some(file) = queue.pop_front() {
+            self.observe(&file, Access::Read);
+            let source = self
+                .retry_io(|| self.fs.read_to_string(&file))
+                .map_err(|err| io_error(&file, err))?;
+            let basedir = file.parent().unwrap_or(&file).to_path_buf();
+            let resolver = self.with_basedir(basedir);
+            let mut edges = BTreeMap::new();
+            for specifier in extract(&file, &source) {
+                if self.runtime.is_builtin(&specifier) {
+                    continue;
+                }
+                let resolved = resolver.resolve_with_context(&specifier, &mut Context::default());
+                let resolved = match resolved {
+                    Ok(resolved) => resolved,
+                    Err(_) => {
+                        scan.missing.entry(specifier).or_insert_with(|| file.clone());
+                        continue;
+                    }
+                };
+
+                edges.insert(specifier.clone(), resolved.clone());
+                if let Some(package_root) = node_modules_package(&resolved) {
+                    if !scan.versions.contains_key(&package_root) {
+                        if let Some(version) = self.package_version(&package_root) {
+                            scan.versions.insert(package_root.clone(), version);
+                        }
+                    }
+                    if !is_path_specifier(&specifier) && !specifier.starts_with('#') {
+                        scan.imports.entry(specifier).or_insert(package_root);
+                    }
+                } else if self.is_source_file(&resolved) && seen.insert(resolved.clone()) {
+                    queue.push_back(resolved);
+                }
+            }
+            scan.graph.insert(file.clone(), edges);
+            scan.scanned.push(file);
+        }
+
+        Ok(scan)
+    }
+
+    /// Read the "version" field of the package in `pkg_dir`.
+    fn package_version(&self, pkg_dir: &Path) -> Option<String> {
+        let pkg = self
+            .load_package_json(&pkg_dir.join("package.json"), &mut Context::default())
+            .ok()?;
+        pkg.get("version")?.as_str().map(String::from)
+    }
+
+    /// Check if a file has one of the resolver's extensions.
+    fn is_source_file(&self, path: &Path) -> bool {
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+        self.extensions.iter().any(|extension| name.ends_with(extension.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Resolver;
+    use serde_json::json;
+    use std::env;
+
+    /// Find the specifiers of `import ... from '...'` lines, which is enough for the fixtures.
+    fn extract_imports(source: &str) -> Vec<String> {
+        source
+            .lines()
+            .filter_map(|line| line.split(" from '").nth(1))
+            .map(|rest| rest.trim_end_matches("';").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn scans_imports() {
+        let root = env::current_dir().unwrap().join("fixtures/scan");
+        let scan = Resolver::default()
+            .scan_imports(vec![root.join("src/main.js")], |_, source| {
+                extract_imports(source)
+            })
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                root.join("src/main.js"),
+                root.join("src/util.js"),
+                root.join("packages/local/index.js"),
+            ],
+            scan.scanned
+        );
+        let imports: Vec<_> = scan.imports.iter().map(|(k, v)| (k.as_str(), v)).collect();
+        assert_eq!(
+            vec![
+                ("@scope/ui", &root.join("node_modules/@scope/ui")),
+                ("react", &root.join("node_modules/react")),
+                ("react/jsx-runtime", &root.join("node_modules/react")),
+            ],
+            imports
+        );
+        assert_eq!(2, scan.package_roots().len());
+        assert_eq!(Some(&root.join("src/util.js")), scan.missing.get("missing-package"));
+    }
+
+    #[test]
+    fn exports_import_graphs() {
+        let root = env::current_dir().unwrap().join("fixtures/scan");
+        let path = |part: &str| root.join(part).display().to_string();
+        let scan = Resolver::default()
+            .scan_imports(vec![root.join("src/main.js")], |_, source| {
+                extract_imports(source)
+            })
+            .unwrap();
+
+        let graph = scan.to_json();
+        assert_eq!(
+            json!({
+                "react": path("node_modules/react/index.js"),
+                "./util": path("src/util.js"),
+                "local": path("packages/local/index.js"),
+                "./styles.css": path("src/styles.css"),
+            }),
+            graph[path("src/main.js")]["imports"]
+        );
+        assert_eq!(
+            json!({ "package": "react", "version": "18.2.0" }),
+            graph[path("node_modules/react/index.js")]
+        );
+        assert_eq!(
+            json!({ "package": "@scope/ui", "version": "1.0.0" }),
+            graph[path("node_modules/@scope/ui/index.js")]
+        );
+        assert!(graph[path("src/util.js")]["imports"].get("missing-package").is_none());
+        assert!(graph[path("src/util.js")]["imports"].get("fs").is_none());
+
+        let dot = scan.to_dot();
+        assert!(dot.starts_with("digraph modules {\n"));
+        assert!(dot.contains(&format!(
+            "  \"{}\" [label=\"@scope/ui@1.0.0 index.js\", shape=box];\n",
+            path("node_modules/@scope/ui/index.js")
+        )));
+        assert!(dot.contains(&format!(
+            "  \"{}\" -> \"{}\" [label=\"./util\"];\n",
+            path("src/main.js"),
+            path("src/util.js")
+        )));
+    }
+}
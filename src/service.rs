@@ -0,0 +1,274 @@
+//! A pool of threads that resolve specifiers in the background.
+
+use crate::{CompiledResolver, Error, Resolver};
+use std::future::Future;
+use std::io::{Error as IOError, ErrorKind as IOErrorKind};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+/// Resolves specifiers on a fixed number of worker threads.
+///
+/// [`ResolverService::submit`] queues a resolution and returns right away. The result can be
+/// waited for with [`PendingResolution::wait`], or awaited, because [`PendingResolution`] is a
+/// future. This lets applications without an async runtime fan out many resolutions while
+/// only as many run at the same time as there are threads. The workers share one
+/// [`CompiledResolver`], so they share its cache.
+///
+/// Dropping the service waits until every queued resolution is done. If resolving a specifier
+/// panics, its worker thread stops, and the result is an [`Error::IOError`].
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::{Resolver, ResolverService};
+/// use std::path::Path;
+///
+/// let service = ResolverService::new(Resolver::default(), 4);
+/// let pending: Vec<_> = specifiers
+///     .iter()
+///     .map(|specifier| service.submit(specifier, Path::new("/project/src")))
+///     .collect();
+/// for pending in pending {
+///     println!("{:?}", pending.wait());
+/// }
+/// ```
+pub struct ResolverService {
+    resolver: CompiledResolver,
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+/// A queued resolution.
+struct Job {
+    specifier: String,
+    basedir: PathBuf,
+    reply: Reply,
+}
+
+impl ResolverService {
+    /// Start a service with `threads` worker threads that resolve with `resolver`. There is
+    /// always at least one thread. The resolver is [compiled](Resolver::compile), so it gets a
+    /// bounded cache if it did not have one.
+    pub fn new(resolver: Resolver, threads: usize) -> Self {
+        let resolver = resolver.compile();
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..threads.max(1))
+            .map(|_| {
+                let resolver = resolver.clone();
+                let receiver = Arc::clone(&receiver);
+                thread::spawn(move || work(&resolver, &receiver))
+            })
+            .collect();
+
+        ResolverService {
+            resolver,
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    /// Queue the resolution of `specifier` from `basedir`.
+    pub fn submit(&self, specifier: &str, basedir: &Path) -> PendingResolution {
+        let shared = Arc::new(Shared::default());
+        let job = Job {
+            specifier: specifier.to_string(),
+            basedir: basedir.to_path_buf(),
+            reply: Reply(Some(Arc::clone(&shared))),
+        };
+        if let Some(sender) = &self.sender {
+            // If the workers are gone, dropping the job reports an error.
+            let _ = sender.send(job);
+        }
+        PendingResolution { shared }
+    }
+
+    /// Get the number of worker threads.
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Get the resolver that the workers use, for example to check its statistics or to clear
+    /// its cache.
+    pub fn resolver(&self) -> &CompiledResolver {
+        &self.resolver
+    }
+}
+
+impl Drop for ResolverService {
+    fn drop(&mut self) {
+        // Closing the channel stops the workers once they are idle.
+        self.sender = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Run queued jobs until the channel is closed.
+fn work(resolver: &CompiledResolver, receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = receiver.lock().unwrap_or_else(PoisonError::into_inner).recv();
+        let Job {
+            specifier,
+            basedir,
+            mut reply,
+        } = match job {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        reply.send(resolver.resolve(&specifier, &basedir));
+    }
+}
+
+/// The state that a [`PendingResolution`] shares with the worker that resolves it.
+#[derive(Default)]
+struct Shared {
+    slot: Mutex<Slot>,
+    done: Condvar,
+}
+
+#[derive(Default)]
+struct Slot {
+    result: Option<Result<PathBuf, Error>>,
+    waker: Option<Waker>,
+}
+
+/// Sends the result of a job. If a job is dropped before it is done, for example because
+/// resolving panicked or every worker is gone, an error is sent instead.
+struct Reply(Option<Arc<Shared>>);
+
+impl Reply {
+    fn send(&mut self, result: Result<PathBuf, Error>) {
+        let shared = match self.0.take() {
+            Some(shared) => shared,
+            None => return,
+        };
+        let mut slot = shared.slot.lock().unwrap_or_else(PoisonError::into_inner);
+        slot.result = Some(result);
+        if let Some(waker) = slot.waker.take() {
+            waker.wake();
+        }
+        shared.done.notify_all();
+    }
+}
+
+impl Drop for Reply {
+    fn drop(&mut self) {
+        self.send(Err(Error::IOError(IOError::new(
+            IOErrorKind::Interrupted,
+            "the resolver service could not finish the resolution",
+        ))));
+    }
+}
+
+/// The result of a resolution that was submitted to a [`ResolverService`].
+///
+/// Call [`PendingResolution::wait`] to block until it is done, or `.await` it.
+pub struct PendingResolution {
+    shared: Arc<Shared>,
+}
+
+impl PendingResolution {
+    /// Block the current thread until the resolution is done, and return its result.
+    pub fn wait(self) -> Result<PathBuf, Error> {
+        let mut slot = self.shared.slot.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            if let Some(result) = slot.result.take() {
+                return result;
+            }
+            slot = self
+                .shared
+                .done
+                .wait(slot)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Get the result if the resolution is done, without blocking.
+    pub fn try_wait(&mut self) -> Option<Result<PathBuf, Error>> {
+        self.shared
+            .slot
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .result
+            .take()
+    }
+}
+
+impl Future for PendingResolution {
+    type Output = Result<PathBuf, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.shared.slot.lock().unwrap_or_else(PoisonError::into_inner);
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::task::Wake;
+
+    /// Wakes a thread that is blocked in `block_on`.
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Poll a future on the current thread until it is done.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn resolves_on_worker_threads() {
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let basedir = fixtures.join("node-modules/package-json");
+        let dep = basedir.join("node_modules/dep/lib/index.js");
+        let service = ResolverService::new(Resolver::default(), 3);
+        assert_eq!(3, service.threads());
+
+        let pending: Vec<_> = (0..20).map(|_| service.submit("dep", &basedir)).collect();
+        for pending in pending {
+            assert_eq!(Ok(dep.clone()), pending.wait());
+        }
+        assert!(service.resolver().stats().cache_hits > 0);
+
+        assert_eq!(Ok(dep.clone()), block_on(service.submit("dep", &basedir)));
+        assert!(matches!(
+            service.submit("missing", &basedir).wait(),
+            Err(Error::NotFound { .. })
+        ));
+
+        let mut pending = service.submit("dep", &basedir);
+        drop(service);
+        assert_eq!(Some(Ok(dep)), pending.try_wait());
+        assert_eq!(None, pending.try_wait());
+
+        let service = ResolverService::new(Resolver::default(), 0);
+        assert_eq!(1, service.threads());
+    }
+}
@@ -0,0 +1,285 @@
+//! Resolution results that are kept between builds, and only redone when files change.
+
+use crate::{normalize, CompiledResolver, Context, Resolver, SharedResult};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// A `(specifier, basedir)` pair.
+type Request = (String, PathBuf);
+
+/// A result, and the paths whose existence or contents it depends on.
+struct Entry {
+    result: SharedResult,
+    dependencies: Vec<PathBuf>,
+}
+
+/// Keeps resolution results between builds, and forgets only the ones that depend on files
+/// that changed.
+///
+/// Every resolution records the paths it checked, including the ones that did not exist, like
+/// the watched paths of [`Resolver::resolve_full`]. Failed resolutions are kept too. When a
+/// watcher reports a change, call [`Session::mark_changed`] with the path that changed. The
+/// results that depend on it, or on a path inside it if it is a directory, are resolved again
+/// the next time they are requested, and the lookups of the changed paths are removed from
+/// the resolver's cache. Everything else is reused, which makes rebuilds in watch mode fast.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::{Resolver, Session};
+/// use std::path::Path;
+///
+/// let session = Session::new(Resolver::default());
+/// session.resolve("./utils", Path::new("/project/src"));
+/// // The watcher saw /project/src/utils.ts being created.
+/// session.mark_changed("/project/src/utils.ts");
+/// session.resolve("./utils", Path::new("/project/src"));
+/// ```
+pub struct Session {
+    resolver: CompiledResolver,
+    results: Mutex<HashMap<Request, Entry>>,
+    /// How often results were forgotten. Results that were computed while this changed may
+    /// be stale, so they are not kept.
+    generation: AtomicU64,
+}
+
+impl Session {
+    /// Create a session that resolves with `resolver`. The resolver is
+    /// [compiled](Resolver::compile), so it gets a bounded cache if it did not have one.
+    pub fn new(resolver: Resolver) -> Self {
+        Session {
+            resolver: resolver.compile(),
+            results: Mutex::default(),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Resolve `specifier` from `basedir`, or reuse the result from an earlier call if none of
+    /// the paths it depends on changed since. If a change is
+    /// [marked](Session::mark_changed) while resolving, the result is returned, but not kept.
+    pub fn resolve(&self, specifier: &str, basedir: &Path) -> SharedResult {
+        let key = (specifier.to_string(), basedir.to_path_buf());
+        if let Some(entry) = self.lock().get(&key) {
+            return Arc::clone(&entry.result);
+        }
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let mut ctx = Context::default();
+        let resolver = self.resolver.resolver().with_basedir(basedir.to_path_buf());
+        let result = resolver.resolve_with_context(specifier, &mut ctx);
+        let mut dependencies: Vec<PathBuf> =
+            ctx.checked.iter().map(|(path, _)| normalize(path)).collect();
+        if let Ok(path) = &result {
+            // The result may be a symlink target that was not checked itself.
+            dependencies.push(path.clone());
+        }
+        dependencies.sort();
+        dependencies.dedup();
+        let result = Arc::new(result.map(|path| resolver.output_path(path)));
+
+        let entry = Entry {
+            result: Arc::clone(&result),
+            dependencies,
+        };
+        let mut results = self.lock();
+        // The lock is not held while resolving, so files may have changed in the meantime.
+        if self.generation.load(Ordering::SeqCst) == generation {
+            results.insert(key, entry);
+        }
+        result
+    }
+
+    /// Record that the file or directory at `path` was created, removed, or changed. Results
+    /// that depend on it, or on paths inside it, are resolved again the next time.
+    ///
+    /// When a directory is created, mark the directory itself as changed, and not only the
+    /// files inside it, because results only depend on the directories they checked.
+    ///
+    /// With [case-insensitive](Resolver::case_insensitive) lookups, results may depend on the
+    /// path in a different case, so everything in its directory is resolved again.
+    pub fn mark_changed<P: AsRef<Path>>(&self, path: P) {
+        let mut path = normalize(path.as_ref());
+        if let Some(fs) = &self.resolver.resolver().case_insensitive_fs {
+            fs.forget(&path);
+            if let Some(dir) = path.parent() {
+                path = dir.to_path_buf();
+            }
+        }
+        if let Some(cache) = &self.resolver.resolver().cache {
+            cache.forget(&path);
+        }
+        self.resolver.resolver().compiled_exports.forget(&path);
+        let mut results = self.lock();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        results.retain(|_, entry| {
+            !entry
+                .dependencies
+                .iter()
+                .any(|dependency| dependency.starts_with(&path))
+        });
+    }
+
+    /// Get the number of results that are kept.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Check if no results are kept.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Forget every result and the whole cache, for example after a branch switch.
+    pub fn clear(&self) {
+        self.resolver.clear_cache();
+        let mut results = self.lock();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        results.clear();
+    }
+
+    /// Get the resolver that the session uses.
+    pub fn resolver(&self) -> &CompiledResolver {
+        &self.resolver
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Request, Entry>> {
+        self.results.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EntryKind, FileSystem, SnapshotFileSystem};
+    use std::io::Error as IOError;
+    use std::sync::{OnceLock, RwLock, Weak};
+
+    /// A filesystem whose files can be replaced between resolutions.
+    #[derive(Default)]
+    struct ChangingFileSystem(RwLock<SnapshotFileSystem>);
+
+    impl ChangingFileSystem {
+        fn set(&self, files: &[&str]) {
+            *self.0.write().unwrap() = files.iter().collect();
+        }
+    }
+
+    impl FileSystem for ChangingFileSystem {
+        fn entry_kind(&self, path: &Path) -> EntryKind {
+            self.0.read().unwrap().entry_kind(path)
+        }
+
+        fn read_to_string(&self, path: &Path) -> Result<String, IOError> {
+            self.0.read().unwrap().read_to_string(path)
+        }
+
+        fn canonicalize(&self, path: &Path) -> Result<PathBuf, IOError> {
+            self.0.read().unwrap().canonicalize(path)
+        }
+
+        fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, IOError> {
+            self.0.read().unwrap().read_dir(path)
+        }
+    }
+
+    #[test]
+    fn invalidates_dependent_results() {
+        let fs = Arc::new(ChangingFileSystem::default());
+        fs.set(&["/src/utils.json", "/src/other.js", "/src/node_modules/dep/index.js"]);
+        let session = Session::new(Resolver::default().filesystem(fs.clone()));
+        let src = Path::new("/src");
+
+        assert_eq!(Ok(src.join("utils.json")), *session.resolve("./utils", src));
+        assert_eq!(Ok(src.join("other.js")), *session.resolve("./other", src));
+        assert_eq!(Ok(src.join("node_modules/dep/index.js")), *session.resolve("dep", src));
+        assert!(session.resolve("./missing", src).is_err());
+        assert_eq!(4, session.len());
+        let other = session.resolve("./other", src);
+
+        // Creating a file with a preferred extension only affects the results that tried it.
+        fs.set(&[
+            "/src/utils.js",
+            "/src/utils.json",
+            "/src/other.js",
+            "/src/missing.js",
+            "/src/node_modules/dep/index.js",
+        ]);
+        assert_eq!(Ok(src.join("utils.json")), *session.resolve("./utils", src));
+        session.mark_changed("/src/utils.js");
+        assert_eq!(3, session.len());
+        assert_eq!(Ok(src.join("utils.js")), *session.resolve("./utils", src));
+        assert!(Arc::ptr_eq(&other, &session.resolve("./other", src)));
+
+        // Failed resolutions are redone when a candidate appears.
+        session.mark_changed("/src/missing.js");
+        assert_eq!(Ok(src.join("missing.js")), *session.resolve("./missing", src));
+
+        // Removing a directory affects the results that checked paths inside it.
+        fs.set(&["/src/utils.js", "/src/utils.json", "/src/other.js", "/src/missing.js"]);
+        session.mark_changed("/src/node_modules/");
+        assert!(session.resolve("dep", src).is_err());
+        assert!(Arc::ptr_eq(&other, &session.resolve("./other", src)));
+
+        session.clear();
+        assert!(session.is_empty());
+    }
+
+    /// A filesystem that reports a change to its session during the first lookup, like a
+    /// watcher that fires while a resolution is running.
+    #[derive(Default)]
+    struct RacingFileSystem {
+        files: SnapshotFileSystem,
+        session: OnceLock<Weak<Session>>,
+    }
+
+    impl FileSystem for RacingFileSystem {
+        fn entry_kind(&self, path: &Path) -> EntryKind {
+            if let Some(session) = self.session.get().and_then(Weak::upgrade) {
+                session.mark_changed("/src/utils.js");
+            }
+            self.files.entry_kind(path)
+        }
+
+        fn read_to_string(&self, path: &Path) -> Result<String, IOError> {
+            self.files.read_to_string(path)
+        }
+
+        fn canonicalize(&self, path: &Path) -> Result<PathBuf, IOError> {
+            self.files.canonicalize(path)
+        }
+
+        fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, IOError> {
+            self.files.read_dir(path)
+        }
+    }
+
+    #[test]
+    fn drops_results_of_racing_changes() {
+        let fs = Arc::new(RacingFileSystem {
+            files: ["/src/utils.js"].iter().collect(),
+            ..RacingFileSystem::default()
+        });
+        let session = Arc::new(Session::new(Resolver::default().filesystem(fs.clone())));
+        fs.session.set(Arc::downgrade(&session)).unwrap();
+        let src = Path::new("/src");
+
+        assert_eq!(Ok(src.join("utils.js")), *session.resolve("./utils", src));
+        assert!(session.is_empty());
+    }
+
+    #[test]
+    fn invalidates_case_insensitive_listings() {
+        let fs = Arc::new(ChangingFileSystem::default());
+        fs.set(&["/src/other.js"]);
+        let resolver = Resolver::default().filesystem(fs.clone()).case_insensitive(true);
+        let session = Session::new(resolver);
+        let src = Path::new("/src");
+        assert!(session.resolve("./utils", src).is_err());
+
+        fs.set(&["/src/other.js", "/src/Utils.js"]);
+        session.mark_changed("/src/Utils.js");
+        assert_eq!(Ok(src.join("Utils.js")), *session.resolve("./utils", src));
+    }
+}
@@ -0,0 +1,224 @@
+//! Split module specifiers into their parts, the same way the resolver does.
+
+use crate::{is_core_module, is_path_specifier, Error};
+
+/// The kind of a module specifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecifierKind {
+    /// A core module, like `"events"`.
+    Builtin,
+    /// A path relative to the basedir, like `"./lib"`, `"../index.js"`, or `"."`.
+    Relative,
+    /// An absolute path, like `"/src/index.js"`.
+    Absolute,
+    /// A specifier that is looked up in node_modules folders, like `"@scope/pkg/sub/path"`.
+    Bare,
+    /// A specifier that is mapped by the "imports" field of the current package, like
+    /// `"#utils"`.
+    SubpathImport,
+}
+
+/// A module specifier that was split into its parts by [`parse_specifier`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Specifier<'a> {
+    /// What kind of specifier this is.
+    pub kind: SpecifierKind,
+    /// The package name of a bare specifier, like `"@scope/pkg"`.
+    pub package_name: Option<&'a str>,
+    /// The scope of a scoped package name, like `"@scope"`.
+    pub scope: Option<&'a str>,
+    /// The path inside the package of a bare specifier, like `"sub/path"`. This is `None` if
+    /// the specifier refers to the package root.
+    pub subpath: Option<&'a str>,
+}
+
+/// Split a module specifier into its parts.
+///
+/// # Examples
+///
+/// ```rust
+/// use node_resolve::{parse_specifier, SpecifierKind};
+///
+/// let specifier = parse_specifier("@scope/pkg/sub/path");
+/// assert_eq!(specifier.kind, SpecifierKind::Bare);
+/// assert_eq!(specifier.package_name, Some("@scope/pkg"));
+/// assert_eq!(specifier.scope, Some("@scope"));
+/// assert_eq!(specifier.subpath, Some("sub/path"));
+/// ```
+pub fn parse_specifier(specifier: &str) -> Specifier<'_> {
+    let kind = if is_core_module(specifier) {
+        SpecifierKind::Builtin
+    } else if specifier.starts_with('/') {
+        SpecifierKind::Absolute
+    } else if is_path_specifier(specifier) {
+        SpecifierKind::Relative
+    } else if specifier.starts_with('#') {
+        SpecifierKind::SubpathImport
+    } else {
+        SpecifierKind::Bare
+    };
+
+    if kind != SpecifierKind::Bare {
+        return Specifier {
+            kind,
+            package_name: None,
+            scope: None,
+            subpath: None,
+        };
+    }
+
+    // Scoped package names contain one slash, so the subpath starts after the second one.
+    let name_end = if specifier.starts_with('@') {
+        specifier
+            .find('/')
+            .and_then(|scope_end| specifier[scope_end + 1..].find('/').map(|i| scope_end + 1 + i))
+    } else {
+        specifier.find('/')
+    };
+    let (package_name, subpath) = match name_end {
+        Some(end) => (&specifier[..end], Some(&specifier[end + 1..])),
+        None => (specifier, None),
+    };
+    let scope = if package_name.starts_with('@') {
+        package_name.split('/').next()
+    } else {
+        None
+    };
+
+    Specifier {
+        kind,
+        package_name: Some(package_name),
+        scope,
+        subpath,
+    }
+}
+
+/// Check that a specifier can be resolved at all, before touching the filesystem.
+///
+/// Package names are validated like Node.js does: they may not start with a dot or contain
+/// backslashes, scoped names need a name after the scope, and scopes may not contain uppercase
+/// letters. Like npm, only characters that don't need percent-encoding in URLs are allowed.
+pub(crate) fn validate_specifier(specifier: &str) -> Result<(), Error> {
+    let invalid = |reason| {
+        Err(Error::InvalidSpecifier {
+            specifier: specifier.to_string(),
+            reason,
+        })
+    };
+
+    if specifier.contains('\0') {
+        return invalid("specifier contains a null byte");
+    }
+
+    let package_name = match parse_specifier(specifier).package_name {
+        Some(package_name) => package_name,
+        None => return Ok(()),
+    };
+    let (scope, name) = match package_name.strip_prefix('@') {
+        Some(scoped) => match scoped.split_once('/') {
+            Some((scope, name)) => (Some(scope), name),
+            None => return invalid("scoped package name is missing a name after the scope"),
+        },
+        None => (None, package_name),
+    };
+
+    if let Some(scope) = scope {
+        if scope.is_empty() {
+            return invalid("package scope is empty");
+        }
+        if scope.chars().any(|c| c.is_ascii_uppercase()) {
+            return invalid("package scope contains uppercase letters");
+        }
+        if !scope.chars().all(is_url_safe) {
+            return invalid("package scope contains characters that must be percent-encoded");
+        }
+    }
+    if name.is_empty() {
+        return invalid("package name is empty");
+    }
+    if name.starts_with('.') {
+        return invalid("package name starts with a dot");
+    }
+    if !name.chars().all(is_url_safe) {
+        return invalid("package name contains characters that must be percent-encoded");
+    }
+
+    Ok(())
+}
+
+/// Check if a character is left alone by JavaScript's `encodeURIComponent`.
+fn is_url_safe(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-_.!~*'()".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_specifiers() {
+        assert_eq!(
+            Specifier {
+                kind: SpecifierKind::Bare,
+                package_name: Some("pkg"),
+                scope: None,
+                subpath: None,
+            },
+            parse_specifier("pkg")
+        );
+        assert_eq!(
+            Specifier {
+                kind: SpecifierKind::Bare,
+                package_name: Some("pkg"),
+                scope: None,
+                subpath: Some("lib/index.js"),
+            },
+            parse_specifier("pkg/lib/index.js")
+        );
+        assert_eq!(
+            Specifier {
+                kind: SpecifierKind::Bare,
+                package_name: Some("@scope/pkg"),
+                scope: Some("@scope"),
+                subpath: None,
+            },
+            parse_specifier("@scope/pkg")
+        );
+        assert_eq!(Some("sub"), parse_specifier("@scope/pkg/sub").subpath);
+        assert_eq!(SpecifierKind::Builtin, parse_specifier("events").kind);
+        assert_eq!(SpecifierKind::Relative, parse_specifier("./events").kind);
+        assert_eq!(SpecifierKind::Relative, parse_specifier("../events").kind);
+        assert_eq!(SpecifierKind::Relative, parse_specifier("..").kind);
+        assert_eq!(SpecifierKind::Absolute, parse_specifier("/events").kind);
+        assert_eq!(SpecifierKind::SubpathImport, parse_specifier("#events").kind);
+        assert_eq!(None, parse_specifier("/events").package_name);
+    }
+
+    #[test]
+    fn validates_package_names() {
+        assert!(validate_specifier("pkg").is_ok());
+        assert!(validate_specifier("JSONStream/index.js").is_ok());
+        assert!(validate_specifier("@scope/pkg/sub%20path").is_ok());
+        assert!(validate_specifier("./with%20percent").is_ok());
+
+        let reason = |specifier| match validate_specifier(specifier) {
+            Err(Error::InvalidSpecifier { reason, .. }) => reason,
+            other => panic!("expected an invalid specifier, got {:?}", other),
+        };
+        assert_eq!("package name starts with a dot", reason(".pkg"));
+        assert_eq!("package scope contains uppercase letters", reason("@Scope/pkg"));
+        assert_eq!(
+            "scoped package name is missing a name after the scope",
+            reason("@scope")
+        );
+        assert_eq!(
+            "package name contains characters that must be percent-encoded",
+            reason("pkg%20name/index.js")
+        );
+        assert_eq!(
+            "package name contains characters that must be percent-encoded",
+            reason("pkg\\index.js")
+        );
+        assert_eq!("specifier contains a null byte", reason("./index\0.js"));
+    }
+}
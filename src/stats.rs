@@ -0,0 +1,349 @@
+//! Counters that describe how much work a resolver has done.
+
+use crate::Resolver;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// The number of packages and resolutions listed in a [`CacheReport`].
+const REPORT_LEN: usize = 10;
+
+/// A snapshot of the work done by a resolver, returned by [`Resolver::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ResolverStats {
+    /// The number of `resolve()` calls, including failed ones.
+    pub resolutions: u64,
+    /// The number of times the resolver checked if a file or directory exists.
+    pub stat_calls: u64,
+    /// The number of package.json files that were read and parsed.
+    pub package_json_reads: u64,
+    /// The number of lookups that were answered by the cache.
+    pub cache_hits: u64,
+    /// The number of lookups that were not in the cache.
+    pub cache_misses: u64,
+    /// The number of entries the cache has evicted since it was created. This is not reset by
+    /// [`Resolver::reset_stats`].
+    pub cache_evictions: u64,
+    /// The total time spent inside `resolve()` calls.
+    pub time_spent: Duration,
+}
+
+/// How well one of the caches of a resolver works, in a [`CacheReport`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheUsage {
+    /// The number of lookups that were answered by the cache.
+    pub hits: u64,
+    /// The number of lookups that were not in the cache.
+    pub misses: u64,
+}
+
+impl CacheUsage {
+    /// Get the share of lookups that were answered by the cache, from 0 to 1. This is 0 if
+    /// there were no lookups.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// A resolution that took long, in a [`CacheReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowResolution {
+    /// The specifier that was resolved.
+    pub specifier: String,
+    /// The directory it was resolved from, if there was one.
+    pub basedir: Option<PathBuf>,
+    /// How long the resolution took.
+    pub duration: Duration,
+}
+
+/// A report on how well the caches of a resolver work, returned by
+/// [`Resolver::cache_report`]. This helps to pick cache capacities, and the packages to load
+/// ahead of time.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct CacheReport {
+    /// Lookups of what exists at a path.
+    pub entries: CacheUsage,
+    /// Lookups of parsed package.json files.
+    pub package_jsons: CacheUsage,
+    /// The number of entries the cache has evicted since it was created.
+    pub evictions: u64,
+    /// The package directories whose package.json files were needed most often, with the
+    /// number of times, most used first. This is only recorded with
+    /// [`Resolver::detailed_stats`].
+    pub hottest_packages: Vec<(PathBuf, u64)>,
+    /// The slowest resolutions, slowest first. This is only recorded with
+    /// [`Resolver::detailed_stats`].
+    pub slowest_resolutions: Vec<SlowResolution>,
+}
+
+/// The details that are only recorded with `Resolver::detailed_stats`.
+#[derive(Debug, Default)]
+struct Details {
+    package_uses: HashMap<PathBuf, u64>,
+    /// The slowest resolutions, slowest first.
+    slowest: Vec<SlowResolution>,
+}
+
+/// The live counters backing `ResolverStats`. These are shared by all resolvers that were
+/// derived from each other, and can be updated from multiple threads.
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    resolutions: AtomicU64,
+    stat_calls: AtomicU64,
+    package_json_reads: AtomicU64,
+    entry_hits: AtomicU64,
+    entry_misses: AtomicU64,
+    package_json_hits: AtomicU64,
+    package_json_misses: AtomicU64,
+    nanos: AtomicU64,
+    details: Mutex<Details>,
+}
+
+impl Counters {
+    pub(crate) fn count_stat(&self) {
+        self.stat_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_package_json_read(&self) {
+        self.package_json_reads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_entry_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.entry_hits
+        } else {
+            &self.entry_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_package_json_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.package_json_hits
+        } else {
+            &self.package_json_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn count_resolution(&self, elapsed: Duration) {
+        self.resolutions.fetch_add(1, Ordering::Relaxed);
+        self.nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Record that a resolution needed the package.json file of the package in `pkg_dir`.
+    pub(crate) fn count_package_use(&self, pkg_dir: &Path) {
+        let mut details = self.details.lock().unwrap_or_else(PoisonError::into_inner);
+        *details.package_uses.entry(pkg_dir.to_path_buf()).or_default() += 1;
+    }
+
+    /// Record a resolution if it is one of the slowest so far.
+    fn record_duration(&self, specifier: &str, basedir: Option<&Path>, duration: Duration) {
+        let mut details = self.details.lock().unwrap_or_else(PoisonError::into_inner);
+        let slowest = &mut details.slowest;
+        if slowest.len() == REPORT_LEN && slowest[REPORT_LEN - 1].duration >= duration {
+            return;
+        }
+        let index = slowest.partition_point(|slow| slow.duration >= duration);
+        slowest.insert(
+            index,
+            SlowResolution {
+                specifier: specifier.to_string(),
+                basedir: basedir.map(Path::to_path_buf),
+                duration,
+            },
+        );
+        slowest.truncate(REPORT_LEN);
+    }
+
+    fn usage(hits: &AtomicU64, misses: &AtomicU64) -> CacheUsage {
+        CacheUsage {
+            hits: hits.load(Ordering::Relaxed),
+            misses: misses.load(Ordering::Relaxed),
+        }
+    }
+
+    fn snapshot(&self, cache_evictions: u64) -> ResolverStats {
+        let entries = Counters::usage(&self.entry_hits, &self.entry_misses);
+        let package_jsons = Counters::usage(&self.package_json_hits, &self.package_json_misses);
+        ResolverStats {
+            resolutions: self.resolutions.load(Ordering::Relaxed),
+            stat_calls: self.stat_calls.load(Ordering::Relaxed),
+            package_json_reads: self.package_json_reads.load(Ordering::Relaxed),
+            cache_hits: entries.hits + package_jsons.hits,
+            cache_misses: entries.misses + package_jsons.misses,
+            cache_evictions,
+            time_spent: Duration::from_nanos(self.nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn report(&self, evictions: u64) -> CacheReport {
+        let details = self.details.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut hottest_packages: Vec<(PathBuf, u64)> = details
+            .package_uses
+            .iter()
+            .map(|(pkg_dir, uses)| (pkg_dir.clone(), *uses))
+            .collect();
+        hottest_packages.sort_by(|(a_dir, a), (b_dir, b)| b.cmp(a).then_with(|| a_dir.cmp(b_dir)));
+        hottest_packages.truncate(REPORT_LEN);
+        CacheReport {
+            entries: Counters::usage(&self.entry_hits, &self.entry_misses),
+            package_jsons: Counters::usage(&self.package_json_hits, &self.package_json_misses),
+            evictions,
+            hottest_packages,
+            slowest_resolutions: details.slowest.clone(),
+        }
+    }
+
+    fn reset(&self) {
+        self.resolutions.store(0, Ordering::Relaxed);
+        self.stat_calls.store(0, Ordering::Relaxed);
+        self.package_json_reads.store(0, Ordering::Relaxed);
+        self.entry_hits.store(0, Ordering::Relaxed);
+        self.entry_misses.store(0, Ordering::Relaxed);
+        self.package_json_hits.store(0, Ordering::Relaxed);
+        self.package_json_misses.store(0, Ordering::Relaxed);
+        self.nanos.store(0, Ordering::Relaxed);
+        let mut details = self.details.lock().unwrap_or_else(PoisonError::into_inner);
+        *details = Details::default();
+    }
+}
+
+impl Resolver {
+    /// Get statistics about the work this resolver has done so far.
+    ///
+    /// Resolvers that were derived from each other, for example using
+    /// [`with_basedir`](Resolver::with_basedir), share their statistics.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default().with_basedir(PathBuf::from("."));
+    /// resolver.resolve("./src/lib.rs");
+    /// println!("{} stat calls", resolver.stats().stat_calls);
+    /// ```
+    pub fn stats(&self) -> ResolverStats {
+        let cache_evictions = self.cache.as_ref().map_or(0, |cache| cache.evictions());
+        self.counters.snapshot(cache_evictions)
+    }
+
+    /// Get a report on how well the caches of this resolver work so far.
+    ///
+    /// Resolvers that were derived from each other share their report. The hottest packages
+    /// and the slowest resolutions are only recorded with [`Resolver::detailed_stats`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use node_resolve::Resolver;
+    ///
+    /// let resolver = Resolver::default()
+    ///     .cache_capacity(10_000)
+    ///     .detailed_stats(true)
+    ///     .with_basedir(PathBuf::from("."));
+    /// resolver.resolve("react");
+    /// let report = resolver.cache_report();
+    /// println!("{:.0}% of stat calls were cached", report.entries.hit_rate() * 100.0);
+    /// for (package, uses) in &report.hottest_packages {
+    ///     println!("{} was needed {} times", package.display(), uses);
+    /// }
+    /// ```
+    pub fn cache_report(&self) -> CacheReport {
+        let evictions = self.cache.as_ref().map_or(0, |cache| cache.evictions());
+        self.counters.report(evictions)
+    }
+
+    /// Set all statistics back to zero.
+    pub fn reset_stats(&self) {
+        self.counters.reset();
+    }
+
+    /// Run a resolution of `target` and record how long it took.
+    pub(crate) fn timed<T, F>(&self, target: &str, resolve: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let start = Instant::now();
+        let result = resolve();
+        let elapsed = start.elapsed();
+        self.counters.count_resolution(elapsed);
+        if self.detailed_stats {
+            self.counters
+                .record_duration(target, self.basedir.as_deref(), elapsed);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheReport, CacheUsage};
+    use crate::{MemoryCache, Resolver};
+    use std::env;
+    use std::sync::Arc;
+
+    #[test]
+    fn counts_work() {
+        let fixtures = env::current_dir().unwrap().join("fixtures");
+        let resolver = Resolver::default().with_basedir(fixtures);
+
+        resolver.resolve("./package-json/main-file").unwrap();
+        let stats = resolver.stats();
+        assert_eq!(1, stats.resolutions);
+        assert_eq!(1, stats.package_json_reads);
+        // ./package-json/main-file{,.js,.json,.node}, the directory, its package.json, and the
+        // main file.
+        assert_eq!(7, stats.stat_calls);
+
+        resolver.with_basedir(".".into()).resolve("events").unwrap();
+        assert_eq!(2, resolver.stats().resolutions);
+
+        resolver.reset_stats();
+        assert_eq!(0, resolver.stats().resolutions);
+    }
+
+    #[test]
+    fn reports_cache_usage() {
+        let basedir = env::current_dir().unwrap().join("fixtures/node-modules/package-json");
+        let resolver = Resolver::default()
+            .cache(Arc::new(MemoryCache::default()))
+            .with_basedir(basedir.clone());
+        resolver.resolve("dep").unwrap();
+        resolver.resolve("dep").unwrap();
+        let report = resolver.cache_report();
+        assert!(report.entries.hits > 0);
+        assert!(report.package_jsons.hits > 0);
+        let hit_rate = report.package_jsons.hit_rate();
+        assert!(hit_rate > 0.0 && hit_rate < 1.0);
+        assert!(report.hottest_packages.is_empty());
+        assert!(report.slowest_resolutions.is_empty());
+
+        let detailed = resolver.detailed_stats(true);
+        for target in &["dep", "dep", "./node_modules/dep/lib"] {
+            detailed.resolve(target).unwrap();
+        }
+        let report = detailed.cache_report();
+        assert_eq!(1, report.hottest_packages.len());
+        assert_eq!(basedir.join("node_modules/dep"), report.hottest_packages[0].0);
+        assert_eq!(3, report.slowest_resolutions.len());
+        let durations: Vec<_> = report.slowest_resolutions.iter().map(|s| s.duration).collect();
+        assert!(durations.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert_eq!(Some(basedir.as_path()), report.slowest_resolutions[0].basedir.as_deref());
+
+        detailed.reset_stats();
+        assert_eq!(CacheReport::default(), detailed.cache_report());
+        assert_eq!(0.0, CacheUsage::default().hit_rate());
+    }
+}
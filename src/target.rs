@@ -0,0 +1,150 @@
+//! Environments that resolved modules will run in, which set several options at once.
+
+use crate::Runtime;
+
+/// The conditions that identify an environment. [`Target`]s replace these, and keep other
+/// conditions like `"import"` or `"development"`.
+static ENVIRONMENT_CONDITIONS: &[&str] = &["node", "browser", "worker", "deno"];
+
+/// The environment that resolved modules will run in. See [`Resolver::target`].
+///
+/// [`Resolver::target`]: crate::Resolver::target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Browsers, through a bundler.
+    ///
+    /// - It resolves .js, .mjs, .json, and .wasm files, in that order;
+    /// - It uses the package.json "browser", "module", and "main" fields, in that order;
+    /// - It uses the "browser" condition in package.json "exports" fields;
+    /// - Node.js builtins are not available, so specifiers like `events` are looked up in
+    ///   node_modules folders.
+    Browser,
+    /// Node.js.
+    ///
+    /// - It resolves .js, .json, and .node files, in that order;
+    /// - It uses the package.json "main" field;
+    /// - It uses the "node" condition in package.json "exports" fields;
+    /// - Node.js builtins are available.
+    Node,
+    /// Web workers, through a bundler. This is like [`Target::Browser`], but also uses the
+    /// "worker" condition, which wins if a package lists it before "browser".
+    Worker,
+    /// Deno.
+    ///
+    /// - It resolves .ts, .tsx, .mts, .js, .jsx, .mjs, and .json files, in that order;
+    /// - It uses the package.json "main" field;
+    /// - It uses the "deno" and "node" conditions in package.json "exports" fields;
+    /// - Node.js builtins are only available with the `node:` prefix.
+    Deno,
+}
+
+impl Target {
+    /// Get the extensions to try, in order.
+    pub(crate) fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Target::Browser | Target::Worker => &[".js", ".mjs", ".json", ".wasm"],
+            Target::Node => &[".js", ".json", ".node"],
+            Target::Deno => &[".ts", ".tsx", ".mts", ".js", ".jsx", ".mjs", ".json"],
+        }
+    }
+
+    /// Get the package.json fields that point to the entry point, in order.
+    pub(crate) fn main_fields(self) -> &'static [&'static str] {
+        match self {
+            Target::Browser | Target::Worker => &["browser", "module", "main"],
+            Target::Node | Target::Deno => &["main"],
+        }
+    }
+
+    /// Get the runtime that determines which specifiers are builtin modules.
+    pub(crate) fn runtime(self) -> Runtime {
+        match self {
+            Target::Browser | Target::Worker => Runtime::Browser,
+            Target::Node => Runtime::Node,
+            Target::Deno => Runtime::Deno,
+        }
+    }
+
+    /// Replace the environment conditions in `conditions` with the ones for this target.
+    pub(crate) fn conditions(self, conditions: &[String]) -> Vec<String> {
+        let environment: &[&str] = match self {
+            Target::Browser => &["browser"],
+            Target::Node => &["node"],
+            Target::Worker => &["worker", "browser"],
+            Target::Deno => &["deno", "node"],
+        };
+        environment
+            .iter()
+            .map(|condition| condition.to_string())
+            .chain(
+                conditions
+                    .iter()
+                    .filter(|condition| !ENVIRONMENT_CONDITIONS.contains(&condition.as_str()))
+                    .cloned(),
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resolver;
+    use std::env;
+
+    #[test]
+    fn configures_targets() {
+        let root = env::current_dir().unwrap().join("fixtures/target");
+        let resolve = |resolver: Resolver, target: &str| {
+            resolver.with_basedir(root.clone()).resolve(target)
+        };
+
+        assert_eq!(
+            Ok(root.join("node_modules/env/node.js")),
+            resolve(Resolver::default().target(Target::Node), "env")
+        );
+        assert_eq!(
+            Ok(root.join("node_modules/env/browser.js")),
+            resolve(Resolver::default().target(Target::Browser), "env")
+        );
+        assert_eq!(
+            Ok(root.join("node_modules/env/worker.mjs")),
+            resolve(Resolver::node_esm().target(Target::Worker), "env")
+        );
+        assert_eq!(
+            Ok(root.join("node_modules/env/deno.ts")),
+            resolve(Resolver::default().target(Target::Deno), "env")
+        );
+        assert_eq!(
+            Ok(root.join("node_modules/legacy/browser.js")),
+            resolve(Resolver::default().target(Target::Browser), "legacy")
+        );
+        assert_eq!(
+            Ok(root.join("node_modules/legacy/main.js")),
+            resolve(Resolver::webpack_web().target(Target::Node), "legacy")
+        );
+
+        // Builtins are looked up in node_modules folders in browsers.
+        assert_eq!(
+            Ok(root.join("node_modules/events/index.js")),
+            resolve(Resolver::default().target(Target::Browser), "events")
+        );
+        assert_eq!(
+            Ok("events".into()),
+            resolve(Resolver::default().target(Target::Node), "events")
+        );
+        assert_eq!(
+            Ok(root.join("node_modules/events/index.js")),
+            resolve(Resolver::default().target(Target::Deno), "events")
+        );
+        assert_eq!(
+            Ok("node:events".into()),
+            resolve(Resolver::default().target(Target::Deno), "node:events")
+        );
+
+        assert_eq!(
+            vec!["worker", "browser", "import"],
+            Target::Worker.conditions(&[String::from("node"), String::from("import")])
+        );
+    }
+}
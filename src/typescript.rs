@@ -0,0 +1,125 @@
+//! TypeScript-specific resolution, matching the `moduleResolution` modes of `tsc`.
+
+use crate::{parse_specifier, Context, Resolver};
+use std::path::{Path, PathBuf};
+
+impl Resolver {
+    /// Find the TypeScript source or declaration file that compiles to the JavaScript file at
+    /// `path`, like `tsc` does for specifiers that end in `.js`, `.mjs`, or `.cjs`. Returns
+    /// `None` if this is disabled, `path` is not a JavaScript file, or there is no such file.
+    pub(crate) fn try_typescript_file(&self, path: &Path, ctx: &mut Context) -> Option<PathBuf> {
+        if !self.typescript_extensions {
+            return None;
+        }
+        let extension = path.extension()?.to_str()?;
+        let replacements: &[&str] = match extension {
+            "js" | "jsx" => &["ts", "tsx", "d.ts"],
+            "mjs" => &["mts", "d.mts"],
+            "cjs" => &["cts", "d.cts"],
+            _ => return None,
+        };
+        replacements
+            .iter()
+            .map(|replacement| path.with_extension(replacement))
+            .find(|candidate| self.try_file(candidate, ctx))
+    }
+
+    /// Resolve a bare specifier in the DefinitelyTyped package for it, like
+    /// `@types/scope__pkg/sub` for `@scope/pkg/sub`. Returns `None` if this is disabled, or if
+    /// there is no declaration file for the specifier.
+    pub(crate) fn resolve_types_package(&self, target: &str, ctx: &mut Context) -> Option<PathBuf> {
+        if !self.types_packages {
+            return None;
+        }
+        let specifier = parse_specifier(target);
+        let package_name = specifier.package_name?;
+        if package_name.starts_with("@types/") {
+            return None;
+        }
+        let types_name = match package_name.strip_prefix('@') {
+            Some(scoped) => scoped.replacen('/', "__", 1),
+            None => package_name.to_string(),
+        };
+        let types_target = match specifier.subpath {
+            Some(subpath) => format!("@types/{}/{}", types_name, subpath),
+            None => format!("@types/{}", types_name),
+        };
+        self.resolve_node_modules(&types_target, ctx)
+            .ok()
+            .filter(|path| is_typescript_file(path))
+    }
+}
+
+/// Check if a path refers to a TypeScript source or declaration file.
+pub(crate) fn is_typescript_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| matches!(extension, "ts" | "tsx" | "mts" | "cts"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Resolver;
+    use std::env;
+    use std::path::PathBuf;
+
+    fn fixture(part: &str) -> PathBuf {
+        env::current_dir().unwrap().join("fixtures/typescript").join(part)
+    }
+
+    #[test]
+    fn resolves_like_tsc() {
+        let import = Resolver::ts_node16_import().with_basedir(fixture("src"));
+        assert_eq!(
+            fixture("node_modules/dual/dist/index.d.mts"),
+            import.resolve("dual").unwrap()
+        );
+        assert_eq!(
+            fixture("node_modules/dual/dist/utils.d.ts"),
+            import.resolve("dual/utils").unwrap()
+        );
+        assert_eq!(fixture("src/helper.ts"), import.resolve("./helper.js").unwrap());
+        assert!(import.resolve("./helper").is_err());
+
+        let require = Resolver::ts_node16_require().with_basedir(fixture("src"));
+        assert_eq!(
+            fixture("node_modules/dual/dist/index.d.cts"),
+            require.resolve("dual").unwrap()
+        );
+        assert_eq!(fixture("src/helper.ts"), require.resolve("./helper").unwrap());
+        assert_eq!(fixture("src/helper.ts"), require.resolve("./helper.js").unwrap());
+
+        let bundler = Resolver::ts_bundler().with_basedir(fixture("src"));
+        assert_eq!(
+            fixture("node_modules/dual/dist/index.d.mts"),
+            bundler.resolve("dual").unwrap()
+        );
+        assert_eq!(fixture("src/helper.ts"), bundler.resolve("./helper").unwrap());
+        assert_eq!(fixture("src/helper.ts"), bundler.resolve("./helper.js").unwrap());
+        assert!(bundler.resolve("dual/dist/index.mjs").is_err());
+
+        // Packages without typings use the DefinitelyTyped package.
+        assert_eq!(
+            fixture("node_modules/@types/untyped/index.d.ts"),
+            bundler.resolve("untyped").unwrap()
+        );
+        assert_eq!(
+            fixture("node_modules/@types/untyped/sub.d.ts"),
+            bundler.resolve("untyped/sub").unwrap()
+        );
+        assert_eq!(
+            fixture("node_modules/@types/scope__lib/index.d.ts"),
+            bundler.resolve("@scope/lib").unwrap()
+        );
+        assert!(bundler.resolve("@types/missing").is_err());
+
+        // Without the "types" condition, the JavaScript files are used.
+        assert_eq!(
+            fixture("node_modules/dual/dist/index.mjs"),
+            Resolver::node_esm()
+                .with_basedir(fixture("src"))
+                .resolve("dual")
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Extended-length paths on Windows.
+//!
+//! Windows APIs reject paths longer than `MAX_PATH` characters, unless they start with the
+//! `\\?\` prefix. Deeply nested node_modules folders easily go over that limit. Such verbatim
+//! paths are passed to the filesystem as they are, so they must be absolute and normalized.
+//! `canonicalize()` also returns verbatim paths, which many JavaScript tools do not accept, so
+//! the prefix is removed from results again.
+//!
+//! Paths on network shares, like `\\server\share\project`, have their own verbatim form,
+//! `\\?\UNC\server\share\project`. They are converted the same way, so paths on a share
+//! round-trip, and the share root stays the top of the path.
+//!
+//! The conversions work on strings, so they can be tested on every platform.
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+/// The length of the longest path that Windows APIs accept without the `\\?\` prefix,
+/// including the terminating NUL character.
+const MAX_PATH: usize = 260;
+
+/// The prefix of verbatim paths.
+const VERBATIM_PREFIX: &str = r"\\?\";
+
+/// The prefix of verbatim paths on network shares.
+const VERBATIM_UNC_PREFIX: &str = r"\\?\UNC\";
+
+/// Add the `\\?\` prefix to an absolute path with a drive letter, or the `\\?\UNC\` prefix to
+/// a path on a network share, if it is too long for the Windows APIs. Returns `None` if the
+/// path does not need it or can not have it.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn extended_length(path: &str) -> Option<String> {
+    if path.len() < MAX_PATH || path.starts_with(VERBATIM_PREFIX) || path.starts_with(r"\\.\") {
+        return None;
+    }
+    if let Some((server, share, rest)) = unc_share(path) {
+        return Some(format!(
+            "{}{}\\{}\\{}",
+            VERBATIM_UNC_PREFIX,
+            server,
+            share,
+            normalize_segments(rest)
+        ));
+    }
+    let drive = drive_prefix(path)?;
+    Some(format!(
+        "{}{}\\{}",
+        VERBATIM_PREFIX,
+        drive,
+        normalize_segments(&path[drive.len()..])
+    ))
+}
+
+/// Remove the `\\?\` prefix from a path with a drive letter, or turn a `\\?\UNC\` path back
+/// into a `\\server\share` path. Returns `None` if the path does not have a prefix, or if it
+/// means something else without it.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn strip_verbatim(path: &str) -> Option<String> {
+    if let Some(unc) = path.strip_prefix(VERBATIM_UNC_PREFIX) {
+        let unc = format!(r"\\{}", unc);
+        let (_, _, rest) = unc_share(&unc).filter(|_| !unc.contains('/'))?;
+        return if is_plain(rest) { Some(unc) } else { None };
+    }
+    let rest = path.strip_prefix(VERBATIM_PREFIX)?;
+    let drive = drive_prefix(rest)?;
+    if is_plain(&rest[drive.len()..]) {
+        Some(rest.to_string())
+    } else {
+        None
+    }
+}
+
+/// Check that the segments of a verbatim path mean the same without the prefix. Verbatim
+/// paths may contain `/` or `.` segments, which would be interpreted without it.
+fn is_plain(segments: &str) -> bool {
+    !segments.contains('/') && !segments.split('\\').any(|name| name == "." || name == "..")
+}
+
+/// Split a path on a network share, like `\\server\share\project`, into the server, the share
+/// and the rest of the path. Returns `None` for other paths, including verbatim paths.
+fn unc_share(path: &str) -> Option<(&str, &str, &str)> {
+    let is_separator = |c: char| c == '\\' || c == '/';
+    let mut chars = path.chars();
+    if !chars.next().is_some_and(is_separator) || !chars.next().is_some_and(is_separator) {
+        return None;
+    }
+    let mut parts = path[2..].splitn(3, is_separator);
+    let server = parts.next().filter(|server| !server.is_empty() && *server != "?")?;
+    let share = parts.next().filter(|share| !share.is_empty())?;
+    Some((server, share, parts.next().unwrap_or("")))
+}
+
+/// Get the drive letter and colon at the start of an absolute path, like `C:`.
+fn drive_prefix(path: &str) -> Option<&str> {
+    let bytes = path.as_bytes();
+    let is_absolute = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/');
+    if is_absolute {
+        Some(&path[..2])
+    } else {
+        None
+    }
+}
+
+/// Join the segments of a path with `\`, and remove `.` and `..` segments, because the
+/// filesystem does not do that for verbatim paths.
+fn normalize_segments(path: &str) -> String {
+    let mut segments: Vec<&str> = vec![];
+    for segment in path.split(['\\', '/']) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("\\")
+}
+
+/// Get a path that the Windows APIs accept even if it is long. Other platforms do not have a
+/// length limit, so the path is returned as it is.
+#[cfg(windows)]
+pub(crate) fn long_path(path: &Path) -> Cow<'_, Path> {
+    match path.to_str().and_then(extended_length) {
+        Some(extended) => Cow::Owned(PathBuf::from(extended)),
+        None => Cow::Borrowed(path),
+    }
+}
+
+/// Get a path that the Windows APIs accept even if it is long. Other platforms do not have a
+/// length limit, so the path is returned as it is.
+#[cfg(not(windows))]
+pub(crate) fn long_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+/// Remove the `\\?\` prefix that `canonicalize()` adds on Windows, if the path means the same
+/// without it.
+#[cfg(windows)]
+pub(crate) fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+    match path.to_str().and_then(strip_verbatim) {
+        Some(stripped) => PathBuf::from(stripped),
+        None => path,
+    }
+}
+
+/// Remove the `\\?\` prefix that `canonicalize()` adds on Windows, if the path means the same
+/// without it.
+#[cfg(not(windows))]
+pub(crate) fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_long_paths() {
+        let short = r"C:\project\node_modules\dep\index.js";
+        assert_eq!(None, extended_length(short));
+
+        let nested = "node_modules\\dep\\".repeat(20);
+        let long = format!(r"C:\project\{}index.js", nested);
+        assert_eq!(Some(format!(r"\\?\{}", long)), extended_length(&long));
+        let mixed = format!(r"C:/project/./src/..\{}index.js", nested.replace('\\', "/"));
+        assert_eq!(Some(format!(r"\\?\{}", long)), extended_length(&mixed));
+        // Relative paths can not be verbatim, and verbatim paths are kept.
+        assert_eq!(None, extended_length(&format!(r"project\{}", nested)));
+        assert_eq!(None, extended_length(&format!(r"\\?\{}", long)));
+
+        assert_eq!(Some(short.to_string()), strip_verbatim(&format!(r"\\?\{}", short)));
+        assert_eq!(Some(long.clone()), strip_verbatim(&format!(r"\\?\{}", long)));
+        assert_eq!(None, strip_verbatim(short));
+        assert_eq!(None, strip_verbatim(r"\\?\C:\project\..\secret"));
+        assert_eq!(None, strip_verbatim(r"\\?\Volume{b75e2c83-0000-0000-0000-602f00000000}\a"));
+    }
+
+    #[test]
+    fn converts_share_paths() {
+        let share = r"\\server\share\project\index.js";
+        assert_eq!(None, extended_length(share));
+        let verbatim_share = r"\\?\UNC\server\share\project\index.js";
+        assert_eq!(Some(share.to_string()), strip_verbatim(verbatim_share));
+
+        let nested = "node_modules\\dep\\".repeat(20);
+        let long = format!(r"\\server\share\{}index.js", nested);
+        let verbatim = format!(r"\\?\UNC\server\share\{}index.js", nested);
+        assert_eq!(Some(verbatim.clone()), extended_length(&long));
+        assert_eq!(Some(long.clone()), strip_verbatim(&verbatim));
+        // `..` segments do not leave the share.
+        let escaping = format!(r"//server/share/../../{}index.js", nested.replace('\\', "/"));
+        assert_eq!(Some(verbatim), extended_length(&escaping));
+
+        assert_eq!(Some(r"\\server\share".to_string()), strip_verbatim(r"\\?\UNC\server\share"));
+        assert_eq!(None, strip_verbatim(r"\\?\UNC\server"));
+        assert_eq!(None, strip_verbatim(r"\\?\UNC\server\share\..\other"));
+        assert_eq!(None, unc_share(r"\\?\UNC\server\share"));
+        assert_eq!(None, unc_share(r"\\server"));
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn stops_ascent_at_share_root() {
+        let paths = crate::Resolver::default().node_modules_paths(Path::new(r"\\server\share\app"));
+        assert_eq!(
+            vec![
+                PathBuf::from(r"\\server\share\app\node_modules"),
+                PathBuf::from(r"\\server\share\node_modules"),
+            ],
+            paths
+        );
+    }
+}